@@ -1,4 +1,27 @@
+pub mod asn;
+pub mod autohostlist;
+pub mod batch;
+pub mod conflicts;
+pub mod dns;
+pub mod engine;
+pub mod errors;
+pub mod exclusions;
+pub mod forwarder;
+pub mod health;
+pub mod hostlist;
 pub mod hosts;
+pub mod hostsbackup;
+pub mod hostsfile;
+pub mod hostshealth;
+pub mod hostsplatform;
+pub mod ipset;
+pub mod lookup;
+pub mod netwatch;
+pub mod network;
+pub mod poison;
 pub mod proxies;
+pub mod rules;
+pub mod subscriptions;
 pub mod tor;
+pub mod watchdog;
 pub mod zapret;
@@ -0,0 +1,275 @@
+use crate::bypass::ipset::{self, Cidr, Ipset};
+use crate::bypass::zapret::Zapret;
+use crate::utils::*;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+const DATASET_FILE: &str = "asn/prefixes.txt";
+// по шапке узнаём свои файлы, чужие ipset-ы не перезаписываем
+const HEADER_PREFIX: &str = "# generated by zust from ";
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DatasetInfo {
+    pub prefixes: usize,
+    pub skipped: usize,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AsnIpsetReport {
+    pub file_name: String,
+    pub matched: usize,
+    pub written: usize,
+}
+
+pub struct Asn;
+
+impl Asn {
+    // поддерживаются:
+    //   CAIDA pfx2as:  "1.0.0.0\t24\t13335" (multi-origin "13335_4826", AS-set "1,2" или "{1,2}")
+    //   prefix + ASN:  "1.0.0.0/24 13335" или CSV "1.0.0.0/24,AS13335"
+    //   iptoasn:       "1.0.0.0\t1.0.0.255\t13335\tUS\tCLOUDFLARENET"
+    pub fn parse_line(line: &str) -> Option<(Vec<Cidr>, Vec<u32>)> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (cidrs, asn_col) = match Self::split_csv(line) {
+            Some((prefix, asn)) => (vec![prefix.parse().ok()?], asn),
+            None => Self::split_columns(line)?,
+        };
+        let asns = Self::parse_origins(asn_col);
+        // у iptoasn 0 означает "не анонсируется"
+        (!asns.is_empty() && asns != [0]).then_some((cidrs, asns))
+    }
+
+    // "1.0.0.0/24,AS13335[,...]": запятая - разделитель колонок только в этой форме,
+    // в остальных она внутри AS-set
+    fn split_csv(line: &str) -> Option<(&str, &str)> {
+        let (prefix, rest) = line.split_once(',')?;
+        let prefix = prefix.trim();
+        if !prefix.contains('/') || prefix.contains(char::is_whitespace) {
+            return None;
+        }
+        let rest = rest.trim_start();
+        let asn = match rest.strip_prefix('{') {
+            Some(set) => &set[..set.find('}')?],
+            None => rest.split(',').next()?,
+        };
+        Some((prefix, asn.trim()))
+    }
+
+    // колонки через пробелы и табы
+    fn split_columns(line: &str) -> Option<(Vec<Cidr>, &str)> {
+        let cols: Vec<&str> = line.split_whitespace().collect();
+        match cols.as_slice() {
+            [prefix, asn, ..] if prefix.contains('/') => Some((vec![prefix.parse().ok()?], *asn)),
+            [start, end, asn, ..] if end.parse::<IpAddr>().is_ok() => {
+                let cidrs = ipset::range_cidrs(start.parse().ok()?, end.parse().ok()?).ok()?;
+                Some((cidrs, *asn))
+            }
+            [addr, len, asn, ..] => {
+                let cidr = Cidr::new(addr.parse().ok()?, len.parse().ok()?).ok()?;
+                Some((vec![cidr], *asn))
+            }
+            _ => None,
+        }
+    }
+
+    // "13335", "AS13335", "13335_4826" (multi-origin), "{1,2}" и "1,2" (AS-set)
+    fn parse_origins(col: &str) -> Vec<u32> {
+        col.trim_matches(['{', '}'])
+            .split([',', '_'])
+            .filter_map(|a| {
+                let a = a.trim();
+                let a = a.strip_prefix("AS").or(a.strip_prefix("as")).unwrap_or(a);
+                a.parse().ok()
+            })
+            .collect()
+    }
+
+    // "AS13335", "as 13335", "13335"
+    pub fn parse_asn(input: &str) -> Result<u32, String> {
+        input
+            .trim()
+            .to_uppercase()
+            .trim_start_matches("AS")
+            .trim()
+            .parse()
+            .map_err(|_| format!("некорректный ASN: {}", input))
+    }
+
+    fn dataset_path(app: &AppHandle) -> PathBuf {
+        Zapret::zapret_path(app, DATASET_FILE)
+    }
+
+    fn scan(path: &Path, mut f: impl FnMut(Vec<Cidr>, Vec<u32>)) -> Result<usize, String> {
+        let file = fs::File::open(path).map_err(|e| format!("датасет не найден: {}", e))?;
+        let mut skipped = 0;
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|e| e.to_string())?;
+            match Self::parse_line(&line) {
+                Some((cidrs, asns)) => f(cidrs, asns),
+                None if !line.trim().is_empty() && !line.trim_start().starts_with('#') => {
+                    skipped += 1
+                }
+                None => {}
+            }
+        }
+        Ok(skipped)
+    }
+
+    pub fn dataset_info(app: &AppHandle) -> Result<DatasetInfo, String> {
+        let mut prefixes = 0;
+        let skipped = Self::scan(&Self::dataset_path(app), |cidrs, _| prefixes += cidrs.len())?;
+        Ok(DatasetInfo { prefixes, skipped })
+    }
+
+    // копируем дамп к себе, чтобы генерация работала офлайн
+    pub fn import_dataset(app: &AppHandle, source: &str) -> Result<DatasetInfo, String> {
+        let mut prefixes = 0;
+        let skipped = Self::scan(Path::new(source), |cidrs, _| prefixes += cidrs.len())?;
+        if prefixes == 0 {
+            return Err("в файле нет ни одного префикса".to_string());
+        }
+        let target = Self::dataset_path(app);
+        if let Some(dir) = target.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        fs::copy(source, &target).map_err(|e| format!("ошибка копирования: {}", e))?;
+        info(
+            app,
+            &format!("asn датасет: {} префиксов, пропущено {}", prefixes, skipped),
+        );
+        Ok(DatasetInfo { prefixes, skipped })
+    }
+
+    pub fn generate_ipset(
+        app: &AppHandle,
+        asns: Vec<String>,
+        file_name: &str,
+    ) -> Result<AsnIpsetReport, String> {
+        check_list_name(file_name)?;
+        let wanted: HashSet<u32> = asns
+            .iter()
+            .map(|a| Self::parse_asn(a))
+            .collect::<Result<_, _>>()?;
+        if wanted.is_empty() {
+            return Err("не указан ни один ASN".to_string());
+        }
+        let path = Zapret::zapret_path(app, "ipset-configs").join(file_name);
+        if let Ok(existing) = fs::read_to_string(&path)
+            && !existing.starts_with(HEADER_PREFIX)
+        {
+            return Err(format!(
+                "{} уже есть и создан не из ASN, выберите другое имя",
+                file_name
+            ));
+        }
+
+        let mut matched = Vec::new();
+        Self::scan(&Self::dataset_path(app), |cidrs, origins| {
+            if origins.iter().any(|a| wanted.contains(a)) {
+                matched.extend(cidrs);
+            }
+        })?;
+        if matched.is_empty() {
+            return Err("в датасете нет префиксов для этих ASN".to_string());
+        }
+
+        let count = matched.len();
+        let set = Ipset::from_cidrs(matched);
+        let mut sorted: Vec<u32> = wanted.into_iter().collect();
+        sorted.sort_unstable();
+        let header = format!(
+            "{}{}\n",
+            HEADER_PREFIX,
+            sorted
+                .iter()
+                .map(|a| format!("AS{}", a))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        let cidrs = set.cidrs();
+        write_atomic(&path, &format!("{}{}", header, set.to_text()))?;
+        info(
+            app,
+            &format!(
+                "ipset {} из ASN: {} префиксов -> {}",
+                file_name,
+                count,
+                cidrs.len()
+            ),
+        );
+        Ok(AsnIpsetReport {
+            file_name: file_name.to_string(),
+            matched: count,
+            written: cidrs.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(line: &str) -> Option<(Vec<String>, Vec<u32>)> {
+        Asn::parse_line(line)
+            .map(|(cidrs, asns)| (cidrs.iter().map(|c| c.to_string()).collect(), asns))
+    }
+
+    fn one(cidr: &str, asns: &[u32]) -> Option<(Vec<String>, Vec<u32>)> {
+        Some((vec![cidr.to_string()], asns.to_vec()))
+    }
+
+    #[test]
+    fn caida_pfx2as() {
+        assert_eq!(parsed("1.0.0.0\t24\t13335"), one("1.0.0.0/24", &[13335]));
+        assert_eq!(
+            parsed("1.0.4.0\t22\t38803_56203"),
+            one("1.0.4.0/22", &[38803, 56203])
+        );
+    }
+
+    #[test]
+    fn caida_as_set() {
+        assert_eq!(parsed("2.0.0.0\t16\t1,2"), one("2.0.0.0/16", &[1, 2]));
+        assert_eq!(parsed("2.0.0.0\t16\t{1,2}"), one("2.0.0.0/16", &[1, 2]));
+    }
+
+    #[test]
+    fn prefix_and_asn() {
+        assert_eq!(parsed("1.0.0.0/24 13335"), one("1.0.0.0/24", &[13335]));
+        assert_eq!(parsed("1.0.0.0/24\tAS13335"), one("1.0.0.0/24", &[13335]));
+    }
+
+    #[test]
+    fn csv_prefix_and_asn() {
+        assert_eq!(parsed("1.0.0.0/24,AS13335"), one("1.0.0.0/24", &[13335]));
+        assert_eq!(parsed("1.0.0.0/24, 13335,US"), one("1.0.0.0/24", &[13335]));
+        assert_eq!(parsed("1.0.0.0/24,{1,2},US"), one("1.0.0.0/24", &[1, 2]));
+    }
+
+    #[test]
+    fn iptoasn_range() {
+        assert_eq!(
+            parsed("1.0.0.0\t1.0.0.255\t13335\tUS\tCLOUDFLARENET"),
+            one("1.0.0.0/24", &[13335])
+        );
+        assert_eq!(parsed("1.0.1.0\t1.0.3.255\t0\tNone\tNot routed"), None);
+    }
+
+    #[test]
+    fn junk_is_skipped() {
+        assert_eq!(parsed("# comment"), None);
+        assert_eq!(parsed("prefix,asn"), None);
+        assert_eq!(parsed("1.0.0.0/24"), None);
+        assert_eq!(parsed("1.0.0.0/24,AS"), None);
+    }
+}
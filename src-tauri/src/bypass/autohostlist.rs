@@ -0,0 +1,345 @@
+use crate::bypass::engine::Engine;
+use crate::bypass::hostlist::{Hostlist, HostlistReport};
+use crate::bypass::zapret::Zapret;
+use crate::settings;
+use crate::utils::*;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use tauri::AppHandle;
+
+// имя не должно попадать под "list-*.txt", иначе fallback подключит его как обычный хостлист
+pub const AUTO_LIST: &str = "autohostlist.txt";
+const AUTO_LOG: &str = "autohostlist.log";
+// так файлы назывались раньше
+const LEGACY_LIST: &str = "list-auto.txt";
+const LEGACY_LOG: &str = "list-auto.log";
+// формат даты в --hostlist-auto-debug
+const LOG_DATE: &str = "%d.%m.%Y %H:%M:%S";
+// фильтры, которые ограничивают профиль списком хостов или адресов
+const SCOPE_ARGS: &[&str] = &[
+    "--hostlist=",
+    "--hostlist-domains=",
+    "--ipset=",
+    "--ipset-ip=",
+];
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FilterProfile {
+    // как считает winws: с 1
+    pub index: usize,
+    pub filter: String,
+    // без хостлиста или ipset-а авто-хостлист в профиль не добавляется
+    pub scoped: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LearnedDomain {
+    pub domain: String,
+    pub added_at: Option<DateTime<Local>>,
+    pub profile: Option<usize>,
+}
+
+// профили выбираются для каждой стратегии: номер профиля в другой стратегии значит другое.
+// раньше был общий список без стратегии, такой выбор не переносим
+pub fn by_strategy<'de, D: Deserializer<'de>>(
+    d: D,
+) -> Result<HashMap<String, Vec<usize>>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Stored {
+        Legacy(Vec<usize>),
+        ByStrategy(HashMap<String, Vec<usize>>),
+    }
+    Ok(match Stored::deserialize(d)? {
+        Stored::Legacy(_) => HashMap::new(),
+        Stored::ByStrategy(map) => map,
+    })
+}
+
+pub struct AutoHostlist;
+
+impl AutoHostlist {
+    // профили в аргументах разделены отдельным токеном --new
+    pub fn split_profiles(args: &str) -> Vec<String> {
+        let mut profiles = vec![String::new()];
+        for token in args.split_inclusive(char::is_whitespace) {
+            if token.trim() == "--new" {
+                profiles.push(String::new());
+            } else if let Some(last) = profiles.last_mut() {
+                last.push_str(token);
+            }
+        }
+        profiles.iter().map(|p| p.trim().to_string()).collect()
+    }
+
+    pub fn profiles(raw: &str) -> Vec<FilterProfile> {
+        Self::split_profiles(raw)
+            .iter()
+            .enumerate()
+            .map(|(i, profile)| FilterProfile {
+                index: i + 1,
+                filter: profile
+                    .split_whitespace()
+                    .filter(|t| t.starts_with("--filter-") || t.starts_with("--wf-"))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                scoped: Self::is_scoped(profile),
+            })
+            .collect()
+    }
+
+    // профиль без --hostlist/--ipset работает на весь трафик порта, и
+    // --hostlist-auto в нём тихо расширил бы обход на всё подряд.
+    // %IPSET% в сырой стратегии превращается в --ipset при запуске
+    pub fn is_scoped(profile: &str) -> bool {
+        profile
+            .split_whitespace()
+            .any(|t| SCOPE_ARGS.iter().any(|arg| t.starts_with(arg)) || t.contains("%IPSET%"))
+    }
+
+    // добавляет --hostlist-auto в выбранные профили и общий debug-лог для таймстемпов
+    pub fn inject(args: &str, selected: &[usize], list: &Path, log: &Path) -> String {
+        let mut profiles = Self::split_profiles(args);
+        let mut injected = false;
+        for (i, profile) in profiles.iter_mut().enumerate() {
+            // winws разрешает только один auto-хостлист на профиль
+            if !selected.contains(&(i + 1))
+                || profile.contains("--hostlist-auto=")
+                || !Self::is_scoped(profile)
+            {
+                continue;
+            }
+            profile.push_str(&format!(" --hostlist-auto=\"{}\"", list.display()));
+            injected = true;
+        }
+        let joined = profiles.join(" --new ");
+        if injected {
+            format!("--hostlist-auto-debug=\"{}\" {}", log.display(), joined)
+        } else {
+            joined
+        }
+    }
+
+    // "19.10.2026 12:34:56 : example.com : profile 3 : client ... : adding to C:\...\list-auto.txt"
+    pub fn parse_log(content: &str) -> HashMap<String, (Option<DateTime<Local>>, Option<usize>)> {
+        let mut out = HashMap::new();
+        for line in content.lines().filter(|l| l.contains(": adding to ")) {
+            let parts: Vec<&str> = line.split(" : ").map(str::trim).collect();
+            let [date, domain, profile, ..] = parts.as_slice() else {
+                continue;
+            };
+            let added_at = NaiveDateTime::parse_from_str(date, LOG_DATE)
+                .ok()
+                .and_then(|d| Local.from_local_datetime(&d).single());
+            let profile = profile
+                .strip_prefix("profile ")
+                .and_then(|p| p.parse().ok());
+            out.insert(domain.to_lowercase(), (added_at, profile));
+        }
+        out
+    }
+
+    // старые list-auto.* переименовываем, пока их не подхватил fallback
+    fn migrate(app: &AppHandle, lists_dir: &Path) {
+        for (old, new) in [(LEGACY_LIST, AUTO_LIST), (LEGACY_LOG, AUTO_LOG)] {
+            let (old, new) = (lists_dir.join(old), lists_dir.join(new));
+            if old.exists()
+                && !new.exists()
+                && let Err(e) = fs::rename(&old, &new)
+            {
+                info(app, &format!("auto hostlist: {} err: {}", old.display(), e));
+            }
+        }
+    }
+
+    pub fn args(app: &AppHandle, strategy: &str, args: &str) -> String {
+        let lists_dir = Zapret::zapret_path(app, "lists");
+        Self::migrate(app, &lists_dir);
+        let selected = settings::load_settings()
+            .auto_hostlist_profiles
+            .remove(strategy)
+            .unwrap_or_default();
+        if selected.is_empty() {
+            return args.to_string();
+        }
+        let list = lists_dir.join(AUTO_LIST);
+        if !list.exists() {
+            let _ = fs::write(&list, "");
+        }
+        info(
+            app,
+            &format!("auto hostlist {}: профили {:?}", strategy, selected),
+        );
+        Self::inject(args, &selected, &list, &lists_dir.join(AUTO_LOG))
+    }
+
+    pub fn strategy_profiles(app: &AppHandle, name: &str) -> Result<Vec<FilterProfile>, String> {
+        let raw = fs::read_to_string(Zapret::zapret_path(app, "strategies").join(name))
+            .map_err(|e| format!("{}: {}", name, e))?;
+        Ok(Self::profiles(&raw))
+    }
+
+    pub fn get_profiles(strategy: &str) -> Vec<usize> {
+        settings::load_settings()
+            .auto_hostlist_profiles
+            .remove(strategy)
+            .unwrap_or_default()
+    }
+
+    pub fn set_profiles(
+        app: &AppHandle,
+        strategy: &str,
+        profiles: Vec<usize>,
+    ) -> Result<(), String> {
+        let available = Self::strategy_profiles(app, strategy)?;
+        if let Some(bad) = profiles.iter().find(|&&p| p == 0 || p > available.len()) {
+            return Err(format!("в {} нет профиля {}", strategy, bad));
+        }
+        if let Some(bad) = profiles.iter().find(|&&p| !available[p - 1].scoped) {
+            return Err(format!(
+                "профиль {} без хостлиста и ipset, авто-хостлист расширил бы его на весь трафик",
+                bad
+            ));
+        }
+        let mut current = settings::load_settings();
+        if profiles.is_empty() {
+            current.auto_hostlist_profiles.remove(strategy);
+        } else {
+            current
+                .auto_hostlist_profiles
+                .insert(strategy.to_string(), profiles);
+        }
+        settings::save_settings(current)?;
+        // перезапуск нужен, только если меняли выбор для запущенной стратегии
+        if Zapret::get_strategy() == strategy {
+            Engine::exclusive(|| Zapret::restart_current(app)).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    pub fn learned(app: &AppHandle) -> Vec<LearnedDomain> {
+        let lists_dir = Zapret::zapret_path(app, "lists");
+        let content = fs::read_to_string(lists_dir.join(AUTO_LIST)).unwrap_or_default();
+        let log =
+            Self::parse_log(&fs::read_to_string(lists_dir.join(AUTO_LOG)).unwrap_or_default());
+        let mut learned: Vec<LearnedDomain> = Hostlist::domains(&content)
+            .into_iter()
+            .map(|domain| {
+                let (added_at, profile) = log.get(&domain).cloned().unwrap_or_default();
+                LearnedDomain {
+                    domain,
+                    added_at,
+                    profile,
+                }
+            })
+            .collect();
+        // свежие сверху
+        learned.sort_by(|a, b| b.added_at.cmp(&a.added_at));
+        learned
+    }
+
+    // переносит домены в постоянный хостлист и убирает из авто
+    pub fn promote(
+        app: &AppHandle,
+        domains: Vec<String>,
+        target: &str,
+    ) -> Result<HostlistReport, String> {
+        if target == AUTO_LIST {
+            return Err("нужно выбрать другой список".to_string());
+        }
+        let report = Hostlist::add(app, target, domains.clone())?;
+        Hostlist::remove(app, AUTO_LIST, domains)?;
+        Ok(report)
+    }
+
+    pub fn discard(app: &AppHandle, domains: Vec<String>) -> Result<Vec<String>, String> {
+        Hostlist::remove(app, AUTO_LIST, domains)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARGS: &str = "--wf-tcp=80,443 --filter-udp=443 --hostlist=\"list-general.txt\" --dpi-desync=fake --new \
+                        --filter-tcp=443 --ipset=\"ipset-all.txt\" --dpi-desync=split2 --new \
+                        --filter-udp=50000-50100 --dpi-desync=fake";
+
+    fn inject(args: &str, selected: &[usize]) -> String {
+        AutoHostlist::inject(args, selected, Path::new("auto.txt"), Path::new("auto.log"))
+    }
+
+    #[test]
+    fn split_on_new_token_only() {
+        let profiles = AutoHostlist::split_profiles(ARGS);
+        assert_eq!(profiles.len(), 3);
+        assert!(profiles[0].starts_with("--wf-tcp=80,443"));
+        assert!(profiles[1].starts_with("--filter-tcp=443"));
+        assert_eq!(profiles[2], "--filter-udp=50000-50100 --dpi-desync=fake");
+        // --new-что-то не разделитель
+        assert_eq!(AutoHostlist::split_profiles("--a --newline --b").len(), 1);
+        assert_eq!(AutoHostlist::split_profiles(""), vec![String::new()]);
+    }
+
+    #[test]
+    fn profiles_report_filters_and_scope() {
+        let profiles = AutoHostlist::profiles(ARGS);
+        assert_eq!(profiles[0].filter, "--wf-tcp=80,443 --filter-udp=443");
+        assert!(profiles[0].scoped);
+        assert!(profiles[1].scoped);
+        assert!(!profiles[2].scoped);
+        assert!(AutoHostlist::is_scoped(
+            "--filter-tcp=443 %IPSET% --dpi-desync=fake"
+        ));
+        assert!(!AutoHostlist::is_scoped(
+            "--filter-tcp=443 --hostlist-exclude=\"x.txt\""
+        ));
+    }
+
+    #[test]
+    fn inject_adds_auto_list_once() {
+        let once = inject(ARGS, &[1, 2]);
+        let profiles = AutoHostlist::split_profiles(&once);
+        assert!(profiles[0].starts_with("--hostlist-auto-debug=\"auto.log\" --wf-tcp"));
+        assert!(profiles[0].ends_with("--hostlist-auto=\"auto.txt\""));
+        assert!(profiles[1].ends_with("--hostlist-auto=\"auto.txt\""));
+        assert!(!profiles[2].contains("--hostlist-auto"));
+        // повторный вызов ничего не дописывает
+        assert_eq!(
+            inject(&once, &[1, 2]).matches("--hostlist-auto=").count(),
+            2
+        );
+    }
+
+    #[test]
+    fn inject_skips_unscoped_and_unselected() {
+        assert_eq!(
+            inject(ARGS, &[3]),
+            ARGS.split_whitespace().collect::<Vec<_>>().join(" ")
+        );
+        assert_eq!(inject(ARGS, &[]), inject(ARGS, &[3]));
+        assert_eq!(inject(ARGS, &[9]), inject(ARGS, &[]));
+    }
+
+    #[test]
+    fn parse_log_takes_adding_lines() {
+        let log = "19.10.2026 12:34:56 : Example.com : profile 3 : client 1.2.3.4:5000 : proto tls : fail counter 3/3\n\
+                   19.10.2026 12:34:57 : Example.com : profile 3 : client 1.2.3.4:5000 : adding to C:\\zapret\\autohostlist.txt\n\
+                   bad line : adding to x\n\
+                   garbage : other.com : profile x : adding to y\n";
+        let parsed = AutoHostlist::parse_log(log);
+        assert_eq!(parsed.len(), 2);
+        let (added_at, profile) = parsed["example.com"];
+        assert_eq!(profile, Some(3));
+        assert_eq!(
+            added_at.map(|d| d.format(LOG_DATE).to_string()).as_deref(),
+            Some("19.10.2026 12:34:57")
+        );
+        assert_eq!(parsed["other.com"], (None, None));
+    }
+}
@@ -0,0 +1,313 @@
+use crate::bypass::dns::{Dns, ResolverPreset};
+use crate::bypass::hostlist::Hostlist;
+use crate::bypass::ipset::{self, Cidr};
+use crate::bypass::zapret::Zapret;
+use crate::settings;
+use crate::utils::*;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::net::IpAddr;
+use std::sync::Arc;
+use tauri::AppHandle;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+const HEADER_PREFIX: &str = "# zust: resolved from ";
+// сколько доменов резолвим одновременно
+const PARALLEL: usize = 16;
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SourcedCidr {
+    pub cidr: Cidr,
+    pub domain: String,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchReport {
+    pub domains: usize,
+    pub failed: Vec<String>,
+    pub added: Vec<SourcedCidr>,
+    pub removed: Vec<SourcedCidr>,
+    pub total: usize,
+    pub written: bool,
+    // файл, с которым сравнивали и куда пишем
+    pub target: String,
+    // выбранный ipset создан не zust, подсети ушли в target
+    pub redirected_from: Option<String>,
+}
+
+pub struct BatchResolve;
+
+impl BatchResolve {
+    // файл состоит из групп "# domain" + подсети этого домена
+    pub fn parse_groups(content: &str) -> Vec<SourcedCidr> {
+        let mut domain = String::new();
+        let mut out = Vec::new();
+        for line in content.lines().map(str::trim) {
+            if line.starts_with(HEADER_PREFIX) {
+                continue;
+            }
+            if let Some(comment) = line.strip_prefix('#') {
+                domain = comment.trim().to_string();
+                continue;
+            }
+            for cidr in ipset::parse_line(line).unwrap_or_default() {
+                out.push(SourcedCidr {
+                    cidr,
+                    domain: domain.clone(),
+                });
+            }
+        }
+        out
+    }
+
+    // свой файл узнаём по шапке, пустой или отсутствующий тоже можно писать
+    fn is_generated(content: &str) -> bool {
+        content.trim().is_empty() || content.starts_with(HEADER_PREFIX)
+    }
+
+    // куда писать вместо чужого ipset-а: "ipset-my.txt" -> "ipset-my-resolved.txt"
+    fn fallback_name(ipset_name: &str) -> String {
+        format!("{}-resolved.txt", ipset_name.trim_end_matches(".txt"))
+    }
+
+    // ipset, собранный руками, не перезаписываем: результат уходит в соседний файл.
+    // read отдаёт содержимое файла по имени, пустую строку если его нет
+    fn target(ipset_name: &str, read: impl Fn(&str) -> String) -> Result<String, String> {
+        if Self::is_generated(&read(ipset_name)) {
+            return Ok(ipset_name.to_string());
+        }
+        let fallback = Self::fallback_name(ipset_name);
+        if !Self::is_generated(&read(&fallback)) {
+            return Err(format!(
+                "{} и {} созданы не zust, выберите другое имя",
+                ipset_name, fallback
+            ));
+        }
+        Ok(fallback)
+    }
+
+    pub fn render(list_name: &str, groups: &BTreeMap<String, Vec<Cidr>>) -> String {
+        let mut out = format!("{}{}\n", HEADER_PREFIX, list_name);
+        for (domain, cidrs) in groups {
+            out.push_str(&format!("# {}\n", domain));
+            for cidr in cidrs {
+                out.push_str(&format!("{}\n", cidr));
+            }
+        }
+        out
+    }
+
+    async fn resolve_all(
+        domains: Vec<String>,
+        presets: &[ResolverPreset],
+    ) -> Result<(BTreeMap<String, Vec<IpAddr>>, Vec<String>), String> {
+        let resolvers = presets
+            .iter()
+            .map(|p| Dns::resolver(*p))
+            .collect::<Result<Vec<_>, _>>()?;
+        let limit = Arc::new(Semaphore::new(PARALLEL));
+        let mut tasks = JoinSet::new();
+        for domain in domains {
+            let resolvers = resolvers.clone();
+            let limit = limit.clone();
+            tasks.spawn(async move {
+                let _permit = limit.acquire_owned().await;
+                let mut ips = Vec::new();
+                for resolver in &resolvers {
+                    if let Ok(found) = Dns::lookup_all(resolver, &domain).await {
+                        ips.extend(found);
+                    }
+                }
+                (domain, ips)
+            });
+        }
+
+        let mut resolved = BTreeMap::new();
+        let mut failed = Vec::new();
+        while let Some(res) = tasks.join_next().await {
+            let (domain, ips) = res.map_err(|e| e.to_string())?;
+            if ips.is_empty() {
+                failed.push(domain);
+            } else {
+                resolved.insert(domain, ips);
+            }
+        }
+        failed.sort();
+        Ok((resolved, failed))
+    }
+
+    // diff_only - ничего не пишем, только показываем что изменится
+    pub async fn run(
+        app: &AppHandle,
+        list_name: &str,
+        ipset_name: &str,
+        presets: Vec<ResolverPreset>,
+        diff_only: bool,
+    ) -> Result<BatchReport, String> {
+        check_list_name(ipset_name)?;
+        let dir = Zapret::zapret_path(app, "ipset-configs");
+        let target = Self::target(ipset_name, |name| {
+            fs::read_to_string(dir.join(name)).unwrap_or_default()
+        })?;
+        let list_path = Hostlist::list_path(app, list_name)?;
+        let content = fs::read_to_string(&list_path).map_err(|e| e.to_string())?;
+        let domains: Vec<String> = Hostlist::domains(&content)
+            .iter()
+            .filter_map(|d| Hostlist::normalize(d).ok())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let presets = if presets.is_empty() {
+            vec![ResolverPreset::Configured]
+        } else {
+            presets
+        };
+        info(
+            app,
+            &format!("batch resolve {}: {} доменов", list_name, domains.len()),
+        );
+
+        let domain_count = domains.len();
+        let (resolved, failed) = Self::resolve_all(domains, &presets).await?;
+
+        let settings = settings::load_settings();
+        let mut seen = HashSet::new();
+        let mut groups: BTreeMap<String, Vec<Cidr>> = BTreeMap::new();
+        for (domain, ips) in resolved {
+            for ip in ips {
+                let prefix = if ip.is_ipv4() {
+                    settings.ipset_prefix_v4
+                } else {
+                    settings.ipset_prefix_v6
+                };
+                let cidr = Cidr::new(ip, prefix)?;
+                // подсеть достаётся первому домену, остальным не дублируем
+                if seen.insert(cidr) {
+                    groups.entry(domain.clone()).or_default().push(cidr);
+                }
+            }
+        }
+        groups.values_mut().for_each(|c| c.sort());
+
+        let path = dir.join(&target);
+        let old = Self::parse_groups(&fs::read_to_string(&path).unwrap_or_default());
+        let old_set: HashSet<Cidr> = old.iter().map(|s| s.cidr).collect();
+        let mut report = BatchReport {
+            domains: domain_count,
+            failed,
+            total: seen.len(),
+            redirected_from: (target != ipset_name).then(|| ipset_name.to_string()),
+            target: target.clone(),
+            ..Default::default()
+        };
+        for (domain, cidrs) in &groups {
+            for cidr in cidrs.iter().filter(|c| !old_set.contains(c)) {
+                report.added.push(SourcedCidr {
+                    cidr: *cidr,
+                    domain: domain.clone(),
+                });
+            }
+        }
+        report.removed = old
+            .into_iter()
+            .filter(|s| !seen.contains(&s.cidr))
+            .collect();
+
+        if !diff_only && !groups.is_empty() {
+            write_atomic(&path, &Self::render(list_name, &groups))?;
+            report.written = true;
+            info(
+                app,
+                &format!(
+                    "ipset {}: +{} -{}",
+                    target,
+                    report.added.len(),
+                    report.removed.len()
+                ),
+            );
+            if target != ipset_name {
+                info(app, &format!("{} создан не zust и не тронут", ipset_name));
+            }
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_own_files_are_rewritten() {
+        assert!(BatchResolve::is_generated(""));
+        assert!(BatchResolve::is_generated(
+            "# zust: resolved from list-x.txt\n1.2.3.0/24\n"
+        ));
+        assert!(!BatchResolve::is_generated(
+            "# my discord ips\n1.2.3.0/24\n"
+        ));
+        assert!(!BatchResolve::is_generated("1.2.3.4\n"));
+    }
+
+    #[test]
+    fn fallback_keeps_txt_extension() {
+        assert_eq!(
+            BatchResolve::fallback_name("ipset-my.txt"),
+            "ipset-my-resolved.txt"
+        );
+    }
+
+    #[test]
+    fn foreign_ipset_redirects_to_fallback() {
+        let files = |own: &'static str, fallback: &'static str| {
+            move |name: &str| match name {
+                "ipset-my.txt" => own.to_string(),
+                "ipset-my-resolved.txt" => fallback.to_string(),
+                _ => String::new(),
+            }
+        };
+        let header = "# zust: resolved from list-x.txt\n1.2.3.0/24\n";
+        assert_eq!(
+            BatchResolve::target("ipset-my.txt", files(header, "")).unwrap(),
+            "ipset-my.txt"
+        );
+        assert_eq!(
+            BatchResolve::target("ipset-my.txt", files("1.2.3.4\n", "")).unwrap(),
+            "ipset-my-resolved.txt"
+        );
+        assert_eq!(
+            BatchResolve::target("ipset-my.txt", files("1.2.3.4\n", header)).unwrap(),
+            "ipset-my-resolved.txt"
+        );
+        assert!(BatchResolve::target("ipset-my.txt", files("1.2.3.4\n", "5.6.7.8\n")).is_err());
+    }
+
+    #[test]
+    fn render_and_parse_round_trip() {
+        let mut groups = BTreeMap::new();
+        groups.insert(
+            "discord.com".to_string(),
+            vec!["162.159.128.0/24".parse().unwrap()],
+        );
+        groups.insert(
+            "x.com".to_string(),
+            vec!["104.244.42.0/24".parse().unwrap()],
+        );
+        let text = BatchResolve::render("list-x.txt", &groups);
+        let parsed: Vec<(String, String)> = BatchResolve::parse_groups(&text)
+            .into_iter()
+            .map(|s| (s.domain, s.cidr.to_string()))
+            .collect();
+        assert_eq!(
+            parsed,
+            [
+                ("discord.com".to_string(), "162.159.128.0/24".to_string()),
+                ("x.com".to_string(), "104.244.42.0/24".to_string())
+            ]
+        );
+    }
+}
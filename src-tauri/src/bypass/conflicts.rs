@@ -0,0 +1,362 @@
+use crate::bypass::zapret::Zapret;
+use crate::utils::*;
+use crate::{sh, sh_output};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::AppHandle;
+#[cfg(windows)]
+use winreg::RegKey;
+#[cfg(windows)]
+use winreg::enums::*;
+
+#[cfg(windows)]
+const SERVICES_PATH: &str = r"SYSTEM\CurrentControlSet\Services";
+
+// процессы других обходов DPI
+const FOREIGN_TOOLS: &[(&str, &str)] = &[
+    ("goodbyedpi.exe", "GoodbyeDPI"),
+    ("ciadpi.exe", "ByeDPI"),
+    ("byedpi.exe", "ByeDPI"),
+    ("spoofdpi.exe", "SpoofDPI"),
+    ("tpws.exe", "zapret tpws"),
+    ("nfqws.exe", "zapret nfqws"),
+];
+// движок zust, чужие копии тоже ловим
+const ENGINE_PROCESS: &str = "winws.exe";
+// сервисы, которые ставят другие обходы
+const FOREIGN_SERVICES: &[&str] = &["GoodbyeDPI", "byedpi", "SpoofDPI", "discordfix_zapret"];
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictKind {
+    ForeignTool,
+    ForeignEngine,
+    ForeignService,
+    ForeignDriver,
+    StaleDriver,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum ConflictFix {
+    KillProcess { pid: u32, name: String },
+    DeleteService { name: String },
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Conflict {
+    pub kind: ConflictKind,
+    pub name: String,
+    pub detail: String,
+    pub fix: ConflictFix,
+}
+
+// службы читаются только из реестра Windows
+#[cfg_attr(not(windows), allow(dead_code))]
+struct ServiceInfo {
+    name: String,
+    image_path: String,
+}
+
+pub struct Conflicts;
+
+impl Conflicts {
+    pub fn scan(app: &AppHandle) -> Vec<Conflict> {
+        let mut processes = Vec::new();
+        let _ = process_list::for_each_process(|pid: u32, path: &Path| {
+            let exe = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            processes.push((pid, exe));
+        });
+        let found = Self::matches(
+            &processes,
+            Self::services(),
+            Self::service_pid("zapret"),
+            &Zapret::zapret_path(app, "bin"),
+        );
+        info(app, &format!("conflicts found: {}", found.len()));
+        found
+    }
+
+    // без системы: процессы (pid, имя exe в нижнем регистре) и службы уже собраны
+    fn matches(
+        processes: &[(u32, String)],
+        services: Vec<ServiceInfo>,
+        own_pid: Option<u32>,
+        own_bin: &Path,
+    ) -> Vec<Conflict> {
+        let mut found = Vec::new();
+        for (pid, exe) in processes.iter().cloned() {
+            if exe == ENGINE_PROCESS && Some(pid) != own_pid {
+                found.push(Conflict {
+                    kind: ConflictKind::ForeignEngine,
+                    name: exe.clone(),
+                    detail: format!("pid {}, запущен не сервисом zust", pid),
+                    fix: ConflictFix::KillProcess { pid, name: exe },
+                });
+            } else if let Some((_, tool)) = FOREIGN_TOOLS.iter().find(|(p, _)| *p == exe) {
+                found.push(Conflict {
+                    kind: ConflictKind::ForeignTool,
+                    name: tool.to_string(),
+                    detail: format!("процесс {} (pid {})", exe, pid),
+                    fix: ConflictFix::KillProcess { pid, name: exe },
+                });
+            }
+        }
+
+        let zapret_running = own_pid.is_some();
+        for svc in services {
+            let image = svc.image_path.to_lowercase();
+            if FOREIGN_SERVICES
+                .iter()
+                .any(|s| s.eq_ignore_ascii_case(&svc.name))
+            {
+                found.push(Conflict {
+                    kind: ConflictKind::ForeignService,
+                    name: svc.name.clone(),
+                    detail: svc.image_path.clone(),
+                    fix: ConflictFix::DeleteService { name: svc.name },
+                });
+            } else if svc.name.eq_ignore_ascii_case("zapret") && !Self::is_own(&image, own_bin) {
+                found.push(Conflict {
+                    kind: ConflictKind::ForeignService,
+                    name: svc.name.clone(),
+                    detail: format!("сервис zapret от другой сборки: {}", svc.image_path),
+                    fix: ConflictFix::DeleteService { name: svc.name },
+                });
+            } else if image.contains("windivert") {
+                let (kind, detail) = if !Self::is_own(&image, own_bin) {
+                    (
+                        ConflictKind::ForeignDriver,
+                        format!("драйвер из чужой папки: {}", svc.image_path),
+                    )
+                } else if !zapret_running {
+                    (
+                        ConflictKind::StaleDriver,
+                        "драйвер остался после остановки".to_string(),
+                    )
+                } else {
+                    continue;
+                };
+                found.push(Conflict {
+                    kind,
+                    name: svc.name.clone(),
+                    detail,
+                    fix: ConflictFix::DeleteService { name: svc.name },
+                });
+            }
+        }
+        found
+    }
+
+    // делит запрошенное на то, что есть в скане, и устаревшее
+    fn accept(found: &[Conflict], fixes: Vec<ConflictFix>) -> (Vec<ConflictFix>, Vec<ConflictFix>) {
+        fixes
+            .into_iter()
+            .partition(|f| found.iter().any(|c| &c.fix == f))
+    }
+
+    // фронту не верим: убиваем и удаляем только то, что нашёл свежий скан
+    pub fn fix(app: &AppHandle, fixes: Vec<ConflictFix>) -> Result<(), String> {
+        let (fixes, rejected) = Self::accept(&Self::scan(app), fixes);
+        for fix in &rejected {
+            info(
+                app,
+                &format!("conflict fix отклонён, нет в скане: {:?}", fix),
+            );
+        }
+        if !rejected.is_empty() && fixes.is_empty() {
+            return Err("конфликты уже изменились, пересканируйте".to_string());
+        }
+        let mut failed = Vec::new();
+        for fix in fixes {
+            match &fix {
+                ConflictFix::KillProcess { pid, name } => {
+                    info(app, &format!("завершаю {} ({})", name, pid));
+                    let pid = pid.to_string();
+                    let ok = sh!("taskkill", "/F", "/PID", &pid, "/T").is_ok_and(|s| s.success());
+                    if !ok {
+                        failed.push(name.clone());
+                    }
+                }
+                ConflictFix::DeleteService { name } => {
+                    info(app, &format!("удаляю сервис {}", name));
+                    let _ = sh!("net", "stop", name);
+                    let ok = sh!("sc", "delete", name).is_ok_and(|s| s.success());
+                    if !ok {
+                        failed.push(name.clone());
+                    }
+                }
+            }
+        }
+        if !rejected.is_empty() {
+            failed.push(format!("{} устаревших", rejected.len()));
+        }
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("не удалось убрать: {}", failed.join(", ")))
+        }
+    }
+
+    fn is_own(image: &str, own_bin: &Path) -> bool {
+        let own = own_bin.to_string_lossy().to_lowercase();
+        image.contains(own.trim_end_matches('\\'))
+    }
+
+    fn service_pid(name: &str) -> Option<u32> {
+        let out = sh_output!("sc", "queryex", name).ok()?;
+        String::from_utf8_lossy(&out.stdout)
+            .lines()
+            .find(|l| l.trim_start().starts_with("PID"))
+            .and_then(|l| l.split(':').nth(1))
+            .and_then(|pid| pid.trim().parse::<u32>().ok())
+            .filter(|pid| *pid != 0)
+    }
+
+    #[cfg(windows)]
+    fn services() -> Vec<ServiceInfo> {
+        let Ok(root) = RegKey::predef(HKEY_LOCAL_MACHINE).open_subkey(SERVICES_PATH) else {
+            return Vec::new();
+        };
+        root.enum_keys()
+            .filter_map(|k| k.ok())
+            .filter_map(|name| {
+                let key = root.open_subkey(&name).ok()?;
+                let image_path: String = key.get_value("ImagePath").ok()?;
+                Some(ServiceInfo { name, image_path })
+            })
+            .collect()
+    }
+
+    #[cfg(not(windows))]
+    fn services() -> Vec<ServiceInfo> {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OWN_BIN: &str = r"C:\Zust\zapret\bin\";
+
+    fn svc(name: &str, image_path: &str) -> ServiceInfo {
+        ServiceInfo {
+            name: name.to_string(),
+            image_path: image_path.to_string(),
+        }
+    }
+
+    fn scan(
+        processes: &[(u32, &str)],
+        services: Vec<ServiceInfo>,
+        own_pid: Option<u32>,
+    ) -> Vec<Conflict> {
+        let processes: Vec<(u32, String)> =
+            processes.iter().map(|(p, n)| (*p, n.to_string())).collect();
+        Conflicts::matches(&processes, services, own_pid, Path::new(OWN_BIN))
+    }
+
+    fn kill(pid: u32, name: &str) -> ConflictFix {
+        ConflictFix::KillProcess {
+            pid,
+            name: name.to_string(),
+        }
+    }
+
+    fn delete(name: &str) -> ConflictFix {
+        ConflictFix::DeleteService {
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn foreign_processes() {
+        let found = scan(
+            &[
+                (10, "winws.exe"),
+                (11, "winws.exe"),
+                (12, "goodbyedpi.exe"),
+                (13, "explorer.exe"),
+            ],
+            Vec::new(),
+            Some(10),
+        );
+        let fixes: Vec<ConflictFix> = found.iter().map(|c| c.fix.clone()).collect();
+        assert_eq!(fixes, [kill(11, "winws.exe"), kill(12, "goodbyedpi.exe")]);
+        assert!(matches!(found[0].kind, ConflictKind::ForeignEngine));
+        assert_eq!(found[1].name, "GoodbyeDPI");
+    }
+
+    #[test]
+    fn services_and_drivers() {
+        let services = || {
+            vec![
+                svc("goodbyedpi", r"C:\gdpi\goodbyedpi.exe"),
+                svc("zapret", r"C:\Other\bin\winws.exe"),
+                svc("WinDivert", r"C:\Other\WinDivert64.sys"),
+                svc("WinDivert14", r"C:\Zust\zapret\bin\WinDivert64.sys"),
+                svc("Spooler", r"C:\Windows\spoolsv.exe"),
+            ]
+        };
+        let names = |found: &[Conflict]| found.iter().map(|c| c.name.clone()).collect::<Vec<_>>();
+
+        // наш драйвер при работающем zapret не конфликт
+        let found = scan(&[], services(), Some(1));
+        assert_eq!(names(&found), ["goodbyedpi", "zapret", "WinDivert"]);
+        assert!(matches!(found[2].kind, ConflictKind::ForeignDriver));
+
+        // без сервиса он остался лишним
+        let found = scan(&[], services(), None);
+        assert_eq!(
+            names(&found),
+            ["goodbyedpi", "zapret", "WinDivert", "WinDivert14"]
+        );
+        assert!(matches!(found[3].kind, ConflictKind::StaleDriver));
+
+        let own = scan(
+            &[],
+            vec![svc("zapret", r"C:\Zust\zapret\bin\winws.exe")],
+            Some(1),
+        );
+        assert!(own.is_empty());
+    }
+
+    #[test]
+    fn stale_fixes_are_rejected() {
+        let found = scan(
+            &[(11, "winws.exe")],
+            vec![svc("byedpi", r"C:\b\ciadpi.exe")],
+            Some(10),
+        );
+        let (fixes, rejected) = Conflicts::accept(
+            &found,
+            vec![
+                kill(11, "winws.exe"),
+                // pid переиспользован другим процессом
+                kill(11, "explorer.exe"),
+                // процесс уже завершился
+                kill(12, "winws.exe"),
+                delete("Spooler"),
+                delete("byedpi"),
+            ],
+        );
+        assert_eq!(fixes, [kill(11, "winws.exe"), delete("byedpi")]);
+        assert_eq!(
+            rejected,
+            [
+                kill(11, "explorer.exe"),
+                kill(12, "winws.exe"),
+                delete("Spooler")
+            ]
+        );
+
+        let (fixes, rejected) = Conflicts::accept(&[], vec![kill(11, "winws.exe")]);
+        assert!(fixes.is_empty());
+        assert_eq!(rejected.len(), 1);
+    }
+}
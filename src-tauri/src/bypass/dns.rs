@@ -0,0 +1,405 @@
+use crate::settings;
+use hickory_resolver::TokioAsyncResolver;
+use hickory_resolver::config::{
+    LookupIpStrategy, NameServerConfigGroup, ResolverConfig, ResolverOpts,
+};
+use hickory_resolver::proto::rr::RData;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+
+// откуда резолвить: DNS из настроек, системный или публичный
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum ResolverPreset {
+    Configured,
+    System,
+    Google,
+    Cloudflare,
+    Quad9,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum DnsMode {
+    #[default]
+    System,
+    Udp,
+    Doh,
+    Dot,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum DnsProvider {
+    #[default]
+    Cloudflare,
+    Google,
+    Quad9,
+    Custom,
+}
+
+// DNS для всех запросов самого zust (резолвер, прокси, загрузки)
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DnsSettings {
+    pub mode: DnsMode,
+    pub provider: DnsProvider,
+    // для Custom: "1.1.1.1" или "1.1.1.1:5353"
+    pub servers: Vec<String>,
+    // имя в сертификате для DoH/DoT, "cloudflare-dns.com"
+    pub tls_name: Option<String>,
+}
+
+// общий резолвер, пересобирается при смене настроек
+static SHARED: Mutex<Option<(DnsSettings, TokioAsyncResolver)>> = Mutex::new(None);
+
+// какой SRV искать перед A/AAAA
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", content = "name", rename_all = "camelCase")]
+pub enum ServiceHint {
+    None,
+    Minecraft,
+    Teamspeak,
+    Xmpp,
+    // "_sip._udp"
+    Custom(String),
+}
+
+impl ServiceHint {
+    fn srv_prefixes(&self) -> Vec<String> {
+        match self {
+            ServiceHint::None => Vec::new(),
+            ServiceHint::Minecraft => vec!["_minecraft._tcp".to_string()],
+            ServiceHint::Teamspeak => vec!["_ts3._udp".to_string()],
+            ServiceHint::Xmpp => vec![
+                "_xmpp-client._tcp".to_string(),
+                "_xmpp-server._tcp".to_string(),
+            ],
+            ServiceHint::Custom(name) => vec![name.trim().trim_end_matches('.').to_string()],
+        }
+    }
+
+    // порт, если SRV-записи нет
+    fn default_port(&self) -> Option<u16> {
+        match self {
+            ServiceHint::Minecraft => Some(25565),
+            ServiceHint::Teamspeak => Some(9987),
+            ServiceHint::Xmpp => Some(5222),
+            ServiceHint::None | ServiceHint::Custom(_) => None,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedAddress {
+    pub ip: IpAddr,
+    pub ttl: u32,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedTarget {
+    pub host: String,
+    pub port: Option<u16>,
+    // SRV-запись, из которой взят таргет
+    pub srv: Option<String>,
+    pub priority: Option<u16>,
+    pub weight: Option<u16>,
+    pub addresses: Vec<ResolvedAddress>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolveReport {
+    pub host: String,
+    pub targets: Vec<ResolvedTarget>,
+}
+
+impl ResolveReport {
+    pub fn ips(&self) -> Vec<IpAddr> {
+        let mut ips: Vec<IpAddr> = self
+            .targets
+            .iter()
+            .flat_map(|t| t.addresses.iter().map(|a| a.ip))
+            .collect();
+        ips.sort();
+        ips.dedup();
+        ips
+    }
+}
+
+// отдаём reqwest наш резолвер, чтобы загрузки не шли через отравленный системный DNS
+pub struct SharedResolve;
+
+impl Resolve for SharedResolve {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let resolver = Dns::shared()?;
+            let ips = Dns::lookup_all(&resolver, &host).await?;
+            let addrs: Addrs = Box::new(ips.into_iter().map(|ip| SocketAddr::new(ip, 0)));
+            Ok(addrs)
+        })
+    }
+}
+
+pub struct Dns;
+
+impl Dns {
+    fn opts() -> ResolverOpts {
+        let mut opts = ResolverOpts::default();
+        opts.ip_strategy = LookupIpStrategy::Ipv4AndIpv6;
+        opts
+    }
+
+    pub fn resolver(preset: ResolverPreset) -> Result<TokioAsyncResolver, String> {
+        let config = match preset {
+            ResolverPreset::Configured => return Self::shared(),
+            ResolverPreset::System => {
+                let (config, _) =
+                    hickory_resolver::system_conf::read_system_conf().map_err(|e| e.to_string())?;
+                config
+            }
+            ResolverPreset::Google => ResolverConfig::google(),
+            ResolverPreset::Cloudflare => ResolverConfig::cloudflare(),
+            ResolverPreset::Quad9 => ResolverConfig::quad9(),
+        };
+        Ok(TokioAsyncResolver::tokio(config, Self::opts()))
+    }
+
+    fn custom_servers(dns: &DnsSettings, default_port: u16) -> Result<Vec<SocketAddr>, String> {
+        let servers = dns
+            .servers
+            .iter()
+            .map(|s| {
+                let s = s.trim();
+                s.parse::<SocketAddr>()
+                    .or_else(|_| {
+                        s.trim_matches(['[', ']'])
+                            .parse::<IpAddr>()
+                            .map(|ip| SocketAddr::new(ip, default_port))
+                    })
+                    .map_err(|_| format!("некорректный адрес DNS: {}", s))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if servers.is_empty() {
+            return Err("не указан ни один DNS-сервер".to_string());
+        }
+        Ok(servers)
+    }
+
+    fn encrypted_group(
+        dns: &DnsSettings,
+        default_port: u16,
+        build: fn(&[IpAddr], u16, String, bool) -> NameServerConfigGroup,
+    ) -> Result<NameServerConfigGroup, String> {
+        let tls_name = dns
+            .tls_name
+            .clone()
+            .filter(|n| !n.trim().is_empty())
+            .ok_or_else(|| "для DoH/DoT нужно имя сервера из сертификата".to_string())?;
+        let servers = Self::custom_servers(dns, default_port)?;
+        let port = servers[0].port();
+        let ips: Vec<IpAddr> = servers.iter().map(|s| s.ip()).collect();
+        Ok(build(&ips, port, tls_name.trim().to_string(), true))
+    }
+
+    pub fn config(dns: &DnsSettings) -> Result<ResolverConfig, String> {
+        let group = match (dns.mode, dns.provider) {
+            (DnsMode::System, _) => {
+                let (config, _) =
+                    hickory_resolver::system_conf::read_system_conf().map_err(|e| e.to_string())?;
+                return Ok(config);
+            }
+            (DnsMode::Udp, DnsProvider::Google) => NameServerConfigGroup::google(),
+            (DnsMode::Udp, DnsProvider::Cloudflare) => NameServerConfigGroup::cloudflare(),
+            (DnsMode::Udp, DnsProvider::Quad9) => NameServerConfigGroup::quad9(),
+            (DnsMode::Udp, DnsProvider::Custom) => {
+                let mut group = NameServerConfigGroup::new();
+                for server in Self::custom_servers(dns, 53)? {
+                    group.merge(NameServerConfigGroup::from_ips_clear(
+                        &[server.ip()],
+                        server.port(),
+                        true,
+                    ));
+                }
+                group
+            }
+            (DnsMode::Doh, DnsProvider::Google) => NameServerConfigGroup::google_https(),
+            (DnsMode::Doh, DnsProvider::Cloudflare) => NameServerConfigGroup::cloudflare_https(),
+            (DnsMode::Doh, DnsProvider::Quad9) => NameServerConfigGroup::quad9_https(),
+            (DnsMode::Doh, DnsProvider::Custom) => {
+                Self::encrypted_group(dns, 443, NameServerConfigGroup::from_ips_https)?
+            }
+            (DnsMode::Dot, DnsProvider::Google) => NameServerConfigGroup::google_tls(),
+            (DnsMode::Dot, DnsProvider::Cloudflare) => NameServerConfigGroup::cloudflare_tls(),
+            (DnsMode::Dot, DnsProvider::Quad9) => NameServerConfigGroup::quad9_tls(),
+            (DnsMode::Dot, DnsProvider::Custom) => {
+                Self::encrypted_group(dns, 853, NameServerConfigGroup::from_ips_tls)?
+            }
+        };
+        Ok(ResolverConfig::from_parts(None, vec![], group))
+    }
+
+    pub fn from_settings(dns: &DnsSettings) -> Result<TokioAsyncResolver, String> {
+        Ok(TokioAsyncResolver::tokio(Self::config(dns)?, Self::opts()))
+    }
+
+    // резолвер из настроек, кешируется пока настройки не поменялись
+    pub fn shared() -> Result<TokioAsyncResolver, String> {
+        let dns = settings::load_settings().dns;
+        let mut shared = SHARED.lock().map_err(|e| e.to_string())?;
+        if let Some((cached, resolver)) = shared.as_ref()
+            && *cached == dns
+        {
+            return Ok(resolver.clone());
+        }
+        let resolver = Self::from_settings(&dns)?;
+        *shared = Some((dns, resolver.clone()));
+        Ok(resolver)
+    }
+
+    // для системного DNS оставляем резолвер reqwest по умолчанию
+    pub fn http_client_builder() -> reqwest::ClientBuilder {
+        let builder = reqwest::Client::builder();
+        if settings::load_settings().dns.mode == DnsMode::System {
+            builder
+        } else {
+            builder.dns_resolver(Arc::new(SharedResolve))
+        }
+    }
+
+    // то же для блокирующих проверок из фоновых потоков (watchdog, сетевые профили)
+    pub fn blocking_client_builder() -> reqwest::blocking::ClientBuilder {
+        let builder = reqwest::blocking::Client::builder();
+        if settings::load_settings().dns.mode == DnsMode::System {
+            builder
+        } else {
+            builder.dns_resolver(Arc::new(SharedResolve))
+        }
+    }
+
+    pub fn http_client() -> Result<reqwest::Client, String> {
+        Self::http_client_builder()
+            .build()
+            .map_err(|e| e.to_string())
+    }
+
+    // проверяем, что с новыми настройками что-то резолвится, и только потом сохраняем
+    pub async fn apply_settings(dns: DnsSettings) -> Result<Vec<IpAddr>, String> {
+        let resolver = Self::from_settings(&dns)?;
+        let ips = Self::lookup_all(&resolver, "github.com").await?;
+        let mut current = settings::load_settings();
+        current.dns = dns;
+        settings::save_settings(current)?;
+        Ok(ips)
+    }
+
+    // все A и AAAA записи
+    pub async fn lookup_all(
+        resolver: &TokioAsyncResolver,
+        host: &str,
+    ) -> Result<Vec<IpAddr>, String> {
+        let lookup = resolver
+            .lookup_ip(host)
+            .await
+            .map_err(|e| format!("Ошибка резолва: {}", e))?;
+        let ips: Vec<IpAddr> = lookup.iter().collect();
+        if ips.is_empty() {
+            return Err("IP-адрес не найден".to_string());
+        }
+        Ok(ips)
+    }
+
+    // A/AAAA вместе с TTL, CNAME-цепочка пропускается
+    pub async fn lookup_with_ttl(
+        resolver: &TokioAsyncResolver,
+        host: &str,
+    ) -> Result<Vec<ResolvedAddress>, String> {
+        let lookup = resolver
+            .lookup_ip(host)
+            .await
+            .map_err(|e| format!("Ошибка резолва: {}", e))?;
+        let addresses: Vec<ResolvedAddress> = lookup
+            .as_lookup()
+            .records()
+            .iter()
+            .filter_map(|r| {
+                let ip = match r.data()? {
+                    RData::A(a) => IpAddr::V4(a.0),
+                    RData::AAAA(aaaa) => IpAddr::V6(aaaa.0),
+                    _ => return None,
+                };
+                Some(ResolvedAddress { ip, ttl: r.ttl() })
+            })
+            .collect();
+        if addresses.is_empty() {
+            return Err("IP-адрес не найден".to_string());
+        }
+        Ok(addresses)
+    }
+
+    // port задан явно - SRV не ищем, как и клиент майнкрафта
+    pub async fn resolve_service(
+        resolver: &TokioAsyncResolver,
+        host: &str,
+        hint: &ServiceHint,
+        port: Option<u16>,
+    ) -> Result<ResolveReport, String> {
+        let host = host.trim().trim_end_matches('.').to_lowercase();
+        if host.is_empty() {
+            return Err("пустой адрес".to_string());
+        }
+        let mut targets = Vec::new();
+
+        if port.is_none() {
+            for prefix in hint.srv_prefixes() {
+                let name = format!("{}.{}", prefix, host);
+                let Ok(srv) = resolver.srv_lookup(name.as_str()).await else {
+                    continue;
+                };
+                let mut records: Vec<_> = srv.iter().collect();
+                records.sort_by_key(|r| (r.priority(), std::cmp::Reverse(r.weight())));
+                for record in records {
+                    let target = record.target().to_utf8().trim_end_matches('.').to_string();
+                    // "." в SRV значит, что сервиса нет
+                    if target.is_empty() {
+                        continue;
+                    }
+                    let (addresses, error) = match Self::lookup_with_ttl(resolver, &target).await {
+                        Ok(a) => (a, None),
+                        Err(e) => (Vec::new(), Some(e)),
+                    };
+                    targets.push(ResolvedTarget {
+                        host: target,
+                        port: Some(record.port()),
+                        srv: Some(name.clone()),
+                        priority: Some(record.priority()),
+                        weight: Some(record.weight()),
+                        addresses,
+                        error,
+                    });
+                }
+            }
+        }
+
+        // SRV нет - резолвим сам хост
+        if targets.is_empty() {
+            let addresses = Self::lookup_with_ttl(resolver, &host).await?;
+            targets.push(ResolvedTarget {
+                host: host.clone(),
+                port: port.or(hint.default_port()),
+                srv: None,
+                priority: None,
+                weight: None,
+                addresses,
+                error: None,
+            });
+        }
+        Ok(ResolveReport { host, targets })
+    }
+}
@@ -0,0 +1,39 @@
+use crate::bypass::errors::ServiceError;
+use crate::bypass::zapret::Zapret;
+use crate::utils::*;
+use std::sync::Mutex;
+use std::time::Instant;
+use tauri::AppHandle;
+
+// время последнего запуска/остановки движка, под этим локом идут все смены состояния
+static LAST_CHANGE: Mutex<Option<Instant>> = Mutex::new(None);
+
+pub struct Engine;
+
+impl Engine {
+    // запуск, остановка или смена стратегии: выполняется всегда, но не одновременно с другими
+    pub fn exclusive<T>(f: impl FnOnce() -> T) -> T {
+        let mut last = LAST_CHANGE.lock().unwrap_or_else(|e| e.into_inner());
+        let out = f();
+        *last = Some(Instant::now());
+        out
+    }
+
+    // перезапуск текущей стратегии из-за события в момент since.
+    // если движок уже перезапускали после этого события, второй раз не трогаем:
+    // смена сети дёргает сразу профили, исключения и watchdog
+    pub fn restart(app: &AppHandle, reason: &str, since: Instant) -> Result<bool, ServiceError> {
+        let mut last = LAST_CHANGE.lock().unwrap_or_else(|e| e.into_inner());
+        if last.is_some_and(|t| t >= since) {
+            info(
+                app,
+                &format!("{}: движок уже перезапущен, пропускаю", reason),
+            );
+            return Ok(false);
+        }
+        info(app, &format!("{}: перезапуск", reason));
+        let restarted = Zapret::restart_current(app)?;
+        *last = Some(Instant::now());
+        Ok(restarted)
+    }
+}
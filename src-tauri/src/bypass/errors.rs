@@ -0,0 +1,84 @@
+use serde::Serialize;
+use std::fmt;
+use std::process::Output;
+
+// ошибки запуска/остановки сервиса zapret
+// во фронт уходят как { code: "...", ...поля }, code менять нельзя
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "code", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ServiceError {
+    NotElevated,
+    #[serde(rename_all = "camelCase")]
+    EngineMissing {
+        path: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    ServiceCreateFailed {
+        exit_code: Option<i32>,
+        stderr: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    ServiceRemoveFailed {
+        name: String,
+        exit_code: Option<i32>,
+        stderr: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    DriverLoadFailed {
+        exit_code: Option<i32>,
+        stderr: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    StrategyInvalid {
+        reason: String,
+    },
+}
+
+impl ServiceError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotElevated => "NOT_ELEVATED",
+            Self::EngineMissing { .. } => "ENGINE_MISSING",
+            Self::ServiceCreateFailed { .. } => "SERVICE_CREATE_FAILED",
+            Self::ServiceRemoveFailed { .. } => "SERVICE_REMOVE_FAILED",
+            Self::DriverLoadFailed { .. } => "DRIVER_LOAD_FAILED",
+            Self::StrategyInvalid { .. } => "STRATEGY_INVALID",
+        }
+    }
+
+    pub fn strategy(reason: impl Into<String>) -> Self {
+        Self::StrategyInvalid {
+            reason: reason.into(),
+        }
+    }
+}
+
+// sc пишет ошибки в stdout, powershell - в stderr, поэтому берём что есть
+pub fn output_text(out: &Output) -> String {
+    let stderr = String::from_utf8_lossy(&out.stderr).trim().to_string();
+    if !stderr.is_empty() {
+        return stderr;
+    }
+    String::from_utf8_lossy(&out.stdout).trim().to_string()
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotElevated => write!(f, "{}: нет прав администратора", self.code()),
+            Self::EngineMissing { path } => write!(f, "{}: не найден {}", self.code(), path),
+            Self::ServiceCreateFailed { exit_code, stderr }
+            | Self::DriverLoadFailed { exit_code, stderr } => {
+                write!(f, "{} ({:?}): {}", self.code(), exit_code, stderr)
+            }
+            Self::ServiceRemoveFailed {
+                name,
+                exit_code,
+                stderr,
+            } => write!(f, "{} {} ({:?}): {}", self.code(), name, exit_code, stderr),
+            Self::StrategyInvalid { reason } => write!(f, "{}: {}", self.code(), reason),
+        }
+    }
+}
+
+impl std::error::Error for ServiceError {}
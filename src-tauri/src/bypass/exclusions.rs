@@ -0,0 +1,515 @@
+use crate::bypass::engine::Engine;
+use crate::bypass::hostlist::Hostlist;
+use crate::bypass::ipset::{self, Cidr, Ipset};
+use crate::bypass::netwatch::NetEvent;
+use crate::bypass::zapret::Zapret;
+use crate::settings;
+use crate::sh_output;
+use crate::utils::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::Instant;
+use tauri::AppHandle;
+
+const AUTO_BEGIN: &str = "# zust: auto begin";
+const AUTO_END: &str = "# zust: auto end";
+// по словам в имени или описании адаптера понимаем, что это VPN.
+// сравниваем слова целиком: "tun" не должен ловить "Teredo Tunneling Pseudo-Interface"
+const VPN_MARKERS: &[&str] = &[
+    "vpn",
+    "wireguard",
+    "wg",
+    "wintun",
+    "openvpn",
+    "tap",
+    "tun",
+    "tailscale",
+    "zerotier",
+    "hamachi",
+    "radmin",
+    "amnezia",
+    "outline",
+];
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ExclusionKind {
+    Ipset,
+    Hostlist,
+}
+
+impl ExclusionKind {
+    fn file_name(self) -> &'static str {
+        match self {
+            ExclusionKind::Ipset => "ipset-exclude.txt",
+            ExclusionKind::Hostlist => "list-exclude.txt",
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalInterface {
+    pub alias: String,
+    pub cidr: Cidr,
+    pub vpn: bool,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ExclusionList {
+    // записи пользователя, их можно править
+    pub user: Vec<String>,
+    // блок, который zust пересобирает сам
+    pub auto: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ExclusionsView {
+    pub ipset: ExclusionList,
+    pub hostlist: ExclusionList,
+    pub interfaces: Vec<LocalInterface>,
+}
+
+pub struct Exclusions;
+
+impl Exclusions {
+    fn path(app: &AppHandle, kind: ExclusionKind) -> PathBuf {
+        Zapret::zapret_path(app, "lists").join(kind.file_name())
+    }
+
+    // "NordVPN", "tun0", "TAP-Windows Adapter V9"
+    fn is_vpn(name: &str) -> bool {
+        name.to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .any(|word| {
+                let base = word.trim_end_matches(|c: char| c.is_ascii_digit());
+                word.ends_with("vpn") || VPN_MARKERS.contains(&base)
+            })
+    }
+
+    // "Ethernet|192.168.1.5|24|Intel(R) Ethernet" - формат вывода из interfaces(),
+    // описание адаптера может отсутствовать
+    pub fn parse_interfaces(output: &str) -> Vec<LocalInterface> {
+        output
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.trim().split('|');
+                let alias = parts.next()?.trim().to_string();
+                // у link-local v6 есть "%12"
+                let addr = parts.next()?.trim().split('%').next()?;
+                let prefix: u8 = parts.next()?.trim().parse().ok()?;
+                let description = parts.next().unwrap_or_default();
+                let ip: IpAddr = addr.parse().ok()?;
+                if ip.is_loopback() || ip.is_unspecified() {
+                    return None;
+                }
+                let vpn = Self::is_vpn(&alias) || Self::is_vpn(description);
+                Some(LocalInterface {
+                    alias,
+                    cidr: Cidr::new(ip, prefix).ok()?,
+                    vpn,
+                })
+            })
+            .collect()
+    }
+
+    pub fn interfaces() -> Vec<LocalInterface> {
+        // у TAP и WireGuard alias бывает просто "Ethernet 3", тип виден только в описании
+        let cmd = "$d = @{}; Get-NetAdapter -IncludeHidden | ForEach-Object { $d[$_.ifIndex] = $_.InterfaceDescription }; Get-NetIPAddress | ForEach-Object { \"$($_.InterfaceAlias)|$($_.IPAddress)|$($_.PrefixLength)|$($d[$_.InterfaceIndex])\" }";
+        sh_output!("powershell", "-NoProfile", "-Command", cmd)
+            .map(|out| Self::parse_interfaces(&String::from_utf8_lossy(&out.stdout)))
+            .unwrap_or_default()
+    }
+
+    // локальные сети, которые нельзя трогать: частные диапазоны и всё, что висит на VPN
+    fn is_internal(iface: &LocalInterface) -> bool {
+        if iface.vpn {
+            return true;
+        }
+        match iface.cidr.addr() {
+            IpAddr::V4(v4) => v4.is_private() || v4.is_link_local(),
+            // fc00::/7 и fe80::/10
+            IpAddr::V6(v6) => {
+                let first = v6.segments()[0];
+                first & 0xfe00 == 0xfc00 || first & 0xffc0 == 0xfe80
+            }
+        }
+    }
+
+    pub fn auto_entries(
+        kind: ExclusionKind,
+        interfaces: &[LocalInterface],
+        corporate_domains: &[String],
+    ) -> Vec<String> {
+        let entries: BTreeSet<String> = match kind {
+            ExclusionKind::Ipset => interfaces
+                .iter()
+                .filter(|i| Self::is_internal(i))
+                .map(|i| i.cidr.to_string())
+                .collect(),
+            ExclusionKind::Hostlist => corporate_domains
+                .iter()
+                .filter_map(|d| Hostlist::normalize(d).ok())
+                .collect(),
+        };
+        entries.into_iter().collect()
+    }
+
+    // то, что уже покрыто ручными записями (10.0.0.0/8, родительский домен), не дублируем
+    fn without_covered(kind: ExclusionKind, user: &[String], auto: Vec<String>) -> Vec<String> {
+        match kind {
+            ExclusionKind::Ipset => {
+                let user_set = Ipset::from_cidrs(
+                    user.iter()
+                        .flat_map(|l| ipset::parse_line(l).unwrap_or_default()),
+                );
+                auto.into_iter()
+                    .filter(|e| e.parse::<Cidr>().is_ok_and(|c| !user_set.covers(&c)))
+                    .collect()
+            }
+            ExclusionKind::Hostlist => {
+                let known: HashSet<String> = user.iter().map(|l| l.to_lowercase()).collect();
+                auto.into_iter()
+                    .filter(|d| {
+                        !known.contains(d) && Hostlist::covering_parent(&known, d).is_none()
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    // (записи пользователя, содержимое авто-блока), комментарии и пустые строки пропускаем
+    pub fn split(content: &str) -> (Vec<String>, Vec<String>) {
+        let mut user = Vec::new();
+        let mut auto = Vec::new();
+        let mut in_auto = false;
+        for line in content.lines().map(str::trim) {
+            match line {
+                AUTO_BEGIN => in_auto = true,
+                AUTO_END => in_auto = false,
+                l if l.is_empty() || l.starts_with('#') => {}
+                l if in_auto => auto.push(l.to_string()),
+                l => user.push(l.to_string()),
+            }
+        }
+        (user, auto)
+    }
+
+    // файл правим построчно: пустые строки, комментарии и переводы строк
+    // пользователя остаются как были, zust трогает только свои строки
+    fn push_lines<'a>(out: &mut String, lines: impl IntoIterator<Item = &'a str>, eol: &str) {
+        for line in lines {
+            out.push_str(line);
+            out.push_str(eol);
+        }
+    }
+
+    // авто-блок пересобирается на своём месте, без него - дописывается в конец
+    pub fn with_auto(content: &str, auto: &[String]) -> String {
+        let eol = Hostlist::line_ending(content);
+        let block = || {
+            let mut block = String::new();
+            if !auto.is_empty() {
+                let lines = auto.iter().map(String::as_str);
+                Self::push_lines(&mut block, [AUTO_BEGIN].into_iter().chain(lines), eol);
+                Self::push_lines(&mut block, [AUTO_END], eol);
+            }
+            block
+        };
+        let mut out = String::new();
+        let mut in_auto = false;
+        let mut placed = false;
+        for line in content.lines() {
+            match line.trim() {
+                AUTO_BEGIN => {
+                    in_auto = true;
+                    if !placed {
+                        out.push_str(&block());
+                        placed = true;
+                    }
+                }
+                AUTO_END => in_auto = false,
+                _ if in_auto => {}
+                _ => Self::push_lines(&mut out, [line], eol),
+            }
+        }
+        if !placed {
+            out.push_str(&block());
+        }
+        out
+    }
+
+    // новые записи встают перед авто-блоком
+    pub fn with_added(content: &str, added: &[String]) -> String {
+        let eol = Hostlist::line_ending(content);
+        let added = added.iter().map(String::as_str);
+        let mut out = String::new();
+        let mut lines = content.lines().peekable();
+        while let Some(line) = lines.next_if(|l| l.trim() != AUTO_BEGIN) {
+            Self::push_lines(&mut out, [line], eol);
+        }
+        Self::push_lines(&mut out, added, eol);
+        Self::push_lines(&mut out, lines, eol);
+        out
+    }
+
+    pub fn with_removed(content: &str, removed: &[String]) -> String {
+        let eol = Hostlist::line_ending(content);
+        let mut out = String::new();
+        let mut in_auto = false;
+        for line in content.lines() {
+            match line.trim() {
+                AUTO_BEGIN => in_auto = true,
+                AUTO_END => in_auto = false,
+                l if !in_auto && removed.iter().any(|r| r == l) => continue,
+                _ => {}
+            }
+            Self::push_lines(&mut out, [line], eol);
+        }
+        out
+    }
+
+    fn read_raw(app: &AppHandle, kind: ExclusionKind) -> String {
+        fs::read_to_string(Self::path(app, kind)).unwrap_or_default()
+    }
+
+    fn parse(content: &str) -> ExclusionList {
+        let (user, auto) = Self::split(content);
+        ExclusionList { user, auto }
+    }
+
+    fn read(app: &AppHandle, kind: ExclusionKind) -> ExclusionList {
+        Self::parse(&Self::read_raw(app, kind))
+    }
+
+    fn write(app: &AppHandle, kind: ExclusionKind, content: &str) -> Result<(), String> {
+        write_atomic(&Self::path(app, kind), content)
+    }
+
+    pub fn get(app: &AppHandle) -> ExclusionsView {
+        ExclusionsView {
+            ipset: Self::read(app, ExclusionKind::Ipset),
+            hostlist: Self::read(app, ExclusionKind::Hostlist),
+            interfaces: Self::interfaces(),
+        }
+    }
+
+    fn normalize_entry(kind: ExclusionKind, input: &str) -> Result<String, String> {
+        match kind {
+            ExclusionKind::Ipset => {
+                let input = input.trim();
+                match ipset::parse_line(input)?.as_slice() {
+                    [] => Err("пустая запись".to_string()),
+                    [cidr] => Ok(cidr.to_string()),
+                    // диапазон оставляем как ввели, zapret их понимает
+                    _ => Ok(input.to_string()),
+                }
+            }
+            ExclusionKind::Hostlist => Hostlist::normalize(input),
+        }
+    }
+
+    pub fn add(
+        app: &AppHandle,
+        kind: ExclusionKind,
+        entries: Vec<String>,
+    ) -> Result<Vec<String>, String> {
+        let content = Self::read_raw(app, kind);
+        let mut list = Self::parse(&content);
+        let mut added = Vec::new();
+        for input in entries {
+            let entry = Self::normalize_entry(kind, &input)?;
+            if !list.user.contains(&entry) && !list.auto.contains(&entry) {
+                list.user.push(entry.clone());
+                added.push(entry);
+            }
+        }
+        if !added.is_empty() {
+            Self::write(app, kind, &Self::with_added(&content, &added))?;
+            info(app, &format!("{}: +{}", kind.file_name(), added.join(", ")));
+            Self::restart(app, Instant::now());
+        }
+        Ok(added)
+    }
+
+    pub fn remove(
+        app: &AppHandle,
+        kind: ExclusionKind,
+        entries: Vec<String>,
+    ) -> Result<Vec<String>, String> {
+        let content = Self::read_raw(app, kind);
+        let mut list = Self::parse(&content);
+        if let Some(auto) = entries.iter().find(|e| list.auto.contains(e)) {
+            return Err(format!(
+                "{} добавлен автоматически, уберите его в настройках",
+                auto
+            ));
+        }
+        let before = list.user.clone();
+        list.user.retain(|l| !entries.contains(l));
+        let removed: Vec<String> = before
+            .into_iter()
+            .filter(|l| !list.user.contains(l))
+            .collect();
+        if !removed.is_empty() {
+            Self::write(app, kind, &Self::with_removed(&content, &removed))?;
+            info(
+                app,
+                &format!("{}: -{}", kind.file_name(), removed.join(", ")),
+            );
+            Self::restart(app, Instant::now());
+        }
+        Ok(removed)
+    }
+
+    pub fn set_corporate_domains(app: &AppHandle, domains: Vec<String>) -> Result<(), String> {
+        let domains = domains
+            .iter()
+            .map(|d| Hostlist::normalize(d))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut current = settings::load_settings();
+        current.corporate_domains = domains;
+        settings::save_settings(current)?;
+        if Self::sync(app, &Self::interfaces())? {
+            Self::restart(app, Instant::now());
+        }
+        Ok(())
+    }
+
+    // пересобирает авто-блоки, true если файлы поменялись
+    pub fn sync(app: &AppHandle, interfaces: &[LocalInterface]) -> Result<bool, String> {
+        let settings = settings::load_settings();
+        let mut changed = false;
+        for kind in [ExclusionKind::Ipset, ExclusionKind::Hostlist] {
+            let auto = match (kind, settings.protect_local_networks) {
+                (ExclusionKind::Ipset, false) => Vec::new(),
+                _ => Self::auto_entries(kind, interfaces, &settings.corporate_domains),
+            };
+            let content = Self::read_raw(app, kind);
+            let list = Self::parse(&content);
+            let auto = Self::without_covered(kind, &list.user, auto);
+            if list.auto == auto {
+                continue;
+            }
+            Self::write(app, kind, &Self::with_auto(&content, &auto))?;
+            info(
+                app,
+                &format!("{}: авто-блок {} записей", kind.file_name(), auto.len()),
+            );
+            changed = true;
+        }
+        Ok(changed)
+    }
+
+    pub fn restart(app: &AppHandle, since: Instant) {
+        if let Err(e) = Engine::restart(app, "exclusions", since) {
+            info(app, &format!("exclusions restart err: {}", e));
+        }
+    }
+
+    // списки переписываются при смене интерфейсов, возвращает момент записи
+    pub fn on_net_event(app: &AppHandle, event: &NetEvent) -> Option<Instant> {
+        if !event.initial && !event.interfaces_changed {
+            return None;
+        }
+        match Self::sync(app, &event.snapshot.interfaces) {
+            Ok(true) => Some(Instant::now()),
+            Ok(false) => None,
+            Err(e) => {
+                info(app, &format!("exclusions sync err: {}", e));
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vpn_flags(output: &str) -> Vec<(String, bool)> {
+        Exclusions::parse_interfaces(output)
+            .into_iter()
+            .map(|i| (i.alias, i.vpn))
+            .collect()
+    }
+
+    #[test]
+    fn vpn_detected_by_whole_words() {
+        let output = "Ethernet|192.168.1.5|24|Intel(R) Ethernet Connection\n\
+                      Teredo Tunneling Pseudo-Interface|2001:0:1::1|64|Teredo Tunneling Pseudo-Interface\n\
+                      NordVPN|10.8.0.2|24|\n\
+                      Ethernet 3|10.9.0.2|24|TAP-Windows Adapter V9\n\
+                      wg0|10.10.0.2|32\n\
+                      Laptop|192.168.0.7|24|Tapestry Network Adapter\n";
+        assert_eq!(
+            vpn_flags(output),
+            [
+                ("Ethernet".to_string(), false),
+                ("Teredo Tunneling Pseudo-Interface".to_string(), false),
+                ("NordVPN".to_string(), true),
+                ("Ethernet 3".to_string(), true),
+                ("wg0".to_string(), true),
+                ("Laptop".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn old_three_column_output_still_parses() {
+        let parsed = Exclusions::parse_interfaces("Wi-Fi|fe80::1%12|64\n127.0.0.1|x|8\n");
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].cidr.to_string(), "fe80::/64");
+    }
+
+    const FILE: &str = "# мои исключения\r\n\r\nexample.com\r\n\r\n# zust: auto begin\r\nold.corp\r\n# zust: auto end\r\n# хвост\r\n";
+
+    #[test]
+    fn auto_block_is_replaced_in_place() {
+        let out = Exclusions::with_auto(FILE, &["a.corp".to_string(), "b.corp".to_string()]);
+        assert_eq!(
+            out,
+            "# мои исключения\r\n\r\nexample.com\r\n\r\n# zust: auto begin\r\na.corp\r\nb.corp\r\n# zust: auto end\r\n# хвост\r\n"
+        );
+        assert_eq!(
+            Exclusions::with_auto(FILE, &[]),
+            "# мои исключения\r\n\r\nexample.com\r\n\r\n# хвост\r\n"
+        );
+    }
+
+    #[test]
+    fn auto_block_is_appended_when_missing() {
+        let out = Exclusions::with_auto("a.com\n\nb.com\n", &["x.corp".to_string()]);
+        assert_eq!(
+            out,
+            "a.com\n\nb.com\n# zust: auto begin\nx.corp\n# zust: auto end\n"
+        );
+    }
+
+    #[test]
+    fn user_entries_added_before_block_and_removed_in_place() {
+        let added = Exclusions::with_added(FILE, &["new.com".to_string()]);
+        assert_eq!(
+            added,
+            "# мои исключения\r\n\r\nexample.com\r\n\r\nnew.com\r\n# zust: auto begin\r\nold.corp\r\n# zust: auto end\r\n# хвост\r\n"
+        );
+        let removed = Exclusions::with_removed(&added, &["example.com".to_string()]);
+        assert_eq!(
+            removed,
+            "# мои исключения\r\n\r\n\r\nnew.com\r\n# zust: auto begin\r\nold.corp\r\n# zust: auto end\r\n# хвост\r\n"
+        );
+    }
+
+    #[test]
+    fn split_skips_comments_and_blank_lines() {
+        let (user, auto) = Exclusions::split(FILE);
+        assert_eq!(user, ["example.com"]);
+        assert_eq!(auto, ["old.corp"]);
+    }
+}
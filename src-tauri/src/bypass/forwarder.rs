@@ -0,0 +1,649 @@
+use crate::bypass::dns::{Dns, DnsMode, DnsSettings};
+use crate::bypass::hostlist::Hostlist;
+use crate::bypass::lookup::Lookup;
+use crate::bypass::zapret::Zapret;
+use crate::settings;
+use crate::utils::*;
+use hickory_resolver::TokioAsyncResolver;
+use hickory_resolver::error::ResolveErrorKind;
+use hickory_resolver::proto::op::{Edns, Message, MessageType, OpCode, Query, ResponseCode};
+use hickory_resolver::proto::rr::rdata::{A, AAAA};
+use hickory_resolver::proto::rr::{RData, Record, RecordType};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+use tauri::async_runtime::JoinHandle;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+
+const CACHE_LIMIT: usize = 4096;
+// без EDNS клиент ждёт не больше 512 байт
+const UDP_LIMIT: usize = 512;
+const EDNS_PAYLOAD: u16 = 1232;
+const OVERRIDE_TTL: u32 = 300;
+// если апстрим не прислал SOA
+const NEGATIVE_TTL: u32 = 60;
+const TCP_IDLE: Duration = Duration::from_secs(10);
+// старый сокет освобождается не сразу после abort
+const BIND_ATTEMPTS: u32 = 10;
+// пауза после ошибки сокета, чтобы не крутиться вхолостую
+const RECV_BACKOFF: Duration = Duration::from_millis(100);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DnsOverride {
+    // покрывает и поддомены, как в хостлистах
+    pub domain: String,
+    // пустой список - отвечаем NXDOMAIN
+    pub ips: Vec<IpAddr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ForwarderSettings {
+    pub enabled: bool,
+    pub listen: String,
+    pub upstream: DnsSettings,
+    pub overrides: Vec<DnsOverride>,
+}
+
+impl Default for ForwarderSettings {
+    fn default() -> Self {
+        ForwarderSettings {
+            enabled: false,
+            listen: "127.0.0.1:53".to_string(),
+            upstream: DnsSettings {
+                mode: DnsMode::Doh,
+                ..Default::default()
+            },
+            overrides: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ForwarderStatus {
+    pub running: bool,
+    pub listen: Option<SocketAddr>,
+    pub cached: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+struct CacheEntry {
+    expires: Instant,
+    code: ResponseCode,
+    records: Vec<Record>,
+}
+
+#[derive(Default)]
+struct Cache {
+    entries: HashMap<(String, RecordType), CacheEntry>,
+    hits: u64,
+    misses: u64,
+}
+
+// разбор запросов отдельно от сокетов, апстрим можно подменить любым резолвером
+pub struct ForwarderCore {
+    resolver: TokioAsyncResolver,
+    overrides: Vec<DnsOverride>,
+    cache: Mutex<Cache>,
+}
+
+impl ForwarderCore {
+    pub fn new(resolver: TokioAsyncResolver, overrides: Vec<DnsOverride>) -> Self {
+        ForwarderCore {
+            resolver,
+            overrides,
+            cache: Mutex::new(Cache::default()),
+        }
+    }
+
+    // самое длинное совпадение выигрывает
+    fn override_for(&self, domain: &str) -> Option<&DnsOverride> {
+        self.overrides
+            .iter()
+            .filter(|o| Lookup::domain_matches(&o.domain, domain))
+            .max_by_key(|o| o.domain.len())
+    }
+
+    fn override_records(o: &DnsOverride, query: &Query) -> Vec<Record> {
+        o.ips
+            .iter()
+            .filter_map(|ip| {
+                let rdata = match (ip, query.query_type()) {
+                    (IpAddr::V4(v4), RecordType::A) => RData::A(A(*v4)),
+                    (IpAddr::V6(v6), RecordType::AAAA) => RData::AAAA(AAAA(*v6)),
+                    _ => return None,
+                };
+                Some(Record::from_rdata(
+                    query.name().clone(),
+                    OVERRIDE_TTL,
+                    rdata,
+                ))
+            })
+            .collect()
+    }
+
+    fn cached(&self, key: &(String, RecordType)) -> Option<(ResponseCode, Vec<Record>)> {
+        let mut cache = self.cache.lock().ok()?;
+        let now = Instant::now();
+        let hit = cache.entries.get(key).and_then(|entry| {
+            let left = entry.expires.checked_duration_since(now)?.as_secs() as u32;
+            let records = entry
+                .records
+                .iter()
+                .cloned()
+                .map(|mut r| {
+                    r.set_ttl(left.min(r.ttl()));
+                    r
+                })
+                .collect();
+            Some((entry.code, records))
+        });
+        match hit {
+            Some(_) => cache.hits += 1,
+            None => {
+                cache.entries.remove(key);
+                cache.misses += 1;
+            }
+        }
+        hit
+    }
+
+    fn store(&self, key: (String, RecordType), code: ResponseCode, records: &[Record], ttl: u32) {
+        let Ok(mut cache) = self.cache.lock() else {
+            return;
+        };
+        if cache.entries.len() >= CACHE_LIMIT {
+            let now = Instant::now();
+            cache.entries.retain(|_, e| e.expires > now);
+            if cache.entries.len() >= CACHE_LIMIT {
+                cache.entries.clear();
+            }
+        }
+        cache.entries.insert(
+            key,
+            CacheEntry {
+                expires: Instant::now() + Duration::from_secs(ttl as u64),
+                code,
+                records: records.to_vec(),
+            },
+        );
+    }
+
+    async fn resolve(&self, query: &Query) -> (ResponseCode, Vec<Record>) {
+        let name = query.name().to_lowercase().to_utf8();
+        if let Some(o) = self.override_for(name.trim_end_matches('.')) {
+            if o.ips.is_empty() {
+                return (ResponseCode::NXDomain, Vec::new());
+            }
+            return (ResponseCode::NoError, Self::override_records(o, query));
+        }
+
+        let key = (name, query.query_type());
+        if let Some(hit) = self.cached(&key) {
+            return hit;
+        }
+        let (code, records, ttl) = match self
+            .resolver
+            .lookup(query.name().clone(), query.query_type())
+            .await
+        {
+            Ok(lookup) => {
+                let records = lookup.records().to_vec();
+                let ttl = records.iter().map(|r| r.ttl()).min().unwrap_or(0);
+                (ResponseCode::NoError, records, ttl)
+            }
+            Err(e) => match e.kind() {
+                ResolveErrorKind::NoRecordsFound {
+                    response_code,
+                    negative_ttl,
+                    ..
+                } => (
+                    *response_code,
+                    Vec::new(),
+                    negative_ttl.unwrap_or(NEGATIVE_TTL),
+                ),
+                // апстрим недоступен - не кешируем
+                _ => return (ResponseCode::ServFail, Vec::new()),
+            },
+        };
+        self.store(key, code, &records, ttl);
+        (code, records)
+    }
+
+    // None - не DNS-запрос, отвечать не на что
+    pub async fn answer(&self, request: &[u8], udp: bool) -> Option<Vec<u8>> {
+        let request = Message::from_vec(request).ok()?;
+        if request.message_type() != MessageType::Query {
+            return None;
+        }
+        let mut response = Message::new();
+        response
+            .set_id(request.id())
+            .set_message_type(MessageType::Response)
+            .set_op_code(request.op_code())
+            .set_recursion_desired(request.recursion_desired())
+            .set_recursion_available(true);
+        match (request.op_code(), request.queries()) {
+            (OpCode::Query, [query]) => {
+                response.add_query(query.clone());
+                let (code, records) = self.resolve(query).await;
+                response.set_response_code(code);
+                response.add_answers(records);
+            }
+            (OpCode::Query, _) => {
+                response.set_response_code(ResponseCode::FormErr);
+            }
+            _ => {
+                response.set_response_code(ResponseCode::NotImp);
+            }
+        }
+
+        let mut limit = UDP_LIMIT;
+        if let Some(edns) = request.extensions() {
+            limit = (edns.max_payload() as usize).clamp(UDP_LIMIT, EDNS_PAYLOAD as usize);
+            let mut edns = Edns::new();
+            edns.set_max_payload(EDNS_PAYLOAD);
+            response.set_edns(edns);
+        }
+        let mut bytes = response.to_vec().ok()?;
+        // не влезло в UDP - клиент повторит по TCP
+        if udp && bytes.len() > limit {
+            response.take_answers();
+            response.set_truncated(true);
+            bytes = response.to_vec().ok()?;
+        }
+        Some(bytes)
+    }
+
+    fn status(&self) -> (usize, u64, u64) {
+        self.cache
+            .lock()
+            .map(|c| (c.entries.len(), c.hits, c.misses))
+            .unwrap_or_default()
+    }
+}
+
+pub struct ForwarderHandle {
+    pub addr: SocketAddr,
+    core: Arc<ForwarderCore>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl Drop for ForwarderHandle {
+    fn drop(&mut self) {
+        self.tasks.iter().for_each(|t| t.abort());
+    }
+}
+
+// запущенный форвардер и настройки, с которыми он стартовал
+static RUNNING: Mutex<Option<(ForwarderSettings, ForwarderHandle)>> = Mutex::new(None);
+
+pub struct Forwarder;
+
+impl Forwarder {
+    fn bind(listen: SocketAddr) -> Result<(std::net::UdpSocket, std::net::TcpListener), String> {
+        let mut attempt = 0;
+        loop {
+            let bound = std::net::UdpSocket::bind(listen).and_then(|udp| {
+                // порт 0 в тестах: TCP на тот же порт, что выдали UDP
+                let tcp = std::net::TcpListener::bind(udp.local_addr()?)?;
+                Ok((udp, tcp))
+            });
+            match bound {
+                Ok(sockets) => return Ok(sockets),
+                Err(_) if attempt < BIND_ATTEMPTS => {
+                    attempt += 1;
+                    std::thread::sleep(Duration::from_millis(50));
+                }
+                Err(e) => return Err(format!("{}: {}", listen, e)),
+            }
+        }
+    }
+
+    // слушает UDP и TCP на одном адресе
+    pub fn spawn(listen: SocketAddr, core: ForwarderCore) -> Result<ForwarderHandle, String> {
+        let (udp, tcp) = Self::bind(listen)?;
+        let addr = udp.local_addr().map_err(|e| e.to_string())?;
+        udp.set_nonblocking(true).map_err(|e| e.to_string())?;
+        tcp.set_nonblocking(true).map_err(|e| e.to_string())?;
+        let core = Arc::new(core);
+        let tasks = vec![
+            tauri::async_runtime::spawn(Self::serve_udp(udp, core.clone())),
+            tauri::async_runtime::spawn(Self::serve_tcp(tcp, core.clone())),
+        ];
+        Ok(ForwarderHandle { addr, core, tasks })
+    }
+
+    async fn serve_udp(socket: std::net::UdpSocket, core: Arc<ForwarderCore>) {
+        let Ok(socket) = UdpSocket::from_std(socket) else {
+            return;
+        };
+        let socket = Arc::new(socket);
+        let mut buf = [0u8; 4096];
+        loop {
+            let (len, peer) = match socket.recv_from(&mut buf).await {
+                Ok(received) => received,
+                // на Windows сюда прилетает ICMP unreachable от прошлых ответов
+                Err(e) if Self::is_transient(&e) => continue,
+                Err(_) => {
+                    tokio::time::sleep(RECV_BACKOFF).await;
+                    continue;
+                }
+            };
+            let (socket, core, request) = (socket.clone(), core.clone(), buf[..len].to_vec());
+            tokio::spawn(async move {
+                if let Some(response) = core.answer(&request, true).await {
+                    let _ = socket.send_to(&response, peer).await;
+                }
+            });
+        }
+    }
+
+    fn is_transient(e: &std::io::Error) -> bool {
+        use std::io::ErrorKind::*;
+        matches!(
+            e.kind(),
+            ConnectionReset | ConnectionRefused | Interrupted | WouldBlock
+        )
+    }
+
+    async fn serve_tcp(listener: std::net::TcpListener, core: Arc<ForwarderCore>) {
+        let Ok(listener) = TcpListener::from_std(listener) else {
+            return;
+        };
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(Self::serve_tcp_conn(stream, core.clone()));
+        }
+    }
+
+    // сообщения с 2-байтовой длиной впереди
+    async fn serve_tcp_conn(mut stream: TcpStream, core: Arc<ForwarderCore>) {
+        loop {
+            let Ok(Ok(len)) = tokio::time::timeout(TCP_IDLE, stream.read_u16()).await else {
+                return;
+            };
+            let mut request = vec![0u8; len as usize];
+            if stream.read_exact(&mut request).await.is_err() {
+                return;
+            }
+            let Some(response) = core.answer(&request, false).await else {
+                return;
+            };
+            if stream.write_u16(response.len() as u16).await.is_err()
+                || stream.write_all(&response).await.is_err()
+            {
+                return;
+            }
+        }
+    }
+
+    // через системный DNS нельзя: система может смотреть на сам форвардер
+    fn upstream(settings: &ForwarderSettings) -> Result<TokioAsyncResolver, String> {
+        if !matches!(settings.upstream.mode, DnsMode::Doh | DnsMode::Dot) {
+            return Err("форвардер работает только через DoH или DoT".to_string());
+        }
+        Dns::from_settings(&settings.upstream)
+    }
+
+    fn validate(settings: ForwarderSettings) -> Result<ForwarderSettings, String> {
+        let listen: SocketAddr = settings
+            .listen
+            .trim()
+            .parse()
+            .map_err(|_| format!("некорректный адрес: {}", settings.listen))?;
+        let overrides = settings
+            .overrides
+            .iter()
+            .map(|o| {
+                Ok(DnsOverride {
+                    domain: Hostlist::normalize(&o.domain)?,
+                    ips: o.ips.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(ForwarderSettings {
+            listen: listen.to_string(),
+            overrides,
+            ..settings
+        })
+    }
+
+    pub fn start(app: &AppHandle, settings: ForwarderSettings) -> Result<SocketAddr, String> {
+        let mut running = RUNNING.lock().map_err(|e| e.to_string())?;
+        if let Some((current, handle)) = running.as_ref()
+            && *current == settings
+        {
+            return Ok(handle.addr);
+        }
+        // старый освобождает порт
+        *running = None;
+        let listen: SocketAddr = settings
+            .listen
+            .parse()
+            .map_err(|_| format!("некорректный адрес: {}", settings.listen))?;
+        let core = ForwarderCore::new(Self::upstream(&settings)?, settings.overrides.clone());
+        let handle = Self::spawn(listen, core)?;
+        let addr = handle.addr;
+        info(
+            app,
+            &format!(
+                "dns forwarder: {} -> {:?}/{:?}, подмен: {}",
+                addr,
+                settings.upstream.mode,
+                settings.upstream.provider,
+                settings.overrides.len()
+            ),
+        );
+        *running = Some((settings, handle));
+        Ok(addr)
+    }
+
+    pub fn stop(app: &AppHandle) {
+        let stopped = RUNNING.lock().ok().and_then(|mut r| r.take());
+        if stopped.is_some() {
+            info(app, "dns forwarder остановлен");
+        }
+    }
+
+    // запускается и останавливается вместе с движком
+    pub fn sync(app: &AppHandle) -> Result<(), String> {
+        let settings = settings::load_settings().dns_forwarder;
+        if settings.enabled {
+            Self::start(app, settings).map(|_| ())
+        } else {
+            Self::stop(app);
+            Ok(())
+        }
+    }
+
+    pub fn status() -> ForwarderStatus {
+        let running = RUNNING.lock().ok();
+        let Some((_, handle)) = running.as_ref().and_then(|r| r.as_ref()) else {
+            return ForwarderStatus::default();
+        };
+        let (cached, hits, misses) = handle.core.status();
+        ForwarderStatus {
+            running: true,
+            listen: Some(handle.addr),
+            cached,
+            hits,
+            misses,
+        }
+    }
+
+    pub fn apply_settings(
+        app: &AppHandle,
+        forwarder: ForwarderSettings,
+    ) -> Result<ForwarderStatus, String> {
+        let forwarder = Self::validate(forwarder)?;
+        Self::upstream(&forwarder)?;
+        let mut current = settings::load_settings();
+        current.dns_forwarder = forwarder;
+        settings::save_settings(current)?;
+        if Zapret::get_strategy() != "Отсутствует" {
+            Self::sync(app)?;
+        }
+        Ok(Self::status())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hickory_resolver::config::{NameServerConfig, Protocol, ResolverConfig, ResolverOpts};
+    use hickory_resolver::proto::rr::Name;
+    use std::net::Ipv4Addr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const STUB_IP: Ipv4Addr = Ipv4Addr::new(10, 1, 2, 3);
+
+    // апстрим на 127.0.0.1:0: на любой A отвечает STUB_IP и считает запросы
+    async fn stub_upstream() -> (SocketAddr, Arc<AtomicUsize>) {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        let seen = Arc::new(AtomicUsize::new(0));
+        let counter = seen.clone();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 4096];
+            while let Ok((len, peer)) = socket.recv_from(&mut buf).await {
+                let Ok(request) = Message::from_vec(&buf[..len]) else {
+                    continue;
+                };
+                counter.fetch_add(1, Ordering::SeqCst);
+                let mut response = Message::new();
+                response
+                    .set_id(request.id())
+                    .set_message_type(MessageType::Response)
+                    .set_op_code(OpCode::Query)
+                    .set_recursion_desired(request.recursion_desired())
+                    .set_recursion_available(true);
+                for query in request.queries() {
+                    response.add_query(query.clone());
+                    if query.query_type() == RecordType::A {
+                        response.add_answer(Record::from_rdata(
+                            query.name().clone(),
+                            120,
+                            RData::A(A(STUB_IP)),
+                        ));
+                    }
+                }
+                let _ = socket.send_to(&response.to_vec().unwrap(), peer).await;
+            }
+        });
+        (addr, seen)
+    }
+
+    fn core(upstream: SocketAddr, overrides: Vec<DnsOverride>) -> ForwarderCore {
+        let mut config = ResolverConfig::new();
+        config.add_name_server(NameServerConfig::new(upstream, Protocol::Udp));
+        let mut opts = ResolverOpts::default();
+        // кеш проверяем свой, а не резолвера
+        opts.cache_size = 0;
+        opts.attempts = 1;
+        opts.timeout = Duration::from_secs(2);
+        ForwarderCore::new(TokioAsyncResolver::tokio(config, opts), overrides)
+    }
+
+    fn query(id: u16, name: &str) -> Vec<u8> {
+        let mut message = Message::new();
+        message
+            .set_id(id)
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Query)
+            .set_recursion_desired(true);
+        message.add_query(Query::query(Name::from_ascii(name).unwrap(), RecordType::A));
+        message.to_vec().unwrap()
+    }
+
+    async fn ask_udp(addr: SocketAddr, id: u16, name: &str) -> Message {
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        client.send_to(&query(id, name), addr).await.unwrap();
+        let mut buf = [0u8; 4096];
+        let (len, _) = tokio::time::timeout(Duration::from_secs(5), client.recv_from(&mut buf))
+            .await
+            .expect("форвардер не ответил")
+            .unwrap();
+        Message::from_vec(&buf[..len]).unwrap()
+    }
+
+    fn answer_ips(response: &Message) -> Vec<IpAddr> {
+        response
+            .answers()
+            .iter()
+            .filter_map(|r| r.data()?.ip_addr())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn udp_query_is_forwarded_and_cached() {
+        let (upstream, seen) = stub_upstream().await;
+        let handle =
+            Forwarder::spawn("127.0.0.1:0".parse().unwrap(), core(upstream, Vec::new())).unwrap();
+
+        let response = ask_udp(handle.addr, 0x1234, "example.com.").await;
+        assert_eq!(response.id(), 0x1234);
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        assert_eq!(answer_ips(&response), vec![IpAddr::V4(STUB_IP)]);
+        assert_eq!(seen.load(Ordering::SeqCst), 1);
+
+        let response = ask_udp(handle.addr, 0x4321, "EXAMPLE.com.").await;
+        assert_eq!(response.id(), 0x4321);
+        assert_eq!(answer_ips(&response), vec![IpAddr::V4(STUB_IP)]);
+        assert_eq!(seen.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn tcp_query_is_answered() {
+        let (upstream, seen) = stub_upstream().await;
+        let handle =
+            Forwarder::spawn("127.0.0.1:0".parse().unwrap(), core(upstream, Vec::new())).unwrap();
+
+        let mut stream = TcpStream::connect(handle.addr).await.unwrap();
+        let request = query(7, "example.org.");
+        stream.write_u16(request.len() as u16).await.unwrap();
+        stream.write_all(&request).await.unwrap();
+        let len = stream.read_u16().await.unwrap();
+        let mut buf = vec![0u8; len as usize];
+        stream.read_exact(&mut buf).await.unwrap();
+
+        let response = Message::from_vec(&buf).unwrap();
+        assert_eq!(response.id(), 7);
+        assert_eq!(answer_ips(&response), vec![IpAddr::V4(STUB_IP)]);
+        assert_eq!(seen.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn overrides_skip_upstream() {
+        let (upstream, seen) = stub_upstream().await;
+        let overrides = vec![
+            DnsOverride {
+                domain: "blocked.test".to_string(),
+                ips: Vec::new(),
+            },
+            DnsOverride {
+                domain: "local.test".to_string(),
+                ips: vec!["192.168.0.10".parse().unwrap()],
+            },
+        ];
+        let handle =
+            Forwarder::spawn("127.0.0.1:0".parse().unwrap(), core(upstream, overrides)).unwrap();
+
+        let response = ask_udp(handle.addr, 1, "www.blocked.test.").await;
+        assert_eq!(response.response_code(), ResponseCode::NXDomain);
+        assert!(response.answers().is_empty());
+
+        let response = ask_udp(handle.addr, 2, "local.test.").await;
+        assert_eq!(
+            answer_ips(&response),
+            vec!["192.168.0.10".parse::<IpAddr>().unwrap()]
+        );
+        assert_eq!(seen.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,101 @@
+use crate::bypass::dns::Dns;
+use crate::bypass::zapret::Zapret;
+use crate::utils::*;
+use serde::Serialize;
+use std::fs;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+// сколько целей из targets.txt проверять в быстром режиме
+const QUICK_TARGETS: usize = 4;
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetResult {
+    pub name: String,
+    pub url: String,
+    pub ok: bool,
+    pub ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthReport {
+    pub engine_running: bool,
+    pub healthy: bool,
+    pub results: Vec<TargetResult>,
+}
+
+pub struct Health;
+
+impl Health {
+    // формат как у test zapret.ps1: Name = "https://..."
+    pub fn parse_targets(content: &str) -> Vec<(String, String)> {
+        content
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .filter_map(|l| {
+                let (name, value) = l.split_once('=')?;
+                let url = value.trim().trim_matches('"');
+                url.starts_with("https://")
+                    .then(|| (name.trim().to_string(), url.to_string()))
+            })
+            .collect()
+    }
+
+    // блокирующая, из async вызывать через spawn_blocking
+    pub fn quick_check(app: &AppHandle) -> HealthReport {
+        let engine_running = Zapret::get_strategy() != "Отсутствует";
+        let content =
+            fs::read_to_string(Zapret::zapret_path(app, "utils/targets.txt")).unwrap_or_default();
+        let client = Dns::blocking_client_builder().timeout(TIMEOUT).build();
+
+        let results: Vec<TargetResult> = Self::parse_targets(&content)
+            .into_iter()
+            .take(QUICK_TARGETS)
+            .map(|(name, url)| {
+                let start = Instant::now();
+                let res = client
+                    .as_ref()
+                    .map_err(|e| e.to_string())
+                    .and_then(|c| c.head(&url).send().map_err(|e| e.to_string()));
+                match res {
+                    Ok(_) => TargetResult {
+                        name,
+                        url,
+                        ok: true,
+                        ms: Some(start.elapsed().as_millis() as u64),
+                        error: None,
+                    },
+                    Err(e) => TargetResult {
+                        name,
+                        url,
+                        ok: false,
+                        ms: None,
+                        error: Some(e),
+                    },
+                }
+            })
+            .collect();
+
+        let ok = results.iter().filter(|r| r.ok).count();
+        let healthy = engine_running && !results.is_empty() && ok * 2 >= results.len();
+        info(
+            app,
+            &format!(
+                "health: {}/{} ok, engine {}",
+                ok,
+                results.len(),
+                engine_running
+            ),
+        );
+        HealthReport {
+            engine_running,
+            healthy,
+            results,
+        }
+    }
+}
@@ -0,0 +1,363 @@
+use crate::bypass::zapret::Zapret;
+use crate::utils::*;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use tauri::AppHandle;
+use tauri::Url;
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct InvalidDomain {
+    pub input: String,
+    pub error: String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RedundantDomain {
+    pub domain: String,
+    pub parent: String,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HostlistReport {
+    pub added: Vec<String>,
+    pub duplicates: Vec<String>,
+    pub invalid: Vec<InvalidDomain>,
+    // покрыты родительским доменом (новым или уже записанным), zapret матчит по суффиксу
+    pub redundant: Vec<RedundantDomain>,
+}
+
+pub struct Hostlist;
+
+impl Hostlist {
+    // "https://Пример.рф:443/path" -> "xn--e1afmkfd.xn--p1ai"
+    pub fn normalize(input: &str) -> Result<String, String> {
+        let trimmed = input
+            .trim()
+            .trim_start_matches("*.")
+            .trim_start_matches('.');
+        if trimmed.is_empty() {
+            return Err("пустой домен".to_string());
+        }
+        let with_scheme = if trimmed.contains("://") {
+            trimmed.to_string()
+        } else {
+            format!("http://{}", trimmed)
+        };
+        let url = Url::parse(&with_scheme).map_err(|e| format!("некорректный домен: {}", e))?;
+        let host = url
+            .host_str()
+            .ok_or_else(|| "домен не найден".to_string())?;
+        if host.trim_matches(['[', ']']).parse::<IpAddr>().is_ok() {
+            return Err("это IP, его нужно добавлять в ipset".to_string());
+        }
+        let domain = host.trim_end_matches('.');
+        // www. ничего не даёт: запись в хостлисте и так покрывает поддомены
+        let domain = domain
+            .strip_prefix("www.")
+            .filter(|rest| rest.contains('.'))
+            .unwrap_or(domain);
+        if domain.is_empty() || domain.split('.').any(|l| l.is_empty() || l.len() > 63) {
+            return Err("некорректная метка домена".to_string());
+        }
+        Ok(domain.to_string())
+    }
+
+    // ближайший родитель домена, который уже есть в списке
+    pub fn covering_parent(domains: &HashSet<String>, domain: &str) -> Option<String> {
+        let mut rest = domain;
+        while let Some((_, parent)) = rest.split_once('.') {
+            if domains.contains(parent) {
+                return Some(parent.to_string());
+            }
+            rest = parent;
+        }
+        None
+    }
+
+    pub fn list_path(app: &AppHandle, file_name: &str) -> Result<PathBuf, String> {
+        check_list_name(file_name)?;
+        let path = Zapret::zapret_path(app, "lists").join(file_name);
+        if !path.exists() {
+            return Err(format!("Файл {} не найден", file_name));
+        }
+        Ok(path)
+    }
+
+    pub fn domains(content: &str) -> Vec<String> {
+        content
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(str::to_lowercase)
+            .collect()
+    }
+
+    // файл дописываем и переписываем с теми же переводами строк, что в нём уже есть
+    pub fn line_ending(content: &str) -> &'static str {
+        if content.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        }
+    }
+
+    fn plan(content: &str, inputs: Vec<String>) -> HostlistReport {
+        let existing = Self::domains(content);
+        let mut known: HashSet<String> = existing.iter().cloned().collect();
+        let mut report = HostlistReport::default();
+
+        for input in inputs {
+            let domain = match Self::normalize(&input) {
+                Ok(d) => d,
+                Err(error) => {
+                    report.invalid.push(InvalidDomain { input, error });
+                    continue;
+                }
+            };
+            if known.contains(&domain) {
+                report.duplicates.push(domain);
+                continue;
+            }
+            if let Some(parent) = Self::covering_parent(&known, &domain) {
+                report.redundant.push(RedundantDomain {
+                    domain: domain.clone(),
+                    parent,
+                });
+            }
+            known.insert(domain.clone());
+            report.added.push(domain);
+        }
+
+        // и наоборот: новый родитель делает лишними уже записанные поддомены
+        let added: HashSet<String> = report.added.iter().cloned().collect();
+        for domain in existing.iter().chain(&report.added) {
+            if report.redundant.iter().any(|r| &r.domain == domain) {
+                continue;
+            }
+            if let Some(parent) = Self::covering_parent(&added, domain) {
+                report.redundant.push(RedundantDomain {
+                    domain: domain.clone(),
+                    parent,
+                });
+            }
+        }
+        report
+    }
+
+    pub fn add(
+        app: &AppHandle,
+        file_name: &str,
+        inputs: Vec<String>,
+    ) -> Result<HostlistReport, String> {
+        let path = Self::list_path(app, file_name)?;
+        let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        let report = Self::plan(&content, inputs);
+
+        if !report.added.is_empty() {
+            let eol = Self::line_ending(&content);
+            let mut new_content = content;
+            if !new_content.is_empty() && !new_content.ends_with('\n') {
+                new_content.push_str(eol);
+            }
+            for domain in &report.added {
+                new_content.push_str(domain);
+                new_content.push_str(eol);
+            }
+            write_atomic(&path, &new_content)?;
+        }
+        info(
+            app,
+            &format!(
+                "hostlist {}: +{} дублей {} ошибок {}",
+                file_name,
+                report.added.len(),
+                report.duplicates.len(),
+                report.invalid.len()
+            ),
+        );
+        Ok(report)
+    }
+
+    // удаляет строки целиком, комментарии и порядок остальных не трогаются
+    fn without(content: &str, targets: &HashSet<String>) -> (String, Vec<String>) {
+        let eol = Self::line_ending(content);
+        let mut removed = Vec::new();
+        let kept: String = content
+            .lines()
+            .filter(|line| {
+                let domain = line.trim().to_lowercase();
+                if targets.contains(&domain) {
+                    removed.push(domain);
+                    false
+                } else {
+                    true
+                }
+            })
+            .map(|l| format!("{}{}", l, eol))
+            .collect();
+        (kept, removed)
+    }
+
+    pub fn remove(
+        app: &AppHandle,
+        file_name: &str,
+        inputs: Vec<String>,
+    ) -> Result<Vec<String>, String> {
+        let path = Self::list_path(app, file_name)?;
+        let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        let targets: HashSet<String> = inputs
+            .iter()
+            .filter_map(|i| Self::normalize(i).ok())
+            .collect();
+
+        let (kept, removed) = Self::without(&content, &targets);
+        if !removed.is_empty() {
+            write_atomic(&path, &kept)?;
+            info(app, &format!("hostlist {}: -{}", file_name, removed.len()));
+        }
+        Ok(removed)
+    }
+
+    pub fn search(app: &AppHandle, file_name: &str, query: &str) -> Result<Vec<String>, String> {
+        let path = Self::list_path(app, file_name)?;
+        let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        let query = query.trim().to_lowercase();
+        // для кириллицы ищем и по punycode
+        let puny = Self::normalize(&query).ok();
+        Ok(Self::domains(&content)
+            .into_iter()
+            .filter(|d| d.contains(&query) || puny.as_ref().is_some_and(|p| d.contains(p)))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inputs(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn redundant(report: &HostlistReport) -> Vec<(&str, &str)> {
+        report
+            .redundant
+            .iter()
+            .map(|r| (r.domain.as_str(), r.parent.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn normalize_strips_url_parts() {
+        for input in [
+            "example.com",
+            "  EXAMPLE.com  ",
+            "https://example.com/path?q=1#x",
+            "http://example.com:8080",
+            "example.com:443/watch",
+            "www.example.com",
+            "https://www.Example.com./",
+            "*.example.com",
+            ".example.com",
+        ] {
+            assert_eq!(
+                Hostlist::normalize(input).unwrap(),
+                "example.com",
+                "{}",
+                input
+            );
+        }
+        assert_eq!(
+            Hostlist::normalize("a.b.example.com").unwrap(),
+            "a.b.example.com"
+        );
+        // www без зоны - это и есть домен
+        assert_eq!(Hostlist::normalize("www.com").unwrap(), "www.com");
+    }
+
+    #[test]
+    fn normalize_converts_idn_to_punycode() {
+        assert_eq!(
+            Hostlist::normalize("пример.рф").unwrap(),
+            "xn--e1afmkfd.xn--p1ai"
+        );
+        assert_eq!(
+            Hostlist::normalize("https://Почта.Пример.РФ/inbox").unwrap(),
+            "xn--80a1acny.xn--e1afmkfd.xn--p1ai"
+        );
+    }
+
+    #[test]
+    fn normalize_rejects_ips_and_empty() {
+        for input in [
+            "",
+            "   ",
+            "*.",
+            "1.2.3.4",
+            "http://1.2.3.4:80/",
+            "[::1]",
+            "https://[2001:db8::1]/",
+        ] {
+            assert!(Hostlist::normalize(input).is_err(), "{}", input);
+        }
+        assert!(Hostlist::normalize("a..com").is_err());
+    }
+
+    #[test]
+    fn new_subdomain_of_existing_parent_is_redundant() {
+        let report = Hostlist::plan("example.com\n", inputs(&["cdn.example.com"]));
+        assert_eq!(report.added, ["cdn.example.com"]);
+        assert_eq!(redundant(&report), [("cdn.example.com", "example.com")]);
+    }
+
+    #[test]
+    fn new_parent_flags_existing_subdomains() {
+        let report = Hostlist::plan(
+            "cdn.example.com\n# comment\nimg.cdn.example.com\nother.org\n",
+            inputs(&["example.com"]),
+        );
+        assert_eq!(report.added, ["example.com"]);
+        assert_eq!(
+            redundant(&report),
+            [
+                ("cdn.example.com", "example.com"),
+                ("img.cdn.example.com", "example.com")
+            ]
+        );
+    }
+
+    #[test]
+    fn parent_after_child_in_one_batch() {
+        let report = Hostlist::plan("", inputs(&["a.example.com", "example.com"]));
+        assert_eq!(redundant(&report), [("a.example.com", "example.com")]);
+    }
+
+    #[test]
+    fn duplicates_and_invalid_are_not_added() {
+        let report = Hostlist::plan("Example.com\n", inputs(&["example.com", "1.2.3.4", ""]));
+        assert!(report.added.is_empty());
+        assert_eq!(report.duplicates, ["example.com"]);
+        assert_eq!(report.invalid.len(), 2);
+    }
+
+    #[test]
+    fn remove_keeps_crlf() {
+        let targets = HashSet::from(["b.com".to_string()]);
+        let (kept, removed) = Hostlist::without("# list\r\na.com\r\nb.com\r\nc.com\r\n", &targets);
+        assert_eq!(kept, "# list\r\na.com\r\nc.com\r\n");
+        assert_eq!(removed, ["b.com"]);
+    }
+
+    #[test]
+    fn remove_keeps_lf() {
+        let targets = HashSet::from(["a.com".to_string()]);
+        let (kept, _) = Hostlist::without("a.com\nb.com", &targets);
+        assert_eq!(kept, "b.com\n");
+    }
+}
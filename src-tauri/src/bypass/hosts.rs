@@ -1,72 +1,666 @@
-use regex::Regex;
+use chrono::{DateTime, Local};
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::{Path, PathBuf};
-use std::{env, fs};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::time::Duration;
 use tauri::AppHandle;
+use tokio::sync::Mutex;
+use tokio::task::JoinSet;
 
-use crate::{sh, utils::*};
+use crate::bypass::dns::Dns;
+use crate::bypass::hostsbackup::{HostsBackup, HostsBackups};
+use crate::bypass::hostsfile::{BlockMarker, HostsConflict, HostsDiff, HostsFile, LineKind};
+use crate::bypass::hostsplatform::HostsPlatform;
+use crate::bypass::zapret::Zapret;
+use crate::utils::*;
 
 const HOSTS_URL: &str =
     "https://raw.githubusercontent.com/ImMALWARE/dns.malw.link/refs/heads/master/hosts";
+const SOURCES_FILE: &str = "hosts-sources.json";
+const SELECTION_FILE: &str = "hosts-selection.json";
+const CACHE_DIR: &str = "hosts-cache";
+// блок пользователя, источники его никогда не трогают
+pub const CUSTOM_ID: &str = "custom";
+// модалка ждёт ответа 20 секунд, кеш должен успеть подхватиться раньше
+const FETCH_TIMEOUT: Duration = Duration::from_secs(8);
+const CHECK_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+static STORE_LOCK: Mutex<()> = Mutex::const_new(());
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceMarker {
+    pub begin: String,
+    pub end: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HostsSource {
+    // идёт в маркер блока и в имя файла кеша
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    // свои маркеры у источников, которые уже есть в чужих hosts (malw)
+    #[serde(default)]
+    pub marker: Option<SourceMarker>,
+    #[serde(default)]
+    pub last_fetched: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub last_error: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl HostsSource {
+    // малв, я надеюсь ты это менять не будешь. 🥹
+    fn malw() -> Self {
+        HostsSource {
+            id: "dns.malw.link".to_string(),
+            name: "dns.malw.link".to_string(),
+            url: HOSTS_URL.to_string(),
+            enabled: true,
+            marker: Some(SourceMarker {
+                begin: "### dns.malw.link: hosts file".to_string(),
+                end: "### dns.malw.link: end hosts file".to_string(),
+            }),
+            last_fetched: None,
+            last_error: None,
+        }
+    }
+
+    pub fn block_marker(&self) -> BlockMarker {
+        match &self.marker {
+            Some(m) => BlockMarker::custom(&self.id, &m.begin, &m.end),
+            None => BlockMarker::zust(&self.id),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HostsCategory {
+    // от имени, не от позиции: переживает перестановки в источнике
+    pub id: String,
+    pub name: String,
+    pub lines: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HostsSourceData {
+    pub id: String,
+    pub name: String,
+    pub date: String,
+    // в порядке источника
+    pub categories: Vec<HostsCategory>,
+    // сеть недоступна, показываем последнюю удачную загрузку
+    pub cached: bool,
+    pub fetched_at: Option<DateTime<Local>>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HostsSelection {
+    // id источника -> id выбранных категорий
+    #[serde(default)]
+    pub categories: HashMap<String, Vec<String>>,
+    // id источника -> строки, которые исключили после проверки записей
+    #[serde(default)]
+    pub excluded: HashMap<String, Vec<String>>,
+    // 0 - не переприменять по расписанию
+    #[serde(default = "default_auto_update")]
+    pub auto_update_hours: u64,
+    #[serde(default)]
+    pub last_applied: Option<DateTime<Local>>,
+}
+
+fn default_auto_update() -> u64 {
+    24
+}
+
+impl Default for HostsSelection {
+    fn default() -> Self {
+        HostsSelection {
+            categories: HashMap::new(),
+            excluded: HashMap::new(),
+            auto_update_hours: default_auto_update(),
+            last_applied: None,
+        }
+    }
+}
+
+impl HostsSelection {
+    fn is_empty(&self) -> bool {
+        self.categories.values().all(|ids| ids.is_empty())
+    }
+
+    fn is_due(&self, now: DateTime<Local>) -> bool {
+        self.auto_update_hours > 0
+            && !self.is_empty()
+            && self.last_applied.is_none_or(|applied| {
+                now.signed_duration_since(applied).num_hours() >= self.auto_update_hours as i64
+            })
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HostsPreview {
+    pub diff: HostsDiff,
+    pub conflicts: Vec<HostsConflict>,
+}
 
 pub struct Hosts;
 
 impl Hosts {
-    // малв, я надеюсь ты это менять не будешь. 🥹
-    const HOSTS_START_MARKER: &str = "### dns.malw.link: hosts file";
-    const HOSTS_MARKER_END: &str = "### dns.malw.link: end hosts file";
+    fn cache_path(app: &AppHandle, id: &str) -> PathBuf {
+        Zapret::zapret_path(app, CACHE_DIR).join(format!("{}.txt", id))
+    }
 
-    fn get_path() -> PathBuf {
-        let sys_root = env::var("SystemRoot").unwrap_or_else(|_| r"C:\Windows".to_string());
-        Path::new(&sys_root).join(r"System32\drivers\etc\hosts")
+    // без файла - только malw, как было раньше
+    fn load_sources(app: &AppHandle) -> Vec<HostsSource> {
+        fs::read_to_string(Zapret::zapret_path(app, SOURCES_FILE))
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_else(|| vec![HostsSource::malw()])
     }
 
-    pub async fn fetch(app: &AppHandle) -> Result<String, String> {
-        let response = reqwest::get(HOSTS_URL).await.map_err(|e| {
-            let err_msg = format!("err internet: {}", e);
-            info(app, &err_msg);
-            err_msg
-        })?;
+    fn store_sources(app: &AppHandle, sources: &[HostsSource]) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(sources).map_err(|e| e.to_string())?;
+        write_atomic(&Zapret::zapret_path(app, SOURCES_FILE), &json)
+    }
+
+    pub async fn sources(app: &AppHandle) -> Vec<HostsSource> {
+        let _guard = STORE_LOCK.lock().await;
+        Self::load_sources(app)
+    }
+
+    fn check_source(source: &HostsSource) -> Result<(), String> {
+        let id_ok = !source.id.is_empty()
+            && !source.id.starts_with('.')
+            && source
+                .id
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '.');
+        if !id_ok || source.id == CUSTOM_ID {
+            return Err(format!("некорректный id источника: {}", source.id));
+        }
+        if !source.url.starts_with("https://") && !source.url.starts_with("http://") {
+            return Err(format!("некорректный URL: {}", source.url));
+        }
+        if let Some(m) = &source.marker {
+            let (begin, end) = (m.begin.trim(), m.end.trim());
+            if begin.is_empty() || end.is_empty() || begin == end {
+                return Err("маркеры блока должны быть разными и непустыми".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    // новый источник добавляется в конец, существующий обновляется по id
+    pub async fn save_source(app: &AppHandle, source: HostsSource) -> Result<(), String> {
+        Self::check_source(&source)?;
+        let _guard = STORE_LOCK.lock().await;
+        let mut sources = Self::load_sources(app);
+        match sources.iter_mut().find(|s| s.id == source.id) {
+            Some(existing) => {
+                existing.name = source.name;
+                existing.url = source.url;
+                existing.enabled = source.enabled;
+                existing.marker = source.marker;
+            }
+            None => sources.push(HostsSource {
+                last_fetched: None,
+                last_error: None,
+                ..source
+            }),
+        }
+        Self::store_sources(app, &sources)
+    }
+
+    // вместе с источником уходят его блок в hosts и кеш
+    pub async fn remove_source(app: &AppHandle, id: &str) -> Result<(), String> {
+        let _guard = STORE_LOCK.lock().await;
+        let mut sources = Self::load_sources(app);
+        let Some(pos) = sources.iter().position(|s| s.id == id) else {
+            return Ok(());
+        };
+        let removed = sources.remove(pos);
+        let mut markers = Self::markers(&sources);
+        markers.push(removed.block_marker());
+        let current = Self::read_raw()?;
+        let mut file = HostsFile::parse(&current, &markers);
+        if file.remove_block(&removed.id) {
+            Self::commit(app, &current, &file.render())?;
+        }
+        Self::store_sources(app, &sources)?;
+        let mut selection = Self::load_selection(app);
+        selection.categories.remove(id);
+        selection.excluded.remove(id);
+        Self::store_selection(app, &selection)?;
+        let _ = fs::remove_file(Self::cache_path(app, id));
+        info(app, &format!("hosts source removed: {}", id));
+        Ok(())
+    }
+
+    async fn download(url: &str) -> Result<String, String> {
+        let response = Dns::http_client_builder()
+            .timeout(FETCH_TIMEOUT)
+            .build()
+            .map_err(|e| e.to_string())?
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| format!("err internet: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!("err status: {}", response.status()));
+        }
+        response
+            .text()
+            .await
+            .map_err(|e| format!("err read: {}", e))
+    }
+
+    // (текст, из кеша ли, ошибка загрузки)
+    async fn fetch(
+        app: &AppHandle,
+        source: &HostsSource,
+    ) -> (Option<String>, bool, Option<String>) {
+        let cache = Self::cache_path(app, &source.id);
+        match Self::download(&source.url).await {
+            Ok(text) => {
+                if let Some(dir) = cache.parent() {
+                    let _ = fs::create_dir_all(dir);
+                }
+                if let Err(e) = write_atomic(&cache, &text) {
+                    info(app, &format!("hosts cache {}: {}", source.id, e));
+                }
+                (Some(text), false, None)
+            }
+            Err(e) => {
+                info(app, &format!("hosts {}: {}", source.id, e));
+                let cached = fs::read_to_string(&cache).ok();
+                let from_cache = cached.is_some();
+                (cached, from_cache, Some(e))
+            }
+        }
+    }
+
+    // тело блока источника, а если маркеров в файле нет - весь файл
+    fn source_body(source: &HostsSource, content: &str) -> String {
+        let marker = source.block_marker();
+        HostsFile::parse(content, std::slice::from_ref(&marker))
+            .block(&marker.id)
+            .map(|b| b.body())
+            .unwrap_or_else(|| content.to_string())
+    }
+
+    pub async fn fetch_all(app: &AppHandle) -> Result<Vec<HostsSourceData>, String> {
+        let sources: Vec<HostsSource> = Self::sources(app)
+            .await
+            .into_iter()
+            .filter(|s| s.enabled)
+            .collect();
+        let mut tasks = JoinSet::new();
+        for (i, source) in sources.iter().cloned().enumerate() {
+            let app = app.clone();
+            tasks.spawn(async move { (i, Self::fetch(&app, &source).await) });
+        }
+        let mut results = Vec::new();
+        while let Some(res) = tasks.join_next().await {
+            results.push(res.map_err(|e| e.to_string())?);
+        }
+        results.sort_by_key(|(i, _)| *i);
+
+        let _guard = STORE_LOCK.lock().await;
+        let mut stored = Self::load_sources(app);
+        let mut data = Vec::new();
+        for (i, (content, cached, error)) in results {
+            let source = &sources[i];
+            let mut fetched_at = source.last_fetched;
+            if let Some(entry) = stored.iter_mut().find(|s| s.id == source.id) {
+                if error.is_none() {
+                    entry.last_fetched = Some(Local::now());
+                }
+                entry.last_error = error.clone();
+                fetched_at = entry.last_fetched;
+            }
+            let (date, categories) = match &content {
+                Some(text) => (
+                    Self::get_update_date(text),
+                    Self::get_categories(&Self::source_body(source, text)),
+                ),
+                None => ("Неизвестно".to_string(), Vec::new()),
+            };
+            data.push(HostsSourceData {
+                id: source.id.clone(),
+                name: source.name.clone(),
+                date,
+                categories,
+                cached,
+                fetched_at,
+                error,
+            });
+        }
+        Self::store_sources(app, &stored)?;
+        Ok(data)
+    }
+
+    // категории из последней удачной загрузки, без похода в сеть
+    pub fn cached_categories(app: &AppHandle, id: &str) -> Result<Vec<HostsCategory>, String> {
+        let source = Self::load_sources(app)
+            .into_iter()
+            .find(|s| s.id == id)
+            .ok_or_else(|| format!("неизвестный источник hosts: {}", id))?;
+        let content = fs::read_to_string(Self::cache_path(app, id))
+            .map_err(|_| format!("источник {} ещё не загружался", id))?;
+        Ok(Self::get_categories(&Self::source_body(&source, &content)))
+    }
+
+    fn markers(sources: &[HostsSource]) -> Vec<BlockMarker> {
+        sources.iter().map(|s| s.block_marker()).collect()
+    }
+
+    // нечитаемый hosts не трогаем, иначе перепишем его пустым
+    fn read_raw() -> Result<String, String> {
+        match fs::read(HostsPlatform::path()) {
+            Ok(bytes) => String::from_utf8(bytes)
+                .map_err(|_| "hosts не в UTF-8, поправьте его вручную".to_string()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(String::new()),
+            Err(e) => Err(format!("err read: {}", e)),
+        }
+    }
+
+    pub fn read(app: &AppHandle) -> Result<HostsFile, String> {
+        Ok(HostsFile::parse(
+            &Self::read_raw()?,
+            &Self::markers(&Self::load_sources(app)),
+        ))
+    }
+
+    pub fn conflicts(app: &AppHandle) -> Result<Vec<HostsConflict>, String> {
+        Ok(Self::read(app)?.conflicts())
+    }
+
+    fn load_selection(app: &AppHandle) -> HostsSelection {
+        fs::read_to_string(Zapret::zapret_path(app, SELECTION_FILE))
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn store_selection(app: &AppHandle, selection: &HostsSelection) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(selection).map_err(|e| e.to_string())?;
+        write_atomic(&Zapret::zapret_path(app, SELECTION_FILE), &json)
+    }
+
+    pub async fn selection(app: &AppHandle) -> HostsSelection {
+        let _guard = STORE_LOCK.lock().await;
+        Self::load_selection(app)
+    }
+
+    // тело блока источника: выбранные категории в порядке источника, без исключённых строк
+    fn selected_body(
+        app: &AppHandle,
+        source: &HostsSource,
+        selection: &HostsSelection,
+    ) -> Result<String, String> {
+        let ids = selection
+            .categories
+            .get(&source.id)
+            .cloned()
+            .unwrap_or_default();
+        if ids.is_empty() {
+            return Ok(String::new());
+        }
+        let excluded = selection.excluded.get(&source.id);
+        let categories = Self::cached_categories(app, &source.id)?;
+        for id in ids
+            .iter()
+            .filter(|id| !categories.iter().any(|c| &c.id == *id))
+        {
+            info(
+                app,
+                &format!("hosts {}: категории {} больше нет", source.id, id),
+            );
+        }
+        let mut lines = Vec::new();
+        for category in categories.iter().filter(|c| ids.contains(&c.id)) {
+            lines.push(format!("# {}", category.name));
+            lines.extend(
+                category
+                    .lines
+                    .iter()
+                    .filter(|l| excluded.is_none_or(|ex| !ex.contains(*l)))
+                    .cloned(),
+            );
+            lines.push(String::new());
+        }
+        Ok(lines.join("\n"))
+    }
+
+    fn selected_sources(
+        app: &AppHandle,
+        selection: &HostsSelection,
+    ) -> Result<Vec<HostsSource>, String> {
+        let sources = Self::load_sources(app);
+        if let Some(unknown) = selection
+            .categories
+            .keys()
+            .find(|id| !sources.iter().any(|s| &s.id == *id))
+        {
+            return Err(format!("неизвестный источник hosts: {}", unknown));
+        }
+        Ok(sources)
+    }
 
-        let text = response.text().await.map_err(|e| {
-            let err_msg = format!("err read: {}", e);
-            info(app, &err_msg);
-            err_msg
+    // (текущее содержимое, файл после замены блоков)
+    // источник без выбранных категорий - его блок убирается
+    fn planned(app: &AppHandle, selection: &HostsSelection) -> Result<(String, HostsFile), String> {
+        let sources = Self::selected_sources(app, selection)?;
+        let current = Self::read_raw()?;
+        let file = Self::with_blocks(&current, &sources, |source| {
+            Self::selected_body(app, source, selection)
         })?;
+        Ok((current, file))
+    }
 
-        Ok(text)
+    // блоки источников заменяются на body(source), остальное как было
+    fn with_blocks(
+        current: &str,
+        sources: &[HostsSource],
+        mut body: impl FnMut(&HostsSource) -> Result<String, String>,
+    ) -> Result<HostsFile, String> {
+        let mut file = HostsFile::parse(current, &Self::markers(sources));
+        for source in sources {
+            file.set_block(&source.block_marker(), &body(source)?);
+        }
+        Ok(file)
     }
 
-    pub fn clean(content: &str) -> String {
-        let pattern = format!(
-            r"(?s){}.*?{}",
-            regex::escape(Self::HOSTS_START_MARKER),
-            regex::escape(Self::HOSTS_MARKER_END)
-        );
-        let re = Regex::new(&pattern).unwrap();
-        re.replace_all(content, "").trim().to_string()
-    }
-
-    pub fn write(app: &AppHandle, new_data: &str) -> Result<(), String> {
-        let path = Self::get_path();
-        let current_content = fs::read_to_string(&path).unwrap_or_default();
-        if current_content.contains(Self::HOSTS_START_MARKER) {
-            info(app, "hosts block already exists, updating...");
-        } else {
-            info(app, "hosts block not found, creating new...");
-        }
-        let cleaned_base = Self::clean(&current_content);
-        let final_content = format!(
-            "{}\n{}\n{}\n\n{}",
-            Self::HOSTS_START_MARKER,
-            new_data.trim(),
-            Self::HOSTS_MARKER_END,
-            cleaned_base
+    pub fn preview(app: &AppHandle, selection: &HostsSelection) -> Result<HostsPreview, String> {
+        let (current, file) = Self::planned(app, selection)?;
+        Ok(HostsPreview {
+            diff: HostsDiff::between(&current, &file.render()),
+            conflicts: file.conflicts(),
+        })
+    }
+
+    pub fn flush_dns(app: &AppHandle) {
+        for result in HostsPlatform::flush_dns() {
+            info(app, &format!("flushdns {}", result));
+        }
+    }
+
+    // перед каждой записью кладём копию текущего файла.
+    // имя бэкапа, None - hosts уже такой и не трогали
+    fn replace(
+        backups: &HostsBackups,
+        current: &str,
+        content: &str,
+    ) -> Result<Option<String>, String> {
+        if current == content {
+            return Ok(None);
+        }
+        let backup = backups.create(current)?;
+        Self::write_raw(content)?;
+        Ok(Some(backup))
+    }
+
+    fn committed(app: &AppHandle, backup: Option<String>) {
+        match backup {
+            Some(backup) => {
+                info(app, &format!("hosts backup: {}", backup));
+                Self::flush_dns(app);
+            }
+            None => info(app, "hosts без изменений"),
+        }
+    }
+
+    fn commit(app: &AppHandle, current: &str, content: &str) -> Result<(), String> {
+        let backup = Self::replace(&HostsBackups::new(app), current, content)?;
+        Self::committed(app, backup);
+        Ok(())
+    }
+
+    // блоки источников в файле на диске заменяются на body(source), с бэкапом
+    fn apply_blocks(
+        backups: &HostsBackups,
+        sources: &[HostsSource],
+        body: impl FnMut(&HostsSource) -> Result<String, String>,
+    ) -> Result<(HostsFile, Option<String>), String> {
+        let current = Self::read_raw()?;
+        let file = Self::with_blocks(&current, sources, body)?;
+        let backup = Self::replace(backups, &current, &file.render())?;
+        Ok((file, backup))
+    }
+
+    // оборванная запись оставила бы систему без hosts
+    fn write_raw(content: &str) -> Result<(), String> {
+        write_atomic(&HostsPlatform::path(), content)
+    }
+
+    fn log_conflicts(app: &AppHandle, conflicts: &[HostsConflict]) {
+        for c in conflicts {
+            info(
+                app,
+                &format!("hosts conflict {}: работает {}", c.name, c.effective),
+            );
+        }
+    }
+
+    // меняются только блоки источников, остальной файл остаётся байт в байт
+    fn apply(app: &AppHandle, mut selection: HostsSelection) -> Result<Vec<HostsConflict>, String> {
+        let sources = Self::selected_sources(app, &selection)?;
+        let (file, backup) = Self::apply_blocks(&HostsBackups::new(app), &sources, |source| {
+            Self::selected_body(app, source, &selection)
+        })?;
+        Self::committed(app, backup);
+        info(
+            app,
+            &format!("hosts success: {}", file.block_ids().join(", ")),
         );
-        fs::write(&path, final_content).map_err(|e| format!("err write: {}", e))?;
+        selection.last_applied = Some(Local::now());
+        Self::store_selection(app, &selection)?;
+        let conflicts = file.conflicts();
+        Self::log_conflicts(app, &conflicts);
+        Ok(conflicts)
+    }
+
+    // выбор запоминается, планировщик потом переприменяет его к свежим источникам
+    pub async fn write(
+        app: &AppHandle,
+        selection: HostsSelection,
+    ) -> Result<Vec<HostsConflict>, String> {
+        let _guard = STORE_LOCK.lock().await;
+        Self::apply(app, selection)
+    }
+
+    // hosts перепишется, только если в выбранных категориях что-то поменялось
+    pub async fn reapply(app: &AppHandle) -> Result<(), String> {
+        if !Self::selection(app).await.is_due(Local::now()) {
+            return Ok(());
+        }
+        Self::fetch_all(app).await?;
+        let _guard = STORE_LOCK.lock().await;
+        // пока качали, выбор могли поменять из модалки
+        let selection = Self::load_selection(app);
+        if selection.is_empty() {
+            return Ok(());
+        }
+        info(app, "hosts: переприменяем выбор по расписанию");
+        Self::apply(app, selection).map(|_| ())
+    }
+
+    pub fn spawn_scheduler(app: AppHandle) {
+        tauri::async_runtime::spawn(async move {
+            loop {
+                tokio::time::sleep(CHECK_INTERVAL).await;
+                if let Err(e) = Self::reapply(&app).await {
+                    info(&app, &format!("hosts reapply err: {}", e));
+                }
+            }
+        });
+    }
+
+    pub fn custom_entries(app: &AppHandle) -> Result<Vec<String>, String> {
+        Ok(Self::read(app)?
+            .block(CUSTOM_ID)
+            .map(|b| b.lines.iter().map(|l| l.text.clone()).collect())
+            .unwrap_or_default())
+    }
+
+    // свои записи живут в отдельном блоке, выбор категорий их не затирает
+    pub fn set_custom_entries(
+        app: &AppHandle,
+        lines: &[String],
+    ) -> Result<Vec<HostsConflict>, String> {
+        let body = lines.join("\n");
+        let parsed = HostsFile::parse(&body, &[]);
+        if let Some(bad) = parsed.lines().find(|l| l.kind == LineKind::Invalid) {
+            return Err(format!("некорректная строка: {}", bad.text.trim()));
+        }
+        let current = Self::read_raw()?;
+        let mut file = HostsFile::parse(&current, &Self::markers(&Self::load_sources(app)));
+        file.set_block(&BlockMarker::zust(CUSTOM_ID), &body);
+        Self::commit(app, &current, &file.render())?;
+        let conflicts = file.conflicts();
+        Self::log_conflicts(app, &conflicts);
+        Ok(conflicts)
+    }
+
+    pub fn backups(app: &AppHandle) -> Vec<HostsBackup> {
+        HostsBackups::new(app).list()
+    }
+
+    pub fn preview_restore(app: &AppHandle, name: &str) -> Result<HostsPreview, String> {
+        let backup = HostsBackups::new(app).read(name)?;
+        Ok(HostsPreview {
+            diff: HostsDiff::between(&Self::read_raw()?, &backup),
+            conflicts: HostsFile::parse(&backup, &Self::markers(&Self::load_sources(app)))
+                .conflicts(),
+        })
+    }
 
-        let result = sh!("ipconfig", "/flushdns");
-        info(app, &format!("hosts success {:?}", result));
+    // текущий файл тоже уходит в бэкап, так что откат можно откатить
+    fn restore_from(backups: &HostsBackups, name: &str) -> Result<Option<String>, String> {
+        let backup = backups.read(name)?;
+        Self::replace(backups, &Self::read_raw()?, &backup)
+    }
+
+    pub fn restore(app: &AppHandle, name: &str) -> Result<(), String> {
+        let backup = Self::restore_from(&HostsBackups::new(app), name)?;
+        Self::committed(app, backup);
+        info(app, &format!("hosts восстановлен из {}", name));
         Ok(())
     }
 
@@ -83,41 +677,142 @@ impl Hosts {
             .unwrap_or_else(|| "Неизвестно".to_string())
     }
 
-    pub fn get_categories(content: &str) -> HashMap<String, Vec<String>> {
-        let mut map: HashMap<String, Vec<String>> = HashMap::new();
-        let mut inside_block = false;
+    fn category_id(name: &str) -> String {
+        format!("{:x}", Md5::digest(name.trim().to_lowercase()))[..12].to_string()
+    }
+
+    // body - содержимое блока источника, без маркеров
+    // категории в порядке появления, повторный заголовок дописывает в уже встреченную
+    pub fn get_categories(body: &str) -> Vec<HostsCategory> {
+        let mut categories: Vec<HostsCategory> = Vec::new();
         let mut current_category = "Базовая".to_string();
 
-        for line in content.lines() {
+        for line in body.lines() {
             let line = line.trim();
-            if line == Self::HOSTS_START_MARKER {
-                inside_block = true;
+            if line.is_empty() {
                 continue;
             }
-            if line == Self::HOSTS_MARKER_END {
-                break;
-            }
-
-            if inside_block && !line.is_empty() {
-                if line.starts_with('#') {
-                    let comment = line.trim_start_matches('#').trim();
-                    if comment.contains("Последнее обновление") || comment.is_empty()
-                    {
-                        continue;
-                    }
-
-                    current_category = if comment.to_lowercase().starts_with("базов") {
-                        "Базовая".to_string()
-                    } else {
-                        comment.to_string()
-                    };
+            if line.starts_with('#') {
+                let comment = line.trim_start_matches('#').trim();
+                if comment.contains("Последнее обновление") || comment.is_empty()
+                {
                     continue;
                 }
-                map.entry(current_category.clone())
-                    .or_default()
-                    .push(line.to_string());
+
+                current_category = if comment.to_lowercase().starts_with("базов") {
+                    "Базовая".to_string()
+                } else {
+                    comment.to_string()
+                };
+                continue;
+            }
+            match categories.iter_mut().find(|c| c.name == current_category) {
+                Some(category) => category.lines.push(line.to_string()),
+                None => categories.push(HostsCategory {
+                    id: Self::category_id(&current_category),
+                    name: current_category.clone(),
+                    lines: vec![line.to_string()],
+                }),
             }
         }
-        map
+        categories
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Mutex as StdMutex, MutexGuard, OnceLock};
+
+    // переменная окружения одна на процесс, тесты с файлом идут по очереди
+    static HOSTS_LOCK: StdMutex<()> = StdMutex::new(());
+    static HOSTS_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+    fn temp_hosts(content: Option<&[u8]>) -> (MutexGuard<'static, ()>, PathBuf) {
+        let guard = HOSTS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let path = HOSTS_PATH
+            .get_or_init(|| {
+                let path = std::env::temp_dir().join(format!("zust-hosts-{}", std::process::id()));
+                // SAFETY: ставится один раз, до первого чтения HostsPlatform::path()
+                unsafe { std::env::set_var("ZUST_HOSTS_PATH", &path) };
+                path
+            })
+            .clone();
+        let _ = fs::remove_file(&path);
+        if let Some(content) = content {
+            fs::write(&path, content).unwrap();
+        }
+        (guard, path)
+    }
+
+    fn source(id: &str) -> HostsSource {
+        HostsSource {
+            id: id.to_string(),
+            name: id.to_string(),
+            url: String::new(),
+            enabled: true,
+            marker: None,
+            last_fetched: None,
+            last_error: None,
+        }
+    }
+
+    #[test]
+    fn path_comes_from_env() {
+        let (_guard, path) = temp_hosts(None);
+        assert_eq!(HostsPlatform::path(), path);
+    }
+
+    #[test]
+    fn apply_and_restore_keep_user_content() {
+        let original = "\u{feff}127.0.0.1 localhost\r\n# свой комментарий\r\n1.1.1.1 my.test";
+        let (_guard, path) = temp_hosts(Some(original.as_bytes()));
+        let dir = path.with_extension("backups");
+        let _ = fs::remove_dir_all(&dir);
+        let backups = HostsBackups::at(dir.clone());
+        let sources = [source("test")];
+        let apply =
+            |body: &str| Hosts::apply_blocks(&backups, &sources, |_| Ok(body.to_string())).unwrap();
+
+        let (file, before_apply) = apply("1.2.3.4 a.com");
+        let applied = format!(
+            "{}\r\n# zust: begin test\r\n1.2.3.4 a.com\r\n# zust: end test",
+            original
+        );
+        assert_eq!(file.render(), applied);
+        assert_eq!(Hosts::read_raw().unwrap(), applied);
+        let before_apply = before_apply.expect("hosts должен был измениться");
+        assert_eq!(backups.read(&before_apply).unwrap(), original);
+
+        // без изменений ни записи, ни бэкапа
+        assert!(apply("1.2.3.4 a.com").1.is_none());
+        assert!(apply("5.6.7.8 b.com").1.is_some());
+        assert_eq!(backups.list().len(), 2);
+
+        let before_restore = Hosts::restore_from(&backups, &before_apply)
+            .unwrap()
+            .expect("откат должен был изменить hosts");
+        assert_eq!(fs::read(&path).unwrap(), original.as_bytes());
+        assert!(!path.with_extension("tmp").exists());
+
+        // откат тоже откатывается
+        Hosts::restore_from(&backups, &before_restore).unwrap();
+        assert_eq!(
+            Hosts::read_raw().unwrap(),
+            applied.replace("1.2.3.4 a.com", "5.6.7.8 b.com")
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn missing_file_reads_empty() {
+        let _guard = temp_hosts(None);
+        assert_eq!(Hosts::read_raw().unwrap(), "");
+    }
+
+    #[test]
+    fn non_utf8_file_is_refused() {
+        let _guard = temp_hosts(Some(&[0xff, 0xfe, 0x00, b'\n']));
+        assert!(Hosts::read_raw().is_err());
     }
 }
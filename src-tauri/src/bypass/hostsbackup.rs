@@ -0,0 +1,123 @@
+#[cfg(not(windows))]
+use crate::bypass::zapret::Zapret;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::AppHandle;
+#[cfg(windows)]
+use tauri::Manager;
+
+const BACKUP_DIR: &str = "hosts-backups";
+const BACKUP_PREFIX: &str = "hosts-";
+const BACKUP_EXT: &str = ".bak";
+const BACKUP_DATE: &str = "%Y%m%d-%H%M%S%.3f";
+// старые удаляем, hosts бывает на мегабайт
+const MAX_BACKUPS: usize = 30;
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HostsBackup {
+    pub name: String,
+    pub created_at: Option<DateTime<Local>>,
+    pub size: u64,
+}
+
+// папка с бэкапами; в тестах любая временная
+pub struct HostsBackups {
+    dir: PathBuf,
+}
+
+impl HostsBackups {
+    pub fn new(app: &AppHandle) -> Self {
+        Self::at(Self::dir(app))
+    }
+
+    pub fn at(dir: PathBuf) -> Self {
+        HostsBackups { dir }
+    }
+
+    #[cfg(windows)]
+    fn dir(app: &AppHandle) -> PathBuf {
+        let mut dir = app.path().executable_dir().unwrap_or_else(|_| {
+            let mut p = std::env::current_exe().unwrap_or_default();
+            p.pop();
+            p
+        });
+        dir.push(BACKUP_DIR);
+        dir
+    }
+
+    // рядом с бинарником в /usr/bin писать нельзя
+    #[cfg(not(windows))]
+    fn dir(app: &AppHandle) -> PathBuf {
+        Zapret::zapret_path(app, BACKUP_DIR)
+    }
+
+    fn created_at(name: &str) -> Option<DateTime<Local>> {
+        let stamp = name.strip_prefix(BACKUP_PREFIX)?.strip_suffix(BACKUP_EXT)?;
+        let naive = NaiveDateTime::parse_from_str(stamp, BACKUP_DATE).ok()?;
+        Local.from_local_datetime(&naive).single()
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf, String> {
+        if name.contains(['/', '\\']) || Self::created_at(name).is_none() {
+            return Err(format!("некорректное имя бэкапа: {}", name));
+        }
+        Ok(self.dir.join(name))
+    }
+
+    pub fn create(&self, content: &str) -> Result<String, String> {
+        let dir = &self.dir;
+        fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        // две записи подряд укладываются в одну миллисекунду, старый бэкап не затираем
+        let name = loop {
+            let name = format!(
+                "{}{}{}",
+                BACKUP_PREFIX,
+                Local::now().format(BACKUP_DATE),
+                BACKUP_EXT
+            );
+            if !dir.join(&name).exists() {
+                break name;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        };
+        fs::write(dir.join(&name), content).map_err(|e| format!("{}: {}", name, e))?;
+        self.prune();
+        Ok(name)
+    }
+
+    // свежие сверху
+    pub fn list(&self) -> Vec<HostsBackup> {
+        let mut backups: Vec<HostsBackup> = fs::read_dir(&self.dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok())
+                    .filter_map(|e| {
+                        let name = e.file_name().to_string_lossy().into_owned();
+                        let created_at = Self::created_at(&name)?;
+                        Some(HostsBackup {
+                            name,
+                            created_at: Some(created_at),
+                            size: e.metadata().map(|m| m.len()).unwrap_or_default(),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        backups.sort_by_key(|b| std::cmp::Reverse(b.created_at));
+        backups
+    }
+
+    pub fn read(&self, name: &str) -> Result<String, String> {
+        fs::read_to_string(self.path(name)?).map_err(|e| format!("{}: {}", name, e))
+    }
+
+    fn prune(&self) {
+        for old in self.list().iter().skip(MAX_BACKUPS) {
+            let _ = fs::remove_file(self.dir.join(&old.name));
+        }
+    }
+}
@@ -0,0 +1,645 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+// блоки, которые пишет сам zust: "# zust: begin <id>" ... "# zust: end <id>"
+const ZUST_BEGIN: &str = "# zust: begin ";
+const ZUST_END: &str = "# zust: end ";
+// блокнот любит сохранять hosts с BOM
+const BOM: char = '\u{feff}';
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockMarker {
+    pub id: String,
+    pub begin: String,
+    pub end: String,
+}
+
+impl BlockMarker {
+    pub fn zust(id: &str) -> Self {
+        BlockMarker {
+            id: id.to_string(),
+            begin: format!("{}{}", ZUST_BEGIN, id),
+            end: format!("{}{}", ZUST_END, id),
+        }
+    }
+
+    // чужой формат маркеров, например dns.malw.link
+    pub fn custom(id: &str, begin: &str, end: &str) -> Self {
+        BlockMarker {
+            id: id.to_string(),
+            begin: begin.to_string(),
+            end: end.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HostsEntry {
+    pub ip: IpAddr,
+    pub names: Vec<String>,
+    pub comment: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LineKind {
+    Blank,
+    Comment(String),
+    Entry(HostsEntry),
+    // строка, которую не поняли, но трогать не будем
+    Invalid,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HostsLine {
+    // без перевода строки
+    pub text: String,
+    // "\r\n", "\n" или "" у последней строки без перевода
+    pub ending: String,
+    pub kind: LineKind,
+}
+
+impl HostsLine {
+    pub fn parse(raw: &str) -> Self {
+        let (text, ending) = match raw.strip_suffix("\r\n") {
+            Some(t) => (t, "\r\n"),
+            None => match raw.strip_suffix('\n') {
+                Some(t) => (t, "\n"),
+                None => (raw, ""),
+            },
+        };
+        HostsLine {
+            text: text.to_string(),
+            ending: ending.to_string(),
+            kind: Self::kind(text),
+        }
+    }
+
+    fn kind(text: &str) -> LineKind {
+        let trimmed = text.trim();
+        if trimmed.is_empty() {
+            return LineKind::Blank;
+        }
+        if let Some(comment) = trimmed.strip_prefix('#') {
+            return LineKind::Comment(comment.trim().to_string());
+        }
+        let (data, comment) = match trimmed.split_once('#') {
+            Some((data, comment)) => (data, Some(comment.trim().to_string())),
+            None => (trimmed, None),
+        };
+        let mut parts = data.split_whitespace();
+        let ip = parts.next().and_then(|ip| ip.parse::<IpAddr>().ok());
+        let names: Vec<String> = parts.map(|n| n.to_lowercase()).collect();
+        match ip {
+            Some(ip) if !names.is_empty() => LineKind::Entry(HostsEntry { ip, names, comment }),
+            _ => LineKind::Invalid,
+        }
+    }
+
+    pub fn entry(&self) -> Option<&HostsEntry> {
+        match &self.kind {
+            LineKind::Entry(entry) => Some(entry),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ManagedBlock {
+    pub id: String,
+    pub begin: HostsLine,
+    pub lines: Vec<HostsLine>,
+    pub end: HostsLine,
+}
+
+impl ManagedBlock {
+    // тело блока без маркеров, с переводами строк как в файле
+    pub fn body(&self) -> String {
+        self.lines
+            .iter()
+            .map(|l| format!("{}{}", l.text, l.ending))
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Section {
+    User(HostsLine),
+    Managed(ManagedBlock),
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictEntry {
+    pub ip: IpAddr,
+    // с 1, как в блокноте
+    pub line: usize,
+    // None - запись пользователя
+    pub block: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HostsConflict {
+    pub name: String,
+    pub entries: Vec<ConflictEntry>,
+    // система берёт первую запись в файле
+    pub effective: IpAddr,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HostsFile {
+    pub sections: Vec<Section>,
+    // для новых строк берём перевод, который уже преобладает в файле
+    pub ending: String,
+    // при записи возвращаем на место, иначе маркер первой строки не узнать
+    pub bom: bool,
+}
+
+impl HostsFile {
+    pub fn parse(content: &str, markers: &[BlockMarker]) -> Self {
+        let (content, bom) = match content.strip_prefix(BOM) {
+            Some(rest) => (rest, true),
+            None => (content, false),
+        };
+        let lines: Vec<HostsLine> = content
+            .split_inclusive('\n')
+            .map(HostsLine::parse)
+            .collect();
+        let mut sections = Vec::new();
+        let mut i = 0;
+        while i < lines.len() {
+            let text = lines[i].text.trim();
+            let marker = markers
+                .iter()
+                .find(|m| m.begin == text)
+                .cloned()
+                .or_else(|| {
+                    text.strip_prefix(ZUST_BEGIN)
+                        .map(|id| BlockMarker::zust(id.trim()))
+                });
+            // без закрывающего маркера блок не считаем своим, чтобы не снести чужие строки
+            let end = marker.as_ref().and_then(|m| {
+                lines[i + 1..]
+                    .iter()
+                    .position(|l| l.text.trim() == m.end)
+                    .map(|p| i + 1 + p)
+            });
+            match (marker, end) {
+                (Some(marker), Some(end)) => {
+                    sections.push(Section::Managed(ManagedBlock {
+                        id: marker.id,
+                        begin: lines[i].clone(),
+                        lines: lines[i + 1..end].to_vec(),
+                        end: lines[end].clone(),
+                    }));
+                    i = end + 1;
+                }
+                _ => {
+                    sections.push(Section::User(lines[i].clone()));
+                    i += 1;
+                }
+            }
+        }
+        HostsFile {
+            sections,
+            ending: Self::detect_ending(content).to_string(),
+            bom,
+        }
+    }
+
+    fn detect_ending(content: &str) -> &'static str {
+        let crlf = content.matches("\r\n").count();
+        let lf = content.matches('\n').count() - crlf;
+        if crlf > lf || (crlf == 0 && lf == 0 && cfg!(windows)) {
+            "\r\n"
+        } else {
+            "\n"
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        if self.bom {
+            out.push(BOM);
+        }
+        for line in self.lines() {
+            out.push_str(&line.text);
+            out.push_str(&line.ending);
+        }
+        out
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = &HostsLine> {
+        self.sections.iter().flat_map(|s| match s {
+            Section::User(line) => vec![line],
+            Section::Managed(block) => std::iter::once(&block.begin)
+                .chain(block.lines.iter())
+                .chain(std::iter::once(&block.end))
+                .collect(),
+        })
+    }
+
+    pub fn block(&self, id: &str) -> Option<&ManagedBlock> {
+        self.sections.iter().find_map(|s| match s {
+            Section::Managed(block) if block.id == id => Some(block),
+            _ => None,
+        })
+    }
+
+    pub fn block_ids(&self) -> Vec<String> {
+        self.sections
+            .iter()
+            .filter_map(|s| match s {
+                Section::Managed(block) => Some(block.id.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn new_line(&self, text: &str) -> HostsLine {
+        HostsLine {
+            text: text.to_string(),
+            ending: self.ending.clone(),
+            kind: HostsLine::kind(text),
+        }
+    }
+
+    // заменяет блок на месте или дописывает в конец; пустое тело убирает блок.
+    // если у файла не было перевода в конце, его нет и после блока: пустой end.ending
+    // и есть запись о вставленном разделителе, remove_block по ней его уберёт
+    pub fn set_block(&mut self, marker: &BlockMarker, body: &str) {
+        if body.trim().is_empty() {
+            self.remove_block(&marker.id);
+            return;
+        }
+        let mut block = ManagedBlock {
+            id: marker.id.clone(),
+            begin: self.new_line(&marker.begin),
+            lines: body
+                .trim()
+                .lines()
+                .map(|l| self.new_line(l.trim_end()))
+                .collect(),
+            end: self.new_line(&marker.end),
+        };
+        let existing = self
+            .sections
+            .iter()
+            .position(|s| matches!(s, Section::Managed(b) if b.id == marker.id));
+        match existing {
+            Some(pos) => {
+                if let Section::Managed(old) = &self.sections[pos] {
+                    block.end.ending = old.end.ending.clone();
+                }
+                self.sections[pos] = Section::Managed(block);
+            }
+            None => {
+                // последняя строка без перевода - иначе маркер приклеится к ней
+                let ending = self.ending.clone();
+                if let Some(last) = self.lines_mut().last()
+                    && last.ending.is_empty()
+                {
+                    last.ending = ending;
+                    block.end.ending.clear();
+                }
+                self.sections.push(Section::Managed(block));
+            }
+        }
+    }
+
+    pub fn remove_block(&mut self, id: &str) -> bool {
+        let Some(pos) = self
+            .sections
+            .iter()
+            .position(|s| matches!(s, Section::Managed(b) if b.id == id))
+        else {
+            return false;
+        };
+        let removed = self.sections.remove(pos);
+        // блок был последним и забрал себе конец файла без перевода - возвращаем как было
+        if pos == self.sections.len()
+            && matches!(&removed, Section::Managed(b) if b.end.ending.is_empty())
+            && let Some(last) = self.lines_mut().last()
+        {
+            last.ending.clear();
+        }
+        true
+    }
+
+    fn lines_mut(&mut self) -> impl Iterator<Item = &mut HostsLine> {
+        self.sections.iter_mut().flat_map(|s| match s {
+            Section::User(line) => vec![line],
+            Section::Managed(block) => std::iter::once(&mut block.begin)
+                .chain(block.lines.iter_mut())
+                .chain(std::iter::once(&mut block.end))
+                .collect(),
+        })
+    }
+
+    // (номер строки, блок, запись)
+    pub fn entries(&self) -> Vec<(usize, Option<&str>, &HostsEntry)> {
+        let mut out = Vec::new();
+        let mut line_no = 0;
+        for section in &self.sections {
+            match section {
+                Section::User(line) => {
+                    line_no += 1;
+                    if let Some(entry) = line.entry() {
+                        out.push((line_no, None, entry));
+                    }
+                }
+                Section::Managed(block) => {
+                    line_no += 1;
+                    for line in &block.lines {
+                        line_no += 1;
+                        if let Some(entry) = line.entry() {
+                            out.push((line_no, Some(block.id.as_str()), entry));
+                        }
+                    }
+                    line_no += 1;
+                }
+            }
+        }
+        out
+    }
+
+    // одно имя с разными IP одного семейства; одинаковые дубли не конфликт
+    pub fn conflicts(&self) -> Vec<HostsConflict> {
+        let mut by_name: HashMap<(String, bool), Vec<ConflictEntry>> = HashMap::new();
+        let mut order = Vec::new();
+        for (line, block, entry) in self.entries() {
+            for name in &entry.names {
+                let key = (name.clone(), entry.ip.is_ipv4());
+                let list = by_name.entry(key.clone()).or_default();
+                if list.is_empty() {
+                    order.push(key);
+                }
+                list.push(ConflictEntry {
+                    ip: entry.ip,
+                    line,
+                    block: block.map(str::to_string),
+                });
+            }
+        }
+        order
+            .into_iter()
+            .filter_map(|key| {
+                let entries = by_name.remove(&key)?;
+                let effective = entries.first()?.ip;
+                if entries.iter().all(|e| e.ip == effective) {
+                    return None;
+                }
+                Some(HostsConflict {
+                    name: key.0,
+                    entries,
+                    effective,
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum DiffOp {
+    Context,
+    Add,
+    Remove,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffLine {
+    pub op: DiffOp,
+    // номер в старом файле для Remove/Context, в новом для Add
+    pub line: usize,
+    pub text: String,
+}
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HostsDiff {
+    pub lines: Vec<DiffLine>,
+    pub added: usize,
+    pub removed: usize,
+}
+
+const DIFF_CONTEXT: usize = 2;
+
+impl HostsDiff {
+    // отрезаем общие начало и конец, середину сравниваем как мультимножества:
+    // порядок строк в hosts почти никогда не важен, а LCS на тысячах строк дорогой
+    pub fn between(old: &str, new: &str) -> Self {
+        let old: Vec<&str> = old.split_inclusive('\n').collect();
+        let new: Vec<&str> = new.split_inclusive('\n').collect();
+        let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let (old_mid, new_mid) = (
+            &old[prefix..old.len() - suffix],
+            &new[prefix..new.len() - suffix],
+        );
+        if old_mid.is_empty() && new_mid.is_empty() {
+            return HostsDiff::default();
+        }
+
+        let mut left: HashMap<&str, usize> = HashMap::new();
+        new_mid
+            .iter()
+            .for_each(|l| *left.entry(l).or_default() += 1);
+        let mut removed = Vec::new();
+        for (i, l) in old_mid.iter().enumerate() {
+            match left.get_mut(l) {
+                Some(n) if *n > 0 => *n -= 1,
+                _ => removed.push((prefix + i + 1, *l)),
+            }
+        }
+        let mut kept: HashMap<&str, usize> = HashMap::new();
+        old_mid
+            .iter()
+            .for_each(|l| *kept.entry(l).or_default() += 1);
+        let mut added = Vec::new();
+        for (i, l) in new_mid.iter().enumerate() {
+            match kept.get_mut(l) {
+                Some(n) if *n > 0 => *n -= 1,
+                _ => added.push((prefix + i + 1, *l)),
+            }
+        }
+
+        let text = |l: &str| l.trim_end_matches(['\r', '\n']).to_string();
+        let mut lines: Vec<DiffLine> = (prefix.saturating_sub(DIFF_CONTEXT)..prefix)
+            .map(|i| DiffLine {
+                op: DiffOp::Context,
+                line: i + 1,
+                text: text(old[i]),
+            })
+            .collect();
+        lines.extend(removed.iter().map(|(line, l)| DiffLine {
+            op: DiffOp::Remove,
+            line: *line,
+            text: text(l),
+        }));
+        lines.extend(added.iter().map(|(line, l)| DiffLine {
+            op: DiffOp::Add,
+            line: *line,
+            text: text(l),
+        }));
+        let after = old.len() - suffix;
+        lines.extend(
+            (after..(after + DIFF_CONTEXT).min(old.len())).map(|i| DiffLine {
+                op: DiffOp::Context,
+                line: i + 1,
+                text: text(old[i]),
+            }),
+        );
+        HostsDiff {
+            lines,
+            added: added.len(),
+            removed: removed.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "# zust: begin source\n1.2.3.4 a.com\n# zust: end source\n";
+
+    fn round_trip(content: &str) {
+        assert_eq!(HostsFile::parse(content, &[]).render(), content);
+    }
+
+    #[test]
+    fn untouched_file_renders_byte_for_byte() {
+        round_trip("127.0.0.1 localhost\n# comment\n\n1.2.3.4 a.com\n");
+        round_trip("127.0.0.1 localhost\r\n# comment\r\n\r\n1.2.3.4 a.com\r\n");
+        round_trip("127.0.0.1 localhost\r\n1.2.3.4 a.com\n");
+        round_trip("127.0.0.1 localhost\n1.2.3.4 a.com");
+        round_trip("\u{feff}127.0.0.1 localhost\r\n");
+        round_trip("");
+    }
+
+    #[test]
+    fn new_block_keeps_file_line_endings() {
+        let mut file = HostsFile::parse("127.0.0.1 localhost\r\n", &[]);
+        file.set_block(&BlockMarker::zust("source"), "1.2.3.4 a.com");
+        assert_eq!(
+            file.render(),
+            "127.0.0.1 localhost\r\n# zust: begin source\r\n1.2.3.4 a.com\r\n# zust: end source\r\n"
+        );
+    }
+
+    #[test]
+    fn missing_trailing_newline_is_not_glued_to_marker() {
+        let mut file = HostsFile::parse("127.0.0.1 localhost", &[]);
+        file.set_block(&BlockMarker::zust("source"), "1.2.3.4 a.com");
+        let applied = format!("127.0.0.1 localhost\n{}", SOURCE.trim_end());
+        assert_eq!(file.render(), applied);
+
+        // после перечитывания с диска разделитель всё ещё убирается вместе с блоком
+        let mut file = HostsFile::parse(&applied, &[]);
+        file.set_block(&BlockMarker::zust("source"), "5.6.7.8 b.com");
+        assert!(file.render().ends_with("# zust: end source"));
+        file.remove_block("source");
+        assert_eq!(file.render(), "127.0.0.1 localhost");
+    }
+
+    #[test]
+    fn removing_blocks_restores_file_byte_for_byte() {
+        for original in [
+            "",
+            "127.0.0.1 localhost\n",
+            "127.0.0.1 localhost\r\n1.1.1.1 my.test",
+        ] {
+            let mut file = HostsFile::parse(original, &[]);
+            file.set_block(&BlockMarker::zust("a"), "1.2.3.4 a.com");
+            file.set_block(&BlockMarker::zust("b"), "5.6.7.8 b.com");
+            let mut file = HostsFile::parse(&file.render(), &[]);
+            file.remove_block("b");
+            file.remove_block("a");
+            assert_eq!(file.render(), original);
+        }
+    }
+
+    #[test]
+    fn bom_does_not_hide_first_marker() {
+        let content = format!("\u{feff}{}127.0.0.1 localhost\n", SOURCE);
+        let mut file = HostsFile::parse(&content, &[]);
+        assert_eq!(file.block_ids(), vec!["source".to_string()]);
+        file.set_block(&BlockMarker::zust("source"), "5.6.7.8 b.com");
+        assert_eq!(
+            file.render(),
+            "\u{feff}# zust: begin source\n5.6.7.8 b.com\n# zust: end source\n127.0.0.1 localhost\n"
+        );
+        file.remove_block("source");
+        assert_eq!(file.render(), "\u{feff}127.0.0.1 localhost\n");
+    }
+
+    fn entry(ip: &str, line: usize, block: Option<&str>) -> ConflictEntry {
+        ConflictEntry {
+            ip: ip.parse().unwrap(),
+            line,
+            block: block.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn conflict_between_two_blocks() {
+        let content = "# zust: begin a\n1.1.1.1 x.com\n# zust: end a\n\
+                       # zust: begin b\n2.2.2.2 X.com y.com\n# zust: end b\n";
+        let conflicts = HostsFile::parse(content, &[]).conflicts();
+        assert_eq!(
+            conflicts,
+            vec![HostsConflict {
+                name: "x.com".to_string(),
+                entries: vec![
+                    entry("1.1.1.1", 2, Some("a")),
+                    entry("2.2.2.2", 5, Some("b"))
+                ],
+                effective: "1.1.1.1".parse().unwrap(),
+            }]
+        );
+    }
+
+    #[test]
+    fn user_line_above_block_wins() {
+        let content = "127.0.0.1 localhost\n0.0.0.0 x.com # мой\n\
+                       # zust: begin a\n1.1.1.1 x.com\n# zust: end a\n";
+        let conflicts = HostsFile::parse(content, &[]).conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(
+            conflicts[0].entries,
+            vec![entry("0.0.0.0", 2, None), entry("1.1.1.1", 4, Some("a"))]
+        );
+        assert_eq!(conflicts[0].effective, "0.0.0.0".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn block_above_user_line_wins() {
+        let content = "# zust: begin a\n1.1.1.1 x.com\n# zust: end a\n0.0.0.0 x.com\n";
+        let conflicts = HostsFile::parse(content, &[]).conflicts();
+        assert_eq!(conflicts[0].effective, "1.1.1.1".parse::<IpAddr>().unwrap());
+        assert_eq!(conflicts[0].entries[1], entry("0.0.0.0", 4, None));
+    }
+
+    #[test]
+    fn same_ip_or_other_family_is_not_conflict() {
+        let content = "1.1.1.1 x.com\n# zust: begin a\n1.1.1.1 x.com\n::1 x.com\n# zust: end a\n";
+        assert!(HostsFile::parse(content, &[]).conflicts().is_empty());
+    }
+
+    #[test]
+    fn unclosed_marker_stays_user_content() {
+        let content = "# zust: begin source\n1.2.3.4 a.com\n";
+        let file = HostsFile::parse(content, &[]);
+        assert!(file.block_ids().is_empty());
+        assert_eq!(file.render(), content);
+    }
+}
@@ -0,0 +1,174 @@
+use crate::bypass::hosts::Hosts;
+use crate::bypass::hostsfile::HostsLine;
+use crate::utils::*;
+use serde::Serialize;
+use std::error::Error;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::AppHandle;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+const PARALLEL: usize = 16;
+const TIMEOUT: Duration = Duration::from_secs(6);
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum EntryStatus {
+    Ok,
+    // TCP/TLS не поднялся или таймаут
+    Dead,
+    // сервер отвечает, но сертификат не на этот домен
+    Mismatch,
+    // сертификат на домен, но не прошёл проверку (просрочен, чужой CA)
+    BadCertificate,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryHealth {
+    pub category: String,
+    // строка целиком, по ней фронт исключает запись из выбора
+    pub line: String,
+    pub ip: IpAddr,
+    pub name: String,
+    pub status: EntryStatus,
+    pub detail: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HostsHealthReport {
+    pub source: String,
+    pub checked: usize,
+    pub ok: usize,
+    // 0.0.0.0 и прочие заглушки, проверять нечего
+    pub skipped: usize,
+    pub problems: Vec<EntryHealth>,
+}
+
+pub struct HostsHealth;
+
+impl HostsHealth {
+    fn is_sink(ip: &IpAddr) -> bool {
+        ip.is_unspecified() || ip.is_loopback()
+    }
+
+    fn error_chain(e: &reqwest::Error) -> String {
+        let mut chain = e.to_string();
+        let mut source = e.source();
+        while let Some(s) = source {
+            chain.push_str(": ");
+            chain.push_str(&s.to_string());
+            source = s.source();
+        }
+        chain
+    }
+
+    // rustls и platform verifier пишут ошибки по-разному, смотрим текст всей цепочки
+    fn classify(e: &reqwest::Error) -> (EntryStatus, String) {
+        let chain = Self::error_chain(e);
+        let lower = chain.to_lowercase();
+        let status = if e.is_timeout() {
+            EntryStatus::Dead
+        } else if lower.contains("not valid for name")
+            || lower.contains("notvalidforname")
+            || lower.contains("name mismatch")
+        {
+            EntryStatus::Mismatch
+        } else if lower.contains("certificate") {
+            EntryStatus::BadCertificate
+        } else {
+            EntryStatus::Dead
+        };
+        (status, chain)
+    }
+
+    // TLS до закреплённого IP с доменом в SNI, сертификат проверяет reqwest
+    pub async fn probe(ip: IpAddr, name: &str) -> (EntryStatus, Option<String>) {
+        let client = match reqwest::Client::builder()
+            .resolve(name, SocketAddr::new(ip, 443))
+            .connect_timeout(TIMEOUT)
+            .timeout(TIMEOUT * 2)
+            .redirect(reqwest::redirect::Policy::none())
+            .no_proxy()
+            .build()
+        {
+            Ok(c) => c,
+            Err(e) => return (EntryStatus::Dead, Some(e.to_string())),
+        };
+        // рукопожатие прошло, дальше HTTP может что угодно: ответить, зависнуть, оборвать
+        match client.head(format!("https://{}/", name)).send().await {
+            Ok(_) => (EntryStatus::Ok, None),
+            Err(e) if !e.is_connect() => (EntryStatus::Ok, None),
+            Err(e) => {
+                let (status, detail) = Self::classify(&e);
+                (status, Some(detail))
+            }
+        }
+    }
+
+    pub async fn check(app: &AppHandle, source_id: &str) -> Result<HostsHealthReport, String> {
+        let categories = Hosts::cached_categories(app, source_id)?;
+        let mut report = HostsHealthReport {
+            source: source_id.to_string(),
+            ..Default::default()
+        };
+
+        let limit = Arc::new(Semaphore::new(PARALLEL));
+        let mut tasks = JoinSet::new();
+        for category in categories {
+            for line in category.lines {
+                let Some(entry) = HostsLine::parse(&line).entry().cloned() else {
+                    continue;
+                };
+                let ip = entry.ip;
+                for name in entry.names {
+                    report.checked += 1;
+                    if Self::is_sink(&ip) {
+                        report.skipped += 1;
+                        continue;
+                    }
+                    let (category, line, limit) =
+                        (category.name.clone(), line.clone(), limit.clone());
+                    tasks.spawn(async move {
+                        let _permit = limit.acquire_owned().await;
+                        let (status, detail) = Self::probe(ip, &name).await;
+                        EntryHealth {
+                            category,
+                            line,
+                            ip,
+                            name,
+                            status,
+                            detail,
+                        }
+                    });
+                }
+            }
+        }
+
+        while let Some(res) = tasks.join_next().await {
+            let health = res.map_err(|e| e.to_string())?;
+            if health.status == EntryStatus::Ok {
+                report.ok += 1;
+            } else {
+                report.problems.push(health);
+            }
+        }
+        report
+            .problems
+            .sort_by(|a, b| (&a.category, &a.name).cmp(&(&b.category, &b.name)));
+        info(
+            app,
+            &format!(
+                "hosts health {}: {} ок, {} проблем, {} пропущено",
+                source_id,
+                report.ok,
+                report.problems.len(),
+                report.skipped
+            ),
+        );
+        Ok(report)
+    }
+}
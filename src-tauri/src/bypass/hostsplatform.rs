@@ -0,0 +1,60 @@
+#[cfg(any(windows, target_os = "linux"))]
+use crate::sh;
+use std::env;
+use std::path::PathBuf;
+
+// свой путь к hosts: тесты и нестандартные системы
+const PATH_ENV: &str = "ZUST_HOSTS_PATH";
+
+pub struct HostsPlatform;
+
+impl HostsPlatform {
+    pub fn path() -> PathBuf {
+        env::var_os(PATH_ENV)
+            .filter(|p| !p.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(Self::system_path)
+    }
+
+    #[cfg(windows)]
+    fn system_path() -> PathBuf {
+        let sys_root = env::var("SystemRoot").unwrap_or_else(|_| r"C:\Windows".to_string());
+        PathBuf::from(sys_root).join(r"System32\drivers\etc\hosts")
+    }
+
+    #[cfg(not(windows))]
+    fn system_path() -> PathBuf {
+        PathBuf::from("/etc/hosts")
+    }
+
+    // строки для лога, по одной на команду
+    #[cfg(windows)]
+    pub fn flush_dns() -> Vec<String> {
+        vec![format!(
+            "ipconfig /flushdns: {:?}",
+            sh!("ipconfig", "/flushdns")
+        )]
+    }
+
+    // systemd-resolved и nscd кешируют hosts, glibc без них читает файл сам
+    #[cfg(target_os = "linux")]
+    pub fn flush_dns() -> Vec<String> {
+        let resolved = match sh!("resolvectl", "flush-caches") {
+            Ok(status) => format!("resolvectl flush-caches: {}", status),
+            // старые systemd без resolvectl
+            Err(_) => format!(
+                "systemd-resolve --flush-caches: {:?}",
+                sh!("systemd-resolve", "--flush-caches")
+            ),
+        };
+        vec![
+            resolved,
+            format!("nscd -i hosts: {:?}", sh!("nscd", "-i", "hosts")),
+        ]
+    }
+
+    #[cfg(not(any(windows, target_os = "linux")))]
+    pub fn flush_dns() -> Vec<String> {
+        Vec::new()
+    }
+}
@@ -0,0 +1,498 @@
+use crate::bypass::hostlist::Hostlist;
+use crate::utils::write_atomic;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+use std::str::FromStr;
+
+// подсеть, биты хоста всегда обнулены
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Family {
+    V4,
+    V6,
+}
+
+impl Family {
+    fn of(ip: &IpAddr) -> Self {
+        match ip {
+            IpAddr::V4(_) => Self::V4,
+            IpAddr::V6(_) => Self::V6,
+        }
+    }
+
+    fn bits(self) -> u8 {
+        match self {
+            Self::V4 => 32,
+            Self::V6 => 128,
+        }
+    }
+
+    fn addr(self, value: u128) -> IpAddr {
+        match self {
+            Self::V4 => IpAddr::V4(Ipv4Addr::from(value as u32)),
+            Self::V6 => IpAddr::V6(Ipv6Addr::from(value)),
+        }
+    }
+}
+
+fn ip_value(ip: &IpAddr) -> u128 {
+    match ip {
+        IpAddr::V4(v4) => u32::from(*v4) as u128,
+        IpAddr::V6(v6) => u128::from(*v6),
+    }
+}
+
+fn host_mask(host_bits: u8) -> u128 {
+    if host_bits >= 128 {
+        u128::MAX
+    } else {
+        (1u128 << host_bits) - 1
+    }
+}
+
+impl Cidr {
+    pub fn new(addr: IpAddr, prefix: u8) -> Result<Self, String> {
+        let bits = Family::of(&addr).bits();
+        if prefix > bits {
+            return Err(format!("префикс /{} больше {}", prefix, bits));
+        }
+        let start = ip_value(&addr) & !host_mask(bits - prefix);
+        Ok(Self {
+            addr: Family::of(&addr).addr(start),
+            prefix,
+        })
+    }
+
+    pub fn host(addr: IpAddr) -> Self {
+        Self {
+            addr,
+            prefix: Family::of(&addr).bits(),
+        }
+    }
+
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    pub fn is_ipv4(&self) -> bool {
+        self.addr.is_ipv4()
+    }
+
+    fn family(&self) -> Family {
+        Family::of(&self.addr)
+    }
+
+    fn range(&self) -> (u128, u128) {
+        let start = ip_value(&self.addr);
+        (start, start | host_mask(self.family().bits() - self.prefix))
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        if Family::of(ip) != self.family() {
+            return false;
+        }
+        let (start, end) = self.range();
+        (start..=end).contains(&ip_value(ip))
+    }
+
+    pub fn covers(&self, other: &Cidr) -> bool {
+        other.prefix >= self.prefix && self.contains(&other.addr)
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s.split_once('/') {
+            Some((ip, prefix)) => {
+                let ip: IpAddr = ip
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("некорректный IP: {}", ip))?;
+                let prefix: u8 = prefix
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("некорректный префикс: {}", prefix))?;
+                Self::new(ip, prefix)
+            }
+            None => s
+                .parse()
+                .map(Self::host)
+                .map_err(|_| format!("некорректный IP: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.prefix == self.family().bits() {
+            write!(f, "{}", self.addr)
+        } else {
+            write!(f, "{}/{}", self.addr, self.prefix)
+        }
+    }
+}
+
+impl Serialize for Cidr {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+// диапазон [start, end] в минимальный набор подсетей
+fn range_to_cidrs(family: Family, start: u128, end: u128) -> Vec<Cidr> {
+    let bits = family.bits();
+    let mut out = Vec::new();
+    let mut cur = start;
+    loop {
+        let align = if cur == 0 {
+            bits
+        } else {
+            (cur.trailing_zeros() as u8).min(bits)
+        };
+        let remaining = end - cur;
+        let fit = if remaining == u128::MAX {
+            128
+        } else {
+            (127 - (remaining + 1).leading_zeros()) as u8
+        };
+        let host_bits = align.min(fit);
+        out.push(Cidr {
+            addr: family.addr(cur),
+            prefix: bits - host_bits,
+        });
+        let last = cur + host_mask(host_bits);
+        if last >= end {
+            break;
+        }
+        cur = last + 1;
+    }
+    out
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LineError {
+    pub line: usize,
+    pub text: String,
+    pub error: String,
+}
+
+// строка ipset-а: "1.2.3.0/24", "1.2.3.4", "1.2.3.4-1.2.3.10", "# комментарий"
+pub fn parse_line(line: &str) -> Result<Vec<Cidr>, String> {
+    let line = line.split('#').next().unwrap_or_default().trim();
+    if line.is_empty() {
+        return Ok(Vec::new());
+    }
+    match line.split_once('-') {
+        Some((from, to)) => {
+            let from: IpAddr = from
+                .trim()
+                .parse()
+                .map_err(|_| format!("некорректный IP: {}", from.trim()))?;
+            let to: IpAddr = to
+                .trim()
+                .parse()
+                .map_err(|_| format!("некорректный IP: {}", to.trim()))?;
+            range_cidrs(from, to)
+        }
+        None => line.parse().map(|c| vec![c]),
+    }
+}
+
+pub fn range_cidrs(from: IpAddr, to: IpAddr) -> Result<Vec<Cidr>, String> {
+    if Family::of(&from) != Family::of(&to) {
+        return Err("диапазон из разных семейств IP".to_string());
+    }
+    let (start, end) = (ip_value(&from), ip_value(&to));
+    if start > end {
+        return Err("начало диапазона больше конца".to_string());
+    }
+    Ok(range_to_cidrs(Family::of(&from), start, end))
+}
+
+// набор адресов в виде отсортированных непересекающихся диапазонов
+#[derive(Clone, Debug, Default)]
+pub struct Ipset {
+    v4: Vec<(u128, u128)>,
+    v6: Vec<(u128, u128)>,
+}
+
+impl Ipset {
+    pub fn parse(content: &str) -> (Self, Vec<LineError>) {
+        let mut cidrs = Vec::new();
+        let mut errors = Vec::new();
+        for (i, line) in content.lines().enumerate() {
+            match parse_line(line) {
+                Ok(parsed) => cidrs.extend(parsed),
+                Err(error) => errors.push(LineError {
+                    line: i + 1,
+                    text: line.trim().to_string(),
+                    error,
+                }),
+            }
+        }
+        (Self::from_cidrs(cidrs), errors)
+    }
+
+    pub fn from_cidrs(cidrs: impl IntoIterator<Item = Cidr>) -> Self {
+        let mut set = Self::default();
+        for cidr in cidrs {
+            match cidr.family() {
+                Family::V4 => set.v4.push(cidr.range()),
+                Family::V6 => set.v6.push(cidr.range()),
+            }
+        }
+        set.v4 = Self::merge(std::mem::take(&mut set.v4));
+        set.v6 = Self::merge(std::mem::take(&mut set.v6));
+        set
+    }
+
+    // сливает пересекающиеся и соседние диапазоны
+    fn merge(mut ranges: Vec<(u128, u128)>) -> Vec<(u128, u128)> {
+        ranges.sort_unstable();
+        let mut out: Vec<(u128, u128)> = Vec::with_capacity(ranges.len());
+        for (start, end) in ranges {
+            match out.last_mut() {
+                Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+                _ => out.push((start, end)),
+            }
+        }
+        out
+    }
+
+    pub fn extend(&mut self, cidrs: impl IntoIterator<Item = Cidr>) {
+        *self = Self::from_cidrs(self.cidrs().into_iter().chain(cidrs));
+    }
+
+    fn find(ranges: &[(u128, u128)], start: u128, end: u128) -> bool {
+        let idx = ranges.partition_point(|r| r.1 < start);
+        ranges.get(idx).is_some_and(|r| r.0 <= start && end <= r.1)
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        self.covers(&Cidr::host(*ip))
+    }
+
+    pub fn covers(&self, cidr: &Cidr) -> bool {
+        let (start, end) = cidr.range();
+        match cidr.family() {
+            Family::V4 => Self::find(&self.v4, start, end),
+            Family::V6 => Self::find(&self.v6, start, end),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.v4.is_empty() && self.v6.is_empty()
+    }
+
+    // минимальный набор подсетей, сначала IPv4
+    pub fn cidrs(&self) -> Vec<Cidr> {
+        let v4 = self
+            .v4
+            .iter()
+            .flat_map(|(s, e)| range_to_cidrs(Family::V4, *s, *e));
+        let v6 = self
+            .v6
+            .iter()
+            .flat_map(|(s, e)| range_to_cidrs(Family::V6, *s, *e));
+        v4.chain(v6).collect()
+    }
+
+    pub fn to_text(&self) -> String {
+        self.cidrs().iter().map(|c| format!("{}\n", c)).collect()
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CompactReport {
+    pub lines_before: usize,
+    pub lines_after: usize,
+    pub bytes_before: usize,
+    pub bytes_after: usize,
+}
+
+// комментарии внутри списка привязаны к адресам, а после сжатия адресов уже не будет
+fn compact_text(content: &str) -> Result<String, String> {
+    let (set, errors) = Ipset::parse(content);
+    if let Some(first) = errors.first() {
+        return Err(format!(
+            "в файле {} ошибок, первая в строке {}: {} ({})",
+            errors.len(),
+            first.line,
+            first.text,
+            first.error
+        ));
+    }
+
+    // шапку из комментариев и перевод строки оставляем как есть
+    let eol = Hostlist::line_ending(content);
+    let lines: Vec<&str> = content.lines().collect();
+    let header_len = lines
+        .iter()
+        .take_while(|l| {
+            let l = l.trim();
+            l.is_empty() || l.starts_with('#')
+        })
+        .count();
+    if let Some((i, line)) = lines
+        .iter()
+        .enumerate()
+        .skip(header_len)
+        .find(|(_, l)| l.contains('#'))
+    {
+        return Err(format!(
+            "комментарий в строке {} потеряется при сжатии: {}",
+            i + 1,
+            line.trim()
+        ));
+    }
+    Ok(lines[..header_len]
+        .iter()
+        .map(|l| l.to_string())
+        .chain(set.cidrs().iter().map(|c| c.to_string()))
+        .map(|l| format!("{}{}", l, eol))
+        .collect())
+}
+
+// пустые строки не считаем ни до, ни после
+fn compact_report(before: &str, after: &str) -> CompactReport {
+    let count = |s: &str| s.lines().filter(|l| !l.trim().is_empty()).count();
+    CompactReport {
+        lines_before: count(before),
+        lines_after: count(after),
+        bytes_before: before.len(),
+        bytes_after: after.len(),
+    }
+}
+
+pub fn compact_file(path: &Path) -> Result<CompactReport, String> {
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let compacted = compact_text(&content)?;
+    write_atomic(path, &compacted)?;
+    Ok(compact_report(&content, &compacted))
+}
+
+// выбор ipset-ов: раньше был одной строкой, теперь список. принимаем оба варианта
+pub fn selection<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Vec<String>>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match Option::<OneOrMany>::deserialize(d)? {
+        None => None,
+        Some(OneOrMany::One(s)) if s.is_empty() => Some(Vec::new()),
+        Some(OneOrMany::One(s)) => Some(vec![s]),
+        Some(OneOrMany::Many(v)) => Some(v),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v4(s: &str) -> u128 {
+        ip_value(&s.parse().unwrap())
+    }
+
+    fn texts(cidrs: &[Cidr]) -> Vec<String> {
+        cidrs.iter().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    fn range_to_cidrs_aligned_block() {
+        let cidrs = range_to_cidrs(Family::V4, v4("10.0.0.0"), v4("10.0.0.255"));
+        assert_eq!(texts(&cidrs), ["10.0.0.0/24"]);
+    }
+
+    #[test]
+    fn range_to_cidrs_unaligned_range() {
+        let cidrs = range_to_cidrs(Family::V4, v4("1.2.3.4"), v4("1.2.3.10"));
+        assert_eq!(texts(&cidrs), ["1.2.3.4/30", "1.2.3.8/31", "1.2.3.10"]);
+    }
+
+    #[test]
+    fn range_to_cidrs_single_host() {
+        let cidrs = range_to_cidrs(Family::V4, v4("8.8.8.8"), v4("8.8.8.8"));
+        assert_eq!(texts(&cidrs), ["8.8.8.8"]);
+    }
+
+    #[test]
+    fn range_to_cidrs_whole_space() {
+        let cidrs = range_to_cidrs(Family::V4, 0, u32::MAX as u128);
+        assert_eq!(texts(&cidrs), ["0.0.0.0/0"]);
+        let cidrs = range_to_cidrs(Family::V6, 0, u128::MAX);
+        assert_eq!(texts(&cidrs), ["::/0"]);
+    }
+
+    #[test]
+    fn range_to_cidrs_covers_exactly_the_range() {
+        let (start, end) = (v4("192.168.0.77"), v4("192.168.3.200"));
+        let cidrs = range_to_cidrs(Family::V4, start, end);
+        let mut next = start;
+        for cidr in &cidrs {
+            let (s, e) = cidr.range();
+            assert_eq!(s, next, "дырка или нахлёст перед {}", cidr);
+            next = e + 1;
+        }
+        assert_eq!(next, end + 1);
+    }
+
+    #[test]
+    fn merge_joins_overlapping_and_adjacent() {
+        let merged = Ipset::merge(vec![(10, 20), (0, 5), (6, 8), (15, 30), (40, 50)]);
+        assert_eq!(merged, [(0, 8), (10, 30), (40, 50)]);
+    }
+
+    #[test]
+    fn merge_keeps_nested_and_handles_max() {
+        let merged = Ipset::merge(vec![(0, 100), (10, 20), (u128::MAX - 1, u128::MAX)]);
+        assert_eq!(merged, [(0, 100), (u128::MAX - 1, u128::MAX)]);
+    }
+
+    #[test]
+    fn ipset_merges_into_minimal_cidrs() {
+        let (set, errors) = Ipset::parse("1.2.3.0/25\n1.2.3.128/25\n1.2.3.5\n::1\n");
+        assert!(errors.is_empty());
+        assert_eq!(texts(&set.cidrs()), ["1.2.3.0/24", "::1"]);
+    }
+
+    #[test]
+    fn compact_keeps_header() {
+        let out = compact_text("# list\n\n# source: x\n1.2.3.0/25\n1.2.3.128/25\n").unwrap();
+        assert_eq!(out, "# list\n\n# source: x\n1.2.3.0/24\n");
+    }
+
+    #[test]
+    fn compact_refuses_comments_between_addresses() {
+        let err = compact_text("# list\n1.2.3.4\n# discord\n5.6.7.8\n").unwrap_err();
+        assert!(err.contains("строке 3"), "{}", err);
+        assert!(compact_text("1.2.3.4 # cdn\n").is_err());
+    }
+
+    #[test]
+    fn compact_keeps_crlf() {
+        let input = "# list\r\n\r\n1.2.3.0/25\r\n1.2.3.128/25\r\n10.0.0.1";
+        let out = compact_text(input).unwrap();
+        assert_eq!(out, "# list\r\n\r\n1.2.3.0/24\r\n10.0.0.1\r\n");
+        let report = compact_report(input, &out);
+        assert_eq!((report.lines_before, report.lines_after), (4, 3));
+    }
+}
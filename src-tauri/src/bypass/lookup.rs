@@ -0,0 +1,253 @@
+use crate::bypass::hostlist::Hostlist;
+use crate::bypass::ipset::{self, Cidr};
+use crate::bypass::zapret::Zapret;
+use serde::Serialize;
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+use tauri::AppHandle;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum QueryKind {
+    Domain,
+    Ip,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ListMatch {
+    // "lists/list-general.txt", "ipset-configs/my.txt"
+    pub file: String,
+    pub line: usize,
+    pub text: String,
+    pub exclude: bool,
+    // ipset подключён к сервису, хостлисты подключены всегда
+    pub active: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LookupReport {
+    pub query: String,
+    pub kind: QueryKind,
+    pub matches: Vec<ListMatch>,
+    // есть совпадение в exclude-списке - обход не сработает, даже если есть и в обычном
+    pub excluded: bool,
+    pub covered: bool,
+}
+
+pub struct Lookup;
+
+impl Lookup {
+    // как в zapret: "example.com" в списке покрывает и example.com, и *.example.com.
+    // регистр и точка в конце (FQDN) не важны
+    pub fn domain_matches(entry: &str, domain: &str) -> bool {
+        let entry = entry
+            .trim_start_matches("*.")
+            .trim_start_matches('.')
+            .trim_end_matches('.')
+            .to_lowercase();
+        let domain = domain.trim_end_matches('.').to_lowercase();
+        let entry = entry.as_str();
+        !entry.is_empty()
+            && (domain == entry
+                || domain
+                    .strip_suffix(entry)
+                    .is_some_and(|rest| rest.ends_with('.')))
+    }
+
+    pub fn find_domain(content: &str, domain: &str) -> Vec<(usize, String)> {
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| {
+                let l = l.trim();
+                !l.is_empty() && !l.starts_with('#') && Self::domain_matches(l, domain)
+            })
+            .map(|(i, l)| (i + 1, l.trim().to_string()))
+            .collect()
+    }
+
+    // строка подходит, если её подсеть целиком покрывает запрос
+    pub fn find_cidr(content: &str, query: &Cidr) -> Vec<(usize, String)> {
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| {
+                ipset::parse_line(l)
+                    .unwrap_or_default()
+                    .iter()
+                    .any(|c| c.covers(query))
+            })
+            .map(|(i, l)| (i + 1, l.trim().to_string()))
+            .collect()
+    }
+
+    fn txt_files(dir: &Path) -> Vec<String> {
+        let mut files: Vec<String> = fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok())
+                    .map(|e| e.file_name().to_string_lossy().into_owned())
+                    .filter(|n| n.ends_with(".txt") && !n.contains("-hide"))
+                    .collect()
+            })
+            .unwrap_or_default();
+        files.sort();
+        files
+    }
+
+    fn parse_query(query: &str) -> Result<(QueryKind, String, Option<Cidr>), String> {
+        let trimmed = query.trim();
+        if let Ok(cidr) = trimmed.parse::<Cidr>() {
+            return Ok((QueryKind::Ip, cidr.to_string(), Some(cidr)));
+        }
+        if let Ok(ip) = trimmed.trim_matches(['[', ']']).parse::<IpAddr>() {
+            let cidr = Cidr::host(ip);
+            return Ok((QueryKind::Ip, ip.to_string(), Some(cidr)));
+        }
+        Ok((QueryKind::Domain, Hostlist::normalize(trimmed)?, None))
+    }
+
+    pub fn find(app: &AppHandle, query: &str) -> Result<LookupReport, String> {
+        let mut files = Vec::new();
+        for dir in ["lists", "ipset-configs"] {
+            let dir_path = Zapret::zapret_path(app, dir);
+            for file in Self::txt_files(&dir_path) {
+                if let Ok(content) = fs::read_to_string(dir_path.join(&file)) {
+                    files.push((dir, file, content));
+                }
+            }
+        }
+        Self::report(query, &files, &Zapret::active_ipsets())
+    }
+
+    // files: (папка, имя, содержимое), selected - ipset-ы запущенного сервиса
+    fn report(
+        query: &str,
+        files: &[(&str, String, String)],
+        selected: &[String],
+    ) -> Result<LookupReport, String> {
+        let (kind, normalized, cidr) = Self::parse_query(query)?;
+        let mut matches = Vec::new();
+        for (dir, file, content) in files {
+            let is_ipset = *dir == "ipset-configs" || file.starts_with("ipset-");
+            if is_ipset != (kind == QueryKind::Ip) {
+                continue;
+            }
+            let found = match &cidr {
+                Some(cidr) => Self::find_cidr(content, cidr),
+                None => Self::find_domain(content, &normalized),
+            };
+            let exclude = file.contains("exclude");
+            let active = exclude
+                || !is_ipset
+                || selected.contains(file)
+                || (selected.is_empty() && file == "ipset-all.txt");
+            for (line, text) in found {
+                matches.push(ListMatch {
+                    file: format!("{}/{}", dir, file),
+                    line,
+                    text,
+                    exclude,
+                    active,
+                });
+            }
+        }
+
+        let excluded = matches.iter().any(|m| m.exclude);
+        // в режиме any под обход идут все IP
+        let any_mode = kind == QueryKind::Ip && selected.iter().any(|s| s == "any");
+        let covered = !excluded && (any_mode || matches.iter().any(|m| m.active));
+        Ok(LookupReport {
+            query: normalized,
+            kind,
+            matches,
+            excluded,
+            covered,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(dir: &'static str, name: &str, content: &str) -> (&'static str, String, String) {
+        (dir, name.to_string(), content.to_string())
+    }
+
+    #[test]
+    fn entry_covers_subdomains_only() {
+        assert!(Lookup::domain_matches("example.com", "example.com"));
+        assert!(Lookup::domain_matches("example.com", "a.example.com"));
+        assert!(Lookup::domain_matches("*.example.com", "a.b.example.com"));
+        assert!(Lookup::domain_matches(".example.com", "a.example.com"));
+        assert!(!Lookup::domain_matches("example.com", "notexample.com"));
+        assert!(!Lookup::domain_matches("a.example.com", "example.com"));
+        assert!(!Lookup::domain_matches("", "example.com"));
+    }
+
+    #[test]
+    fn trailing_dot_and_case_are_ignored() {
+        assert!(Lookup::domain_matches("Example.COM.", "a.example.com"));
+        assert!(Lookup::domain_matches("example.com", "A.Example.com."));
+        assert_eq!(
+            Lookup::find_domain("# comment\n\nYouTube.com\nexample.org\n", "www.youtube.com"),
+            vec![(3, "YouTube.com".to_string())]
+        );
+    }
+
+    #[test]
+    fn ip_found_inside_cidr() {
+        let content = "# discord\n162.159.128.0/24\n1.2.3.4\n2001:db8::/32\n";
+        let ip: Cidr = "162.159.128.235/32".parse().unwrap();
+        assert_eq!(
+            Lookup::find_cidr(content, &ip),
+            vec![(2, "162.159.128.0/24".to_string())]
+        );
+        let v6: Cidr = "2001:db8::1/128".parse().unwrap();
+        assert_eq!(Lookup::find_cidr(content, &v6).len(), 1);
+        // запрос шире строки - не покрыт
+        let wide: Cidr = "162.159.0.0/16".parse().unwrap();
+        assert!(Lookup::find_cidr(content, &wide).is_empty());
+    }
+
+    #[test]
+    fn exclude_overrides_include() {
+        let files = [
+            file("lists", "list-general.txt", "youtube.com\n"),
+            file("lists", "list-exclude.txt", "music.youtube.com\n"),
+        ];
+        let report = Lookup::report("music.youtube.com", &files, &[]).unwrap();
+        assert_eq!(report.matches.len(), 2);
+        assert!(report.excluded);
+        assert!(!report.covered);
+
+        let report = Lookup::report("www.youtube.com", &files, &[]).unwrap();
+        assert!(!report.excluded);
+        assert!(report.covered);
+    }
+
+    #[test]
+    fn ipset_counts_only_when_selected() {
+        let files = [
+            file("lists", "ipset-all.txt", "1.2.3.0/24\n"),
+            file("ipset-configs", "my.txt", "5.6.7.0/24\n"),
+            file("lists", "list-general.txt", "1.2.3.4\n"),
+        ];
+        let report = Lookup::report("5.6.7.8", &files, &[]).unwrap();
+        assert_eq!(report.kind, QueryKind::Ip);
+        assert!(!report.covered);
+        assert!(
+            Lookup::report("5.6.7.8", &files, &["my.txt".to_string()])
+                .unwrap()
+                .covered
+        );
+        // без выбора работает ipset-all, хостлисты для IP не смотрим
+        let report = Lookup::report("1.2.3.4", &files, &[]).unwrap();
+        assert_eq!(report.matches.len(), 1);
+        assert!(report.covered);
+    }
+}
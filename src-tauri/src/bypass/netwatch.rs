@@ -0,0 +1,102 @@
+use crate::bypass::exclusions::{Exclusions, LocalInterface};
+use crate::bypass::network::{Network, NetworkInfo, SystemProbe};
+use crate::bypass::watchdog::Watchdog;
+use std::time::{Duration, Instant, SystemTime};
+use tauri::AppHandle;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+// если между тиками прошло больше, значит система спала
+const RESUME_GAP: Duration = Duration::from_secs(60);
+// после пробуждения или переключения сетке нужно время подняться
+const SETTLE_DELAY: Duration = Duration::from_secs(5);
+// сеть может дёргаться дольше, тогда отдаём что есть
+const SETTLE_TRIES: usize = 6;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NetSnapshot {
+    pub net: NetworkInfo,
+    pub interfaces: Vec<LocalInterface>,
+}
+
+impl NetSnapshot {
+    fn take() -> Self {
+        Self {
+            net: Network::detect_local(&SystemProbe),
+            interfaces: Exclusions::interfaces(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct NetEvent {
+    pub snapshot: NetSnapshot,
+    // первый снимок после запуска zust
+    pub initial: bool,
+    pub net_changed: bool,
+    pub interfaces_changed: bool,
+    pub resumed: bool,
+    pub at: Instant,
+}
+
+// один поток следит за сетью и по очереди раздаёт изменения,
+// чтобы профили, исключения и watchdog не перезапускали движок наперегонки
+pub struct NetWatch;
+
+impl NetWatch {
+    pub fn spawn(app: AppHandle) {
+        std::thread::spawn(move || {
+            let mut last: Option<NetSnapshot> = None;
+            let mut last_tick = SystemTime::now();
+            loop {
+                let resumed = SystemTime::now()
+                    .duration_since(last_tick)
+                    .is_ok_and(|gap| gap > RESUME_GAP);
+                let mut snapshot = NetSnapshot::take();
+                let changed = last.as_ref().is_some_and(|l| *l != snapshot);
+                if resumed || changed {
+                    snapshot = Self::settle(snapshot);
+                }
+                if last.is_none() || resumed || last.as_ref() != Some(&snapshot) {
+                    let event = NetEvent {
+                        initial: last.is_none(),
+                        net_changed: last.as_ref().is_some_and(|l| l.net != snapshot.net),
+                        interfaces_changed: last
+                            .as_ref()
+                            .is_some_and(|l| l.interfaces != snapshot.interfaces),
+                        resumed,
+                        snapshot: snapshot.clone(),
+                        at: Instant::now(),
+                    };
+                    Self::dispatch(&app, &event);
+                    last = Some(snapshot);
+                }
+                last_tick = SystemTime::now();
+                std::thread::sleep(POLL_INTERVAL);
+            }
+        });
+    }
+
+    // ждём, пока два снимка подряд совпадут
+    fn settle(mut snapshot: NetSnapshot) -> NetSnapshot {
+        for _ in 0..SETTLE_TRIES {
+            std::thread::sleep(SETTLE_DELAY);
+            let next = NetSnapshot::take();
+            if next == snapshot {
+                break;
+            }
+            snapshot = next;
+        }
+        snapshot
+    }
+
+    // исключения пишутся первыми, чтобы профиль и watchdog стартовали уже с новыми списками.
+    // их собственный перезапуск последним: если движок уже подняли, он не нужен
+    fn dispatch(app: &AppHandle, event: &NetEvent) {
+        let synced = Exclusions::on_net_event(app, event);
+        Network::on_net_event(app, event);
+        Watchdog::on_net_event(app, event);
+        if let Some(since) = synced {
+            Exclusions::restart(app, since);
+        }
+    }
+}
@@ -0,0 +1,436 @@
+use crate::bypass::dns::Dns;
+use crate::bypass::engine::Engine;
+use crate::bypass::ipset;
+use crate::bypass::netwatch::NetEvent;
+use crate::bypass::zapret::Zapret;
+use crate::settings;
+use crate::sh_output;
+use crate::utils::*;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::AppHandle;
+
+// запрос раскрывает IP стороннему сервису, поэтому только по HTTPS
+// (ip-api.com без ключа HTTPS не отдаёт). org вида "AS12389 PJSC Rostelecom"
+const ASN_URL: &str = "https://ipinfo.io/json";
+
+// по чему узнаём сеть. пустое поле = не проверяется
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkMatch {
+    pub gateway_mac: Option<String>,
+    pub ssid: Option<String>,
+    pub asn: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkProfile {
+    pub id: String,
+    pub name: String,
+    pub enabled: bool,
+    #[serde(rename = "match")]
+    pub matcher: NetworkMatch,
+    pub strategy: String,
+    #[serde(default, deserialize_with = "ipset::selection")]
+    pub ipset: Option<Vec<String>>,
+    pub game_filter: bool,
+}
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkInfo {
+    pub gateway: Option<String>,
+    pub gateway_mac: Option<String>,
+    pub ssid: Option<String>,
+    pub asn: Option<u32>,
+}
+
+// источник данных о сети, в тестах подменяется
+pub trait NetworkProbe {
+    fn gateway(&self) -> Option<String>;
+    fn gateway_mac(&self, gateway: &str) -> Option<String>;
+    fn ssid(&self) -> Option<String>;
+    fn asn(&self) -> Option<u32>;
+}
+
+pub struct SystemProbe;
+
+impl NetworkProbe for SystemProbe {
+    fn gateway(&self) -> Option<String> {
+        let out = sh_output!("route", "print", "-4", "0.0.0.0").ok()?;
+        Network::parse_gateway(&String::from_utf8_lossy(&out.stdout))
+    }
+
+    fn gateway_mac(&self, gateway: &str) -> Option<String> {
+        let out = sh_output!("arp", "-a", gateway).ok()?;
+        Network::parse_arp(&String::from_utf8_lossy(&out.stdout), gateway)
+    }
+
+    fn ssid(&self) -> Option<String> {
+        let out = sh_output!("netsh", "wlan", "show", "interfaces").ok()?;
+        Network::parse_ssid(&String::from_utf8_lossy(&out.stdout))
+    }
+
+    fn asn(&self) -> Option<u32> {
+        let client = Dns::blocking_client_builder()
+            .timeout(Duration::from_secs(3))
+            .build()
+            .ok()?;
+        let json: serde_json::Value = client.get(ASN_URL).send().ok()?.json().ok()?;
+        Network::parse_asn(json["org"].as_str()?)
+    }
+}
+
+pub struct Network;
+
+impl Network {
+    pub fn detect(probe: &impl NetworkProbe) -> NetworkInfo {
+        let mut net = Self::detect_local(probe);
+        net.asn = probe.asn();
+        net
+    }
+
+    // без запроса ASN наружу, чтобы дёшево замечать смену сети
+    pub fn detect_local(probe: &impl NetworkProbe) -> NetworkInfo {
+        let gateway = probe.gateway();
+        let gateway_mac = gateway.as_deref().and_then(|g| probe.gateway_mac(g));
+        NetworkInfo {
+            gateway,
+            gateway_mac,
+            ssid: probe.ssid(),
+            asn: None,
+        }
+    }
+
+    // профиль подходит, если совпали все заданные в нём поля
+    pub fn match_profile<'a>(
+        profiles: &'a [NetworkProfile],
+        net: &NetworkInfo,
+    ) -> Option<&'a NetworkProfile> {
+        profiles.iter().filter(|p| p.enabled).find(|p| {
+            let m = &p.matcher;
+            let any_set = m.gateway_mac.is_some() || m.ssid.is_some() || m.asn.is_some();
+            let mac_ok = m.gateway_mac.as_deref().is_none_or(|mac| {
+                net.gateway_mac.as_deref() == Some(Self::normalize_mac(mac).as_str())
+            });
+            let ssid_ok = m
+                .ssid
+                .as_deref()
+                .is_none_or(|ssid| net.ssid.as_deref() == Some(ssid));
+            let asn_ok = m.asn.is_none_or(|asn| net.asn == Some(asn));
+            any_set && mac_ok && ssid_ok && asn_ok
+        })
+    }
+
+    // ASN узнаём запросом наружу, без включённых профилей по ASN не спрашиваем
+    pub fn needs_asn(profiles: &[NetworkProfile]) -> bool {
+        profiles
+            .iter()
+            .any(|p| p.enabled && p.matcher.asn.is_some())
+    }
+
+    pub fn get_profiles() -> Vec<NetworkProfile> {
+        settings::load_settings().network_profiles
+    }
+
+    pub fn save_profiles(app: &AppHandle, profiles: Vec<NetworkProfile>) -> Result<(), String> {
+        let mut current = settings::load_settings();
+        current.network_profiles = profiles;
+        settings::save_settings(current)?;
+        info(app, "сетевые профили сохранены");
+        Ok(())
+    }
+
+    pub fn apply(app: &AppHandle, profile: &NetworkProfile) -> Result<(), String> {
+        info(app, &format!("сетевой профиль: {}", profile.name));
+        let index = Zapret::strategy_index(app, &profile.strategy)
+            .ok_or_else(|| format!("стратегия {} не найдена", profile.strategy))?;
+        Engine::exclusive(|| {
+            Zapret::game_filter_toggle(profile.game_filter, app)?;
+            Zapret::start_service(app, index, profile.ipset.clone()).map_err(|e| e.to_string())
+        })
+    }
+
+    pub fn on_net_event(app: &AppHandle, event: &NetEvent) {
+        if !event.initial && !event.net_changed {
+            return;
+        }
+        let profiles = settings::load_settings().network_profiles;
+        if profiles.is_empty() {
+            return;
+        }
+        let mut net = event.snapshot.net.clone();
+        if Self::needs_asn(&profiles) {
+            net.asn = SystemProbe.asn();
+        }
+        info(app, &format!("сеть: {:?}", net));
+        let matched = Self::match_profile(&profiles, &net);
+        // при старте не перезапускаем уже работающую нужную стратегию
+        let already =
+            event.initial && matched.is_some_and(|p| Zapret::get_strategy() == p.strategy);
+        if let Some(profile) = matched.filter(|_| !already)
+            && let Err(e) = Self::apply(app, profile)
+        {
+            info(app, &format!("network profile err: {}", e));
+        }
+    }
+
+    pub fn parse_gateway(route: &str) -> Option<String> {
+        route
+            .lines()
+            .filter_map(|line| {
+                let cols: Vec<&str> = line.split_whitespace().collect();
+                match cols.as_slice() {
+                    ["0.0.0.0", "0.0.0.0", gw, _iface, metric] => {
+                        Some((metric.parse::<u32>().ok()?, gw.to_string()))
+                    }
+                    _ => None,
+                }
+            })
+            .min_by_key(|(metric, _)| *metric)
+            .map(|(_, gw)| gw)
+    }
+
+    pub fn parse_arp(arp: &str, ip: &str) -> Option<String> {
+        arp.lines().find_map(|line| {
+            let mut cols = line.split_whitespace();
+            (cols.next()? == ip).then_some(())?;
+            Some(Self::normalize_mac(cols.next()?))
+        })
+    }
+
+    pub fn parse_ssid(netsh: &str) -> Option<String> {
+        netsh.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim() == "SSID").then(|| value.trim().to_string())
+        })
+    }
+
+    pub fn parse_asn(as_field: &str) -> Option<u32> {
+        as_field
+            .split_whitespace()
+            .next()?
+            .trim_start_matches("AS")
+            .parse()
+            .ok()
+    }
+
+    pub fn normalize_mac(mac: &str) -> String {
+        mac.trim().to_lowercase().replace('-', ":")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeProbe {
+        gateway: Option<&'static str>,
+        mac: Option<&'static str>,
+        ssid: Option<&'static str>,
+        asn: Option<u32>,
+    }
+
+    impl NetworkProbe for FakeProbe {
+        fn gateway(&self) -> Option<String> {
+            self.gateway.map(str::to_string)
+        }
+        fn gateway_mac(&self, gateway: &str) -> Option<String> {
+            (Some(gateway) == self.gateway)
+                .then_some(())
+                .and(self.mac.map(Network::normalize_mac))
+        }
+        fn ssid(&self) -> Option<String> {
+            self.ssid.map(str::to_string)
+        }
+        fn asn(&self) -> Option<u32> {
+            self.asn
+        }
+    }
+
+    const HOME: FakeProbe = FakeProbe {
+        gateway: Some("192.168.1.1"),
+        mac: Some("AA-BB-CC-DD-EE-FF"),
+        ssid: Some("HomeWiFi"),
+        asn: Some(12389),
+    };
+
+    fn profile(id: &str, matcher: NetworkMatch) -> NetworkProfile {
+        NetworkProfile {
+            id: id.to_string(),
+            name: id.to_string(),
+            enabled: true,
+            matcher,
+            strategy: "general".to_string(),
+            ipset: None,
+            game_filter: false,
+        }
+    }
+
+    #[test]
+    fn detect_uses_probe() {
+        let net = Network::detect(&HOME);
+        assert_eq!(net.gateway.as_deref(), Some("192.168.1.1"));
+        assert_eq!(net.gateway_mac.as_deref(), Some("aa:bb:cc:dd:ee:ff"));
+        assert_eq!(net.ssid.as_deref(), Some("HomeWiFi"));
+        assert_eq!(net.asn, Some(12389));
+        assert_eq!(Network::detect_local(&HOME).asn, None);
+    }
+
+    #[test]
+    fn matches_by_mac_in_any_format() {
+        let profiles = [profile(
+            "home",
+            NetworkMatch {
+                gateway_mac: Some("AA:BB:CC:DD:EE:FF".to_string()),
+                ..Default::default()
+            },
+        )];
+        let net = Network::detect(&HOME);
+        assert_eq!(
+            Network::match_profile(&profiles, &net).map(|p| p.id.as_str()),
+            Some("home")
+        );
+    }
+
+    #[test]
+    fn all_set_fields_must_match() {
+        let profiles = [profile(
+            "home",
+            NetworkMatch {
+                ssid: Some("HomeWiFi".to_string()),
+                asn: Some(8359),
+                ..Default::default()
+            },
+        )];
+        assert!(Network::match_profile(&profiles, &Network::detect(&HOME)).is_none());
+    }
+
+    #[test]
+    fn empty_matcher_and_disabled_never_match() {
+        let mut disabled = profile(
+            "off",
+            NetworkMatch {
+                ssid: Some("HomeWiFi".to_string()),
+                ..Default::default()
+            },
+        );
+        disabled.enabled = false;
+        let profiles = [profile("empty", NetworkMatch::default()), disabled];
+        assert!(Network::match_profile(&profiles, &Network::detect(&HOME)).is_none());
+    }
+
+    #[test]
+    fn first_matching_profile_wins() {
+        let by_asn = NetworkMatch {
+            asn: Some(12389),
+            ..Default::default()
+        };
+        let profiles = [
+            profile(
+                "other",
+                NetworkMatch {
+                    ssid: Some("Cafe".to_string()),
+                    ..Default::default()
+                },
+            ),
+            profile("isp", by_asn.clone()),
+            profile("isp2", by_asn),
+        ];
+        let net = Network::detect(&HOME);
+        assert_eq!(
+            Network::match_profile(&profiles, &net).map(|p| p.id.as_str()),
+            Some("isp")
+        );
+    }
+
+    #[test]
+    fn asn_requested_only_for_enabled_asn_profiles() {
+        let by_ssid = profile(
+            "home",
+            NetworkMatch {
+                ssid: Some("HomeWiFi".to_string()),
+                ..Default::default()
+            },
+        );
+        let mut by_asn = profile(
+            "isp",
+            NetworkMatch {
+                asn: Some(12389),
+                ..Default::default()
+            },
+        );
+        assert!(!Network::needs_asn(&[]));
+        assert!(!Network::needs_asn(&[by_ssid.clone()]));
+        assert!(Network::needs_asn(&[by_ssid.clone(), by_asn.clone()]));
+        by_asn.enabled = false;
+        assert!(!Network::needs_asn(&[by_ssid, by_asn]));
+    }
+
+    #[test]
+    fn no_gateway_no_mac() {
+        let probe = FakeProbe {
+            gateway: None,
+            ..HOME
+        };
+        let net = Network::detect_local(&probe);
+        assert_eq!(net.gateway, None);
+        assert_eq!(net.gateway_mac, None);
+    }
+
+    #[test]
+    fn parse_gateway_picks_lowest_metric() {
+        let route = "\
+IPv4 Route Table
+===========================================================================
+Active Routes:
+Network Destination        Netmask          Gateway       Interface  Metric
+          0.0.0.0          0.0.0.0      10.0.0.1      10.0.0.15     50
+          0.0.0.0          0.0.0.0  192.168.1.1   192.168.1.20     25
+===========================================================================
+Persistent Routes:
+  None
+";
+        assert_eq!(
+            Network::parse_gateway(route).as_deref(),
+            Some("192.168.1.1")
+        );
+        assert_eq!(Network::parse_gateway("Active Routes:\n  None\n"), None);
+    }
+
+    #[test]
+    fn parse_arp_finds_gateway() {
+        let arp = "\
+Interface: 192.168.1.20 --- 0x7
+  Internet Address      Physical Address      Type
+  192.168.1.1           aa-bb-cc-dd-ee-ff     dynamic
+  192.168.1.255         ff-ff-ff-ff-ff-ff     static
+";
+        assert_eq!(
+            Network::parse_arp(arp, "192.168.1.1").as_deref(),
+            Some("aa:bb:cc:dd:ee:ff")
+        );
+        assert_eq!(Network::parse_arp(arp, "192.168.1.2"), None);
+    }
+
+    #[test]
+    fn parse_ssid_skips_bssid() {
+        let netsh = "\
+    Name                   : Wi-Fi
+    State                  : connected
+    SSID                   : Home: 5G
+    BSSID                  : aa:bb:cc:dd:ee:ff
+";
+        assert_eq!(Network::parse_ssid(netsh).as_deref(), Some("Home: 5G"));
+        assert_eq!(Network::parse_ssid("    State : disconnected\n"), None);
+    }
+
+    #[test]
+    fn parse_asn_formats() {
+        assert_eq!(Network::parse_asn("AS12389 PJSC Rostelecom"), Some(12389));
+        assert_eq!(Network::parse_asn("8359"), Some(8359));
+        assert_eq!(Network::parse_asn(""), None);
+        assert_eq!(Network::parse_asn("ASX"), None);
+    }
+}
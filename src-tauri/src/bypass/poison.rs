@@ -0,0 +1,416 @@
+use crate::bypass::dns::{Dns, DnsMode, DnsProvider, DnsSettings, ResolverPreset};
+use crate::bypass::hostlist::Hostlist;
+use crate::bypass::ipset::Ipset;
+use crate::bypass::zapret::Zapret;
+use crate::utils::*;
+use hickory_resolver::TokioAsyncResolver;
+use hickory_resolver::error::ResolveErrorKind;
+use hickory_resolver::proto::op::ResponseCode;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::net::IpAddr;
+use std::sync::Arc;
+use tauri::AppHandle;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+// IP страниц блокировки провайдера, формат как у ipset.
+// своего списка не держим: адреса у провайдеров меняются, а проверить их источник нечем
+const STUB_IPS_FILE: &str = "lists/dns-stub-ips.txt";
+const PARALLEL: usize = 16;
+// с чем сравниваем системный DNS
+const UPSTREAMS: &[DnsProvider] = &[DnsProvider::Cloudflare, DnsProvider::Google];
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "status", content = "detail", rename_all = "camelCase")]
+pub enum LookupOutcome {
+    Resolved(Vec<IpAddr>),
+    NxDomain,
+    NoData,
+    Failed(String),
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Verdict {
+    Clean,
+    // ответы не пересекаются, но это может быть и гео-CDN
+    Suspicious,
+    Poisoned,
+    Unresolvable,
+    Inconclusive,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PoisonReport {
+    pub domain: String,
+    pub verdict: Verdict,
+    pub reason: String,
+    pub system: LookupOutcome,
+    pub encrypted: Vec<LookupOutcome>,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkPoisonReport {
+    pub checked: usize,
+    pub poisoned: Vec<PoisonReport>,
+    pub suspicious: Vec<PoisonReport>,
+    pub inconclusive: Vec<String>,
+}
+
+pub struct PoisonCheck;
+
+impl PoisonCheck {
+    // заглушки обычно отдают локальные или служебные адреса
+    pub fn is_stub(ip: &IpAddr, stubs: &Ipset) -> bool {
+        if stubs.contains(ip) {
+            return true;
+        }
+        match ip {
+            IpAddr::V4(v4) => {
+                v4.is_unspecified()
+                    || v4.is_loopback()
+                    || v4.is_private()
+                    || v4.is_link_local()
+                    || v4.is_broadcast()
+            }
+            IpAddr::V6(v6) => {
+                v6.is_unspecified() || v6.is_loopback() || v6.segments()[0] & 0xfe00 == 0xfc00
+            }
+        }
+    }
+
+    pub fn verdict(
+        system: &LookupOutcome,
+        encrypted: &[LookupOutcome],
+        stubs: &Ipset,
+    ) -> (Verdict, String) {
+        let trusted: HashSet<IpAddr> = encrypted
+            .iter()
+            .filter_map(|o| match o {
+                LookupOutcome::Resolved(ips) => Some(ips.iter().copied()),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+
+        if trusted.is_empty() {
+            let nx = encrypted
+                .iter()
+                .any(|o| matches!(o, LookupOutcome::NxDomain | LookupOutcome::NoData));
+            return match system {
+                LookupOutcome::Resolved(_) if nx => (
+                    Verdict::Poisoned,
+                    "системный DNS отвечает на несуществующий домен".to_string(),
+                ),
+                LookupOutcome::Resolved(_) => (
+                    Verdict::Inconclusive,
+                    "зашифрованные DNS недоступны".to_string(),
+                ),
+                _ if nx => (Verdict::Unresolvable, "домен не существует".to_string()),
+                _ => (
+                    Verdict::Inconclusive,
+                    "не удалось получить ответ ни от одного DNS".to_string(),
+                ),
+            };
+        }
+
+        match system {
+            LookupOutcome::Resolved(ips) => {
+                let stub: Vec<String> = ips
+                    .iter()
+                    .filter(|ip| Self::is_stub(ip, stubs) && !trusted.contains(ip))
+                    .map(|ip| ip.to_string())
+                    .collect();
+                if !stub.is_empty() {
+                    (
+                        Verdict::Poisoned,
+                        format!("системный DNS вернул заглушку: {}", stub.join(", ")),
+                    )
+                } else if ips.iter().any(|ip| trusted.contains(ip)) {
+                    (Verdict::Clean, "ответы совпадают".to_string())
+                } else {
+                    (
+                        Verdict::Suspicious,
+                        "ответы не пересекаются (подмена или гео-CDN)".to_string(),
+                    )
+                }
+            }
+            LookupOutcome::NxDomain => (
+                Verdict::Poisoned,
+                "системный DNS говорит NXDOMAIN, зашифрованный резолвит".to_string(),
+            ),
+            LookupOutcome::NoData => (
+                Verdict::Poisoned,
+                "системный DNS вернул пустой ответ".to_string(),
+            ),
+            // таймаут сам по себе ещё не подмена
+            LookupOutcome::Failed(e) => (
+                Verdict::Suspicious,
+                format!("системный DNS не ответил: {}", e),
+            ),
+        }
+    }
+
+    pub async fn lookup(resolver: &TokioAsyncResolver, domain: &str) -> LookupOutcome {
+        match resolver.lookup_ip(domain).await {
+            Ok(lookup) => {
+                let mut ips: Vec<IpAddr> = lookup.iter().collect();
+                ips.sort();
+                if ips.is_empty() {
+                    LookupOutcome::NoData
+                } else {
+                    LookupOutcome::Resolved(ips)
+                }
+            }
+            Err(e) => match e.kind() {
+                ResolveErrorKind::NoRecordsFound {
+                    response_code: ResponseCode::NXDomain,
+                    ..
+                } => LookupOutcome::NxDomain,
+                ResolveErrorKind::NoRecordsFound { .. } => LookupOutcome::NoData,
+                _ => LookupOutcome::Failed(e.to_string()),
+            },
+        }
+    }
+
+    fn stubs(app: &AppHandle) -> Ipset {
+        let content =
+            fs::read_to_string(Zapret::zapret_path(app, STUB_IPS_FILE)).unwrap_or_default();
+        Ipset::parse(&content).0
+    }
+
+    // системный резолвер + DoH апстримы
+    fn resolvers() -> Result<(TokioAsyncResolver, Vec<TokioAsyncResolver>), String> {
+        let system = Dns::resolver(ResolverPreset::System)?;
+        let encrypted = UPSTREAMS
+            .iter()
+            .map(|provider| {
+                Dns::from_settings(&DnsSettings {
+                    mode: DnsMode::Doh,
+                    provider: *provider,
+                    ..Default::default()
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((system, encrypted))
+    }
+
+    async fn check_with(
+        system: &TokioAsyncResolver,
+        encrypted: &[TokioAsyncResolver],
+        stubs: &Ipset,
+        domain: String,
+    ) -> PoisonReport {
+        let system_outcome = Self::lookup(system, &domain).await;
+        let mut encrypted_outcomes = Vec::new();
+        for resolver in encrypted {
+            encrypted_outcomes.push(Self::lookup(resolver, &domain).await);
+        }
+        let (verdict, reason) = Self::verdict(&system_outcome, &encrypted_outcomes, stubs);
+        PoisonReport {
+            domain,
+            verdict,
+            reason,
+            system: system_outcome,
+            encrypted: encrypted_outcomes,
+        }
+    }
+
+    pub async fn check(app: &AppHandle, input: &str) -> Result<PoisonReport, String> {
+        let domain = Hostlist::normalize(input)?;
+        let (system, encrypted) = Self::resolvers()?;
+        let report = Self::check_with(&system, &encrypted, &Self::stubs(app), domain).await;
+        info(
+            app,
+            &format!(
+                "dns check {}: {:?}, {}",
+                report.domain, report.verdict, report.reason
+            ),
+        );
+        Ok(report)
+    }
+
+    pub async fn check_hostlist(
+        app: &AppHandle,
+        list_name: &str,
+    ) -> Result<BulkPoisonReport, String> {
+        let path = Hostlist::list_path(app, list_name)?;
+        let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        let domains: HashSet<String> = Hostlist::domains(&content)
+            .iter()
+            .filter_map(|d| Hostlist::normalize(d).ok())
+            .collect();
+        info(
+            app,
+            &format!("dns check {}: {} доменов", list_name, domains.len()),
+        );
+
+        let (system, encrypted) = Self::resolvers()?;
+        let stubs = Arc::new(Self::stubs(app));
+        let encrypted = Arc::new(encrypted);
+        let limit = Arc::new(Semaphore::new(PARALLEL));
+        let mut tasks = JoinSet::new();
+        for domain in domains {
+            let (system, encrypted, stubs, limit) = (
+                system.clone(),
+                encrypted.clone(),
+                stubs.clone(),
+                limit.clone(),
+            );
+            tasks.spawn(async move {
+                let _permit = limit.acquire_owned().await;
+                Self::check_with(&system, &encrypted, &stubs, domain).await
+            });
+        }
+
+        let mut bulk = BulkPoisonReport::default();
+        while let Some(res) = tasks.join_next().await {
+            let report = res.map_err(|e| e.to_string())?;
+            bulk.checked += 1;
+            match report.verdict {
+                Verdict::Poisoned => bulk.poisoned.push(report),
+                Verdict::Suspicious => bulk.suspicious.push(report),
+                Verdict::Inconclusive => bulk.inconclusive.push(report.domain),
+                Verdict::Clean | Verdict::Unresolvable => {}
+            }
+        }
+        bulk.poisoned.sort_by(|a, b| a.domain.cmp(&b.domain));
+        bulk.suspicious.sort_by(|a, b| a.domain.cmp(&b.domain));
+        bulk.inconclusive.sort();
+        info(
+            app,
+            &format!(
+                "dns check {}: подменено {}, подозрительных {}",
+                list_name,
+                bulk.poisoned.len(),
+                bulk.suspicious.len()
+            ),
+        );
+        Ok(bulk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use LookupOutcome::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn resolved(ips: &[&str]) -> LookupOutcome {
+        Resolved(ips.iter().map(|s| ip(s)).collect())
+    }
+
+    fn stubs() -> Ipset {
+        Ipset::parse("# мой провайдер\n203.0.113.0/24\n").0
+    }
+
+    #[test]
+    fn user_file_marks_stubs() {
+        let stubs = stubs();
+        assert!(PoisonCheck::is_stub(&ip("203.0.113.7"), &stubs));
+        assert!(!PoisonCheck::is_stub(&ip("1.1.1.1"), &stubs));
+    }
+
+    #[test]
+    fn local_addresses_are_stubs() {
+        let stubs = Ipset::default();
+        for addr in [
+            "0.0.0.0",
+            "127.0.0.1",
+            "10.10.34.34",
+            "192.168.0.1",
+            "::1",
+            "fd00::1",
+        ] {
+            assert!(PoisonCheck::is_stub(&ip(addr), &stubs), "{}", addr);
+        }
+    }
+
+    #[test]
+    fn verdicts() {
+        let cdn = resolved(&["104.16.1.1", "104.16.1.2"]);
+        let cases = [
+            // заглушка провайдера из файла
+            (
+                resolved(&["203.0.113.7"]),
+                vec![cdn.clone()],
+                Verdict::Poisoned,
+            ),
+            // локальный адрес вместо настоящего
+            (
+                resolved(&["10.0.0.1"]),
+                vec![cdn.clone()],
+                Verdict::Poisoned,
+            ),
+            (
+                resolved(&["0.0.0.0"]),
+                vec![cdn.clone(), Failed("timeout".into())],
+                Verdict::Poisoned,
+            ),
+            // если и зашифрованный отдаёт локальный адрес, это не подмена
+            (
+                resolved(&["10.0.0.1"]),
+                vec![resolved(&["10.0.0.1"])],
+                Verdict::Clean,
+            ),
+            // система говорит NXDOMAIN, а домен есть
+            (NxDomain, vec![cdn.clone()], Verdict::Poisoned),
+            (NoData, vec![cdn.clone()], Verdict::Poisoned),
+            // система резолвит то, чего нет
+            (
+                resolved(&["1.2.3.4"]),
+                vec![NxDomain, NxDomain],
+                Verdict::Poisoned,
+            ),
+            (NxDomain, vec![NxDomain, NoData], Verdict::Unresolvable),
+            // гео-CDN: разные, но нормальные адреса
+            (
+                resolved(&["104.16.9.9"]),
+                vec![cdn.clone()],
+                Verdict::Suspicious,
+            ),
+            (
+                resolved(&["104.16.1.2", "104.16.1.1"]),
+                vec![cdn.clone()],
+                Verdict::Clean,
+            ),
+            // хватает пересечения хотя бы с одним апстримом
+            (
+                resolved(&["104.16.1.1"]),
+                vec![resolved(&["172.64.0.1"]), cdn.clone()],
+                Verdict::Clean,
+            ),
+            (
+                Failed("timeout".into()),
+                vec![cdn.clone()],
+                Verdict::Suspicious,
+            ),
+            (
+                resolved(&["1.2.3.4"]),
+                vec![Failed("tls".into())],
+                Verdict::Inconclusive,
+            ),
+            (
+                Failed("timeout".into()),
+                vec![Failed("tls".into())],
+                Verdict::Inconclusive,
+            ),
+        ];
+        let stubs = stubs();
+        for (system, encrypted, expected) in cases {
+            let (verdict, reason) = PoisonCheck::verdict(&system, &encrypted, &stubs);
+            assert_eq!(
+                verdict, expected,
+                "{:?} / {:?}: {}",
+                system, encrypted, reason
+            );
+        }
+    }
+}
@@ -1,12 +1,14 @@
 use std::{
     error::Error,
+    net::SocketAddr,
     time::{Duration, Instant},
 };
 
 use tauri::Url;
-use tokio::net::{TcpStream, lookup_host};
+use tokio::net::TcpStream;
 use tokio::time::timeout;
 
+use crate::bypass::dns::Dns;
 use crate::utils::info;
 
 pub struct Proxies;
@@ -22,7 +24,12 @@ pub struct Proxy {
 
 impl Proxies {
     pub async fn get_proxy_list() -> Result<Vec<String>, Box<dyn Error>> {
-        let response = reqwest::get(PROXIES_URL).await?.text().await?;
+        let response = Dns::http_client()?
+            .get(PROXIES_URL)
+            .send()
+            .await?
+            .text()
+            .await?;
         let proxies: Vec<String> = response
             .lines()
             .map(|s| s.trim().to_string())
@@ -58,9 +65,17 @@ impl Proxies {
         } else {
             clean_address.to_string()
         };
-        let addrs = lookup_host(&target).await.ok()?;
-        let addr = addrs.into_iter().next()?;
+        let addr = match target.parse::<SocketAddr>() {
+            Ok(addr) => addr,
+            Err(_) => {
+                let (host, port) = target.rsplit_once(':')?;
+                let resolver = Dns::shared().ok()?;
+                let ip = *Dns::lookup_all(&resolver, host).await.ok()?.first()?;
+                SocketAddr::new(ip, port.parse().ok()?)
+            }
+        };
         let ip_str = addr.ip().to_string();
+        let client = Dns::http_client().ok()?;
 
         let start = Instant::now();
         match timeout(Duration::from_secs(3), TcpStream::connect(&addr)).await {
@@ -68,13 +83,14 @@ impl Proxies {
                 let ms = start.elapsed().as_millis() as u64;
                 let url = format!("http://ip-api.com/json/{}?fields=countryCode", ip_str);
 
-                let country_code = match timeout(Duration::from_secs(2), reqwest::get(url)).await {
-                    Ok(Ok(response)) => {
-                        let json: serde_json::Value = response.json().await.unwrap_or_default();
-                        json["countryCode"].as_str().unwrap_or("??").to_string()
-                    }
-                    _ => "??".to_string(),
-                };
+                let country_code =
+                    match timeout(Duration::from_secs(2), client.get(url).send()).await {
+                        Ok(Ok(response)) => {
+                            let json: serde_json::Value = response.json().await.unwrap_or_default();
+                            json["countryCode"].as_str().unwrap_or("??").to_string()
+                        }
+                        _ => "??".to_string(),
+                    };
 
                 Some(Proxy {
                     ping: ms,
@@ -0,0 +1,293 @@
+use crate::bypass::engine::Engine;
+use crate::bypass::ipset;
+use crate::bypass::zapret::Zapret;
+use crate::settings;
+use crate::utils::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+// правило: пока запущен любой из processes - держим стратегию strategy
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivationRule {
+    pub id: String,
+    pub enabled: bool,
+    pub processes: Vec<String>,
+    pub strategy: String,
+    #[serde(default, deserialize_with = "ipset::selection")]
+    pub ipset: Option<Vec<String>>,
+    #[serde(default)]
+    pub stop_delay_minutes: u64,
+}
+
+impl ActivationRule {
+    fn matches(&self, running: &HashSet<String>) -> bool {
+        self.enabled
+            && self
+                .processes
+                .iter()
+                .any(|p| running.contains(&p.trim().to_lowercase()))
+    }
+}
+
+#[derive(Debug)]
+pub enum RuleAction {
+    Start(ActivationRule),
+    Stop(String),
+}
+
+struct ActiveRule {
+    id: String,
+    last_seen: Instant,
+}
+
+// состояние монитора, без побочных эффектов: решает что делать, но ничего не запускает
+#[derive(Default)]
+pub struct RuleMonitor {
+    active: Option<ActiveRule>,
+    // правила, которые пользователь остановил руками, пока их процессы живы
+    suppressed: HashSet<String>,
+}
+
+impl RuleMonitor {
+    pub fn tick(
+        &mut self,
+        rules: &[ActivationRule],
+        running: &HashSet<String>,
+        engine_running: bool,
+        now: Instant,
+    ) -> Option<RuleAction> {
+        self.suppressed
+            .retain(|id| rules.iter().any(|r| &r.id == id && r.matches(running)));
+
+        if let Some(active) = &mut self.active {
+            let Some(rule) = rules.iter().find(|r| r.id == active.id) else {
+                let id = active.id.clone();
+                self.active = None;
+                return engine_running.then_some(RuleAction::Stop(id));
+            };
+            if !engine_running {
+                // остановили вручную, не трогаем до выхода процессов
+                self.suppressed.insert(rule.id.clone());
+                self.active = None;
+                return None;
+            }
+            if rule.matches(running) {
+                active.last_seen = now;
+                return None;
+            }
+            let delay = Duration::from_secs(rule.stop_delay_minutes * 60);
+            if now.duration_since(active.last_seen) >= delay {
+                let id = active.id.clone();
+                self.active = None;
+                return Some(RuleAction::Stop(id));
+            }
+            return None;
+        }
+
+        // движок запущен пользователем - правила не вмешиваются
+        if engine_running {
+            return None;
+        }
+        let rule = rules
+            .iter()
+            .find(|r| r.matches(running) && !self.suppressed.contains(&r.id))?;
+        self.active = Some(ActiveRule {
+            id: rule.id.clone(),
+            last_seen: now,
+        });
+        Some(RuleAction::Start(rule.clone()))
+    }
+}
+
+pub struct Rules;
+
+impl Rules {
+    pub fn running_processes() -> HashSet<String> {
+        let mut names = HashSet::new();
+        let _ = process_list::for_each_process(|_pid: u32, path: &Path| {
+            if let Some(name) = path.file_name() {
+                names.insert(name.to_string_lossy().to_lowercase());
+            }
+        });
+        names
+    }
+
+    pub fn get() -> Vec<ActivationRule> {
+        settings::load_settings().activation_rules
+    }
+
+    pub fn save(app: &AppHandle, rules: Vec<ActivationRule>) -> Result<(), String> {
+        let mut current = settings::load_settings();
+        current.activation_rules = rules;
+        settings::save_settings(current)?;
+        info(app, "правила активации сохранены");
+        Ok(())
+    }
+
+    pub fn spawn_monitor(app: AppHandle) {
+        std::thread::spawn(move || {
+            let mut monitor = RuleMonitor::default();
+            loop {
+                std::thread::sleep(POLL_INTERVAL);
+                let rules = settings::load_settings().activation_rules;
+                if rules.is_empty() && monitor.active.is_none() {
+                    continue;
+                }
+                let engine_running = Zapret::get_strategy() != "Отсутствует";
+                let action = monitor.tick(
+                    &rules,
+                    &Self::running_processes(),
+                    engine_running,
+                    Instant::now(),
+                );
+                match action {
+                    Some(RuleAction::Start(rule)) => Self::start(&app, &rule),
+                    Some(RuleAction::Stop(id)) => {
+                        info(&app, &format!("правило {}: процессы завершились, стоп", id));
+                        if let Err(e) = Engine::exclusive(|| Zapret::stop_service(&app)) {
+                            info(&app, &format!("rule stop err: {}", e));
+                        }
+                    }
+                    None => {}
+                }
+            }
+        });
+    }
+
+    fn start(app: &AppHandle, rule: &ActivationRule) {
+        let Some(index) = Zapret::strategy_index(app, &rule.strategy) else {
+            info(
+                app,
+                &format!("правило {}: нет стратегии {}", rule.id, rule.strategy),
+            );
+            return;
+        };
+        info(
+            app,
+            &format!("правило {}: запускаю {}", rule.id, rule.strategy),
+        );
+        if let Err(e) = Engine::exclusive(|| Zapret::start_service(app, index, rule.ipset.clone()))
+        {
+            info(app, &format!("rule start err: {}", e));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(id: &str, process: &str, delay: u64) -> ActivationRule {
+        ActivationRule {
+            id: id.to_string(),
+            enabled: true,
+            processes: vec![process.to_string()],
+            strategy: "general".to_string(),
+            ipset: None,
+            stop_delay_minutes: delay,
+        }
+    }
+
+    fn running(names: &[&str]) -> HashSet<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn starts_when_process_appears() {
+        let mut m = RuleMonitor::default();
+        let rules = [rule("game", "Game.exe", 0)];
+        let now = Instant::now();
+        assert!(m.tick(&rules, &running(&[]), false, now).is_none());
+        let action = m.tick(&rules, &running(&["game.exe"]), false, now);
+        assert!(matches!(action, Some(RuleAction::Start(r)) if r.id == "game"));
+        // уже активно - второй раз не стартуем
+        assert!(m.tick(&rules, &running(&["game.exe"]), true, now).is_none());
+    }
+
+    #[test]
+    fn does_not_touch_engine_started_by_user() {
+        let mut m = RuleMonitor::default();
+        let rules = [rule("game", "game.exe", 0)];
+        assert!(
+            m.tick(&rules, &running(&["game.exe"]), true, Instant::now())
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn disabled_rule_is_ignored() {
+        let mut m = RuleMonitor::default();
+        let mut r = rule("game", "game.exe", 0);
+        r.enabled = false;
+        assert!(
+            m.tick(&[r], &running(&["game.exe"]), false, Instant::now())
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn stops_only_after_delay() {
+        let mut m = RuleMonitor::default();
+        let rules = [rule("game", "game.exe", 2)];
+        let t0 = Instant::now();
+        m.tick(&rules, &running(&["game.exe"]), false, t0);
+        // процесс пропал, но задержка ещё не вышла
+        let t1 = t0 + Duration::from_secs(60);
+        assert!(m.tick(&rules, &running(&[]), true, t1).is_none());
+        // вернулся - отсчёт начинается заново
+        let t2 = t0 + Duration::from_secs(90);
+        assert!(m.tick(&rules, &running(&["game.exe"]), true, t2).is_none());
+        let t3 = t2 + Duration::from_secs(119);
+        assert!(m.tick(&rules, &running(&[]), true, t3).is_none());
+        let t4 = t2 + Duration::from_secs(120);
+        let action = m.tick(&rules, &running(&[]), true, t4);
+        assert!(matches!(action, Some(RuleAction::Stop(id)) if id == "game"));
+    }
+
+    #[test]
+    fn zero_delay_stops_immediately() {
+        let mut m = RuleMonitor::default();
+        let rules = [rule("game", "game.exe", 0)];
+        let now = Instant::now();
+        m.tick(&rules, &running(&["game.exe"]), false, now);
+        let action = m.tick(&rules, &running(&[]), true, now);
+        assert!(matches!(action, Some(RuleAction::Stop(_))));
+    }
+
+    #[test]
+    fn manual_stop_suppresses_until_process_exits() {
+        let mut m = RuleMonitor::default();
+        let rules = [rule("game", "game.exe", 0)];
+        let now = Instant::now();
+        m.tick(&rules, &running(&["game.exe"]), false, now);
+        // пользователь остановил движок руками
+        assert!(
+            m.tick(&rules, &running(&["game.exe"]), false, now)
+                .is_none()
+        );
+        assert!(
+            m.tick(&rules, &running(&["game.exe"]), false, now)
+                .is_none()
+        );
+        // процесс вышел и запустился снова - правило снова работает
+        assert!(m.tick(&rules, &running(&[]), false, now).is_none());
+        let action = m.tick(&rules, &running(&["game.exe"]), false, now);
+        assert!(matches!(action, Some(RuleAction::Start(_))));
+    }
+
+    #[test]
+    fn removed_rule_stops_engine() {
+        let mut m = RuleMonitor::default();
+        let rules = [rule("game", "game.exe", 10)];
+        let now = Instant::now();
+        m.tick(&rules, &running(&["game.exe"]), false, now);
+        let action = m.tick(&[], &running(&["game.exe"]), true, now);
+        assert!(matches!(action, Some(RuleAction::Stop(id)) if id == "game"));
+    }
+}
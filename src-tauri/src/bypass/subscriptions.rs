@@ -0,0 +1,430 @@
+use crate::bypass::autohostlist;
+use crate::bypass::dns::Dns;
+use crate::bypass::engine::Engine;
+use crate::bypass::hostlist::Hostlist;
+use crate::bypass::ipset::Ipset;
+use crate::bypass::zapret::Zapret;
+use crate::utils::*;
+use chrono::{DateTime, Local};
+use reqwest::StatusCode;
+use reqwest::header::{ETAG, HeaderName, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+use tokio::sync::Mutex;
+
+const STORE_FILE: &str = "subscriptions.json";
+const CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+// загрузка/сохранение store идут через этот лок, чтобы планировщик и команды не затирали друг друга
+static STORE_LOCK: Mutex<()> = Mutex::const_new(());
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ListKind {
+    Hostlist,
+    Ipset,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Subscription {
+    pub id: String,
+    pub url: String,
+    pub kind: ListKind,
+    pub file_name: String,
+    pub interval_hours: u64,
+    #[serde(default)]
+    pub etag: Option<String>,
+    #[serde(default)]
+    pub last_modified: Option<String>,
+    #[serde(default)]
+    pub last_checked: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub last_updated: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub last_error: Option<String>,
+}
+
+impl Subscription {
+    fn is_due(&self, now: DateTime<Local>) -> bool {
+        self.last_checked.is_none_or(|checked| {
+            now.signed_duration_since(checked).num_hours() >= self.interval_hours as i64
+        })
+    }
+}
+
+pub struct Subscriptions;
+
+impl Subscriptions {
+    fn store_path(app: &AppHandle) -> PathBuf {
+        Zapret::zapret_path(app, STORE_FILE)
+    }
+
+    fn load(app: &AppHandle) -> Vec<Subscription> {
+        fs::read_to_string(Self::store_path(app))
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn store(app: &AppHandle, subs: &[Subscription]) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(subs).map_err(|e| e.to_string())?;
+        write_atomic(&Self::store_path(app), &json)
+    }
+
+    // исключения, ipset-ы zapret в lists и авто-хостлист ведут zapret и zust,
+    // подписка не должна их перезаписывать
+    fn is_reserved(kind: ListKind, file_name: &str) -> bool {
+        let name = file_name.to_lowercase();
+        name.contains("exclude")
+            || name.contains("-hide")
+            || match kind {
+                ListKind::Hostlist => name.starts_with("ipset-") || name == autohostlist::AUTO_LIST,
+                ListKind::Ipset => false,
+            }
+    }
+
+    // файл можно отдать подписке, если его ещё нет или он уже принадлежит ей же
+    fn check_owner(subs: &[Subscription], sub: &Subscription, exists: bool) -> Result<(), String> {
+        let owner = subs
+            .iter()
+            .find(|s| s.kind == sub.kind && s.file_name.eq_ignore_ascii_case(&sub.file_name));
+        match owner {
+            Some(owner) if owner.id != sub.id => Err(format!(
+                "{} уже обновляется другой подпиской",
+                sub.file_name
+            )),
+            Some(_) => Ok(()),
+            None if exists => Err(format!(
+                "{} уже есть, подписка его перезапишет - выберите новое имя",
+                sub.file_name
+            )),
+            None => Ok(()),
+        }
+    }
+
+    pub fn target_path(
+        app: &AppHandle,
+        kind: ListKind,
+        file_name: &str,
+    ) -> Result<PathBuf, String> {
+        check_list_name(file_name)?;
+        if Self::is_reserved(kind, file_name) {
+            return Err(format!(
+                "{} ведёт zapret или zust, выберите другое имя",
+                file_name
+            ));
+        }
+        let dir = match kind {
+            ListKind::Hostlist => "lists",
+            ListKind::Ipset => "ipset-configs",
+        };
+        Ok(Zapret::zapret_path(app, dir).join(file_name))
+    }
+
+    pub async fn get(app: &AppHandle) -> Vec<Subscription> {
+        let _guard = STORE_LOCK.lock().await;
+        Self::load(app)
+    }
+
+    pub async fn save(app: &AppHandle, sub: Subscription) -> Result<(), String> {
+        let path = Self::target_path(app, sub.kind, &sub.file_name)?;
+        if !sub.url.starts_with("https://") && !sub.url.starts_with("http://") {
+            return Err(format!("некорректный URL: {}", sub.url));
+        }
+        let _guard = STORE_LOCK.lock().await;
+        let mut subs = Self::load(app);
+        Self::check_owner(&subs, &sub, path.exists())?;
+        match subs.iter_mut().find(|s| s.id == sub.id) {
+            Some(existing) => {
+                // поменяли источник - кеш больше не валиден
+                if existing.url != sub.url {
+                    existing.etag = None;
+                    existing.last_modified = None;
+                    existing.last_checked = None;
+                }
+                existing.url = sub.url;
+                existing.kind = sub.kind;
+                existing.file_name = sub.file_name;
+                existing.interval_hours = sub.interval_hours;
+            }
+            None => subs.push(sub),
+        }
+        Self::store(app, &subs)
+    }
+
+    pub async fn remove(app: &AppHandle, id: &str) -> Result<(), String> {
+        let _guard = STORE_LOCK.lock().await;
+        let mut subs = Self::load(app);
+        subs.retain(|s| s.id != id);
+        Self::store(app, &subs)
+    }
+
+    // проверка содержимого до того, как подменить файл
+    pub fn validate(kind: ListKind, content: &str) -> Result<usize, String> {
+        let count = match kind {
+            ListKind::Ipset => {
+                let (set, errors) = Ipset::parse(content);
+                if let Some(first) = errors.first() {
+                    return Err(format!("строка {}: {}", first.line, first.error));
+                }
+                set.cidrs().len()
+            }
+            ListKind::Hostlist => {
+                let domains = Hostlist::domains(content);
+                if let Some(bad) = domains.iter().find(|d| Hostlist::normalize(d).is_err()) {
+                    return Err(format!("некорректный домен: {}", bad));
+                }
+                domains.len()
+            }
+        };
+        if count == 0 {
+            return Err("список пуст".to_string());
+        }
+        Ok(count)
+    }
+
+    async fn request(
+        client: &reqwest::Client,
+        sub: &Subscription,
+    ) -> Result<reqwest::Response, String> {
+        let mut req = client.get(&sub.url);
+        if let Some(etag) = &sub.etag {
+            req = req.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(modified) = &sub.last_modified {
+            req = req.header(IF_MODIFIED_SINCE, modified);
+        }
+        req.send().await.map_err(|e| e.to_string())
+    }
+
+    // true, если файл поменялся
+    async fn fetch(app: &AppHandle, sub: &mut Subscription) -> Result<bool, String> {
+        let path = Self::target_path(app, sub.kind, &sub.file_name)?;
+        let client = Dns::http_client_builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .map_err(|e| e.to_string())?;
+        let mut res = Self::request(&client, sub).await?;
+        sub.last_checked = Some(Local::now());
+        if res.status() == StatusCode::NOT_MODIFIED {
+            if path.exists() {
+                return Ok(false);
+            }
+            // файл удалили, а сервер помнит нашу версию: забываем её и качаем заново
+            info(
+                app,
+                &format!("подписка {}: файла нет, качаю заново", sub.file_name),
+            );
+            sub.etag = None;
+            sub.last_modified = None;
+            res = Self::request(&client, sub).await?;
+        }
+        if !res.status().is_success() {
+            return Err(format!("сервер вернул ошибку: {}", res.status()));
+        }
+
+        let header = |name: HeaderName| {
+            res.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+        let body = res.text().await.map_err(|e| e.to_string())?;
+        let count = Self::validate(sub.kind, &body)?;
+
+        let changed = fs::read_to_string(&path).map_or(true, |old| old != body);
+        if changed {
+            write_atomic(&path, &body)?;
+            sub.last_updated = Some(Local::now());
+            info(
+                app,
+                &format!("подписка {}: {} записей", sub.file_name, count),
+            );
+        }
+        sub.etag = etag;
+        sub.last_modified = last_modified;
+        Ok(changed)
+    }
+
+    // force - обновить всё, не глядя на интервал. id - только одну подписку.
+    // качаем без лока, иначе save/remove и соседний refresh ждут медленный сервер
+    pub async fn refresh(app: &AppHandle, id: Option<&str>, force: bool) -> Result<(), String> {
+        let now = Local::now();
+        let mut due: Vec<Subscription> = {
+            let _guard = STORE_LOCK.lock().await;
+            Self::load(app)
+                .into_iter()
+                .filter(|s| id.is_none_or(|id| s.id == id))
+                .filter(|s| force || s.is_due(now))
+                .collect()
+        };
+        if due.is_empty() {
+            return Ok(());
+        }
+        let mut changed = Vec::new();
+        let mut errors = Vec::new();
+
+        for sub in due.iter_mut() {
+            match Self::fetch(app, sub).await {
+                Ok(true) => changed.push((sub.kind, sub.file_name.clone())),
+                Ok(false) => {}
+                Err(e) => {
+                    info(app, &format!("подписка {} err: {}", sub.url, e));
+                    errors.push(format!("{}: {}", sub.file_name, e));
+                    sub.last_error = Some(e);
+                    continue;
+                }
+            }
+            sub.last_error = None;
+        }
+
+        {
+            let _guard = STORE_LOCK.lock().await;
+            let mut subs = Self::load(app);
+            for fetched in due {
+                // пока качали, подписку могли удалить или перенастроить - тогда результат не наш
+                let Some(sub) = subs.iter_mut().find(|s| {
+                    s.id == fetched.id
+                        && s.url == fetched.url
+                        && s.kind == fetched.kind
+                        && s.file_name == fetched.file_name
+                }) else {
+                    continue;
+                };
+                sub.etag = fetched.etag;
+                sub.last_modified = fetched.last_modified;
+                sub.last_checked = fetched.last_checked;
+                sub.last_updated = fetched.last_updated;
+                sub.last_error = fetched.last_error;
+            }
+            Self::store(app, &subs)?;
+        }
+
+        if changed
+            .iter()
+            .any(|(kind, file)| Self::is_active(*kind, file))
+        {
+            let (app, since) = (app.clone(), Instant::now());
+            let _ = tauri::async_runtime::spawn_blocking(move || {
+                if let Err(e) = Engine::restart(&app, "подписки", since) {
+                    info(&app, &format!("subscription restart err: {}", e));
+                }
+            })
+            .await;
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+
+    // хостлисты подхватываются все, ipset - только выбранные
+    fn is_active(kind: ListKind, file_name: &str) -> bool {
+        if Zapret::get_strategy() == "Отсутствует" {
+            return false;
+        }
+        match kind {
+            ListKind::Hostlist => true,
+            ListKind::Ipset => Zapret::active_ipsets().iter().any(|f| f == file_name),
+        }
+    }
+
+    pub fn spawn_scheduler(app: AppHandle) {
+        tauri::async_runtime::spawn(async move {
+            loop {
+                tokio::time::sleep(CHECK_INTERVAL).await;
+                let _ = Self::refresh(&app, None, false).await;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sub(id: &str, kind: ListKind, file_name: &str) -> Subscription {
+        Subscription {
+            id: id.to_string(),
+            url: "https://example.com/list.txt".to_string(),
+            kind,
+            file_name: file_name.to_string(),
+            interval_hours: 24,
+            etag: None,
+            last_modified: None,
+            last_checked: None,
+            last_updated: None,
+            last_error: None,
+        }
+    }
+
+    #[test]
+    fn managed_files_are_reserved() {
+        for name in [
+            "list-exclude.txt",
+            "ipset-all.txt",
+            "ipset-exclude.txt",
+            "IPSET-ANY-HIDE.txt",
+            "autohostlist.txt",
+        ] {
+            assert!(
+                Subscriptions::is_reserved(ListKind::Hostlist, name),
+                "{}",
+                name
+            );
+        }
+        assert!(Subscriptions::is_reserved(
+            ListKind::Ipset,
+            "my-exclude.txt"
+        ));
+        assert!(!Subscriptions::is_reserved(
+            ListKind::Hostlist,
+            "list-sub.txt"
+        ));
+        assert!(!Subscriptions::is_reserved(
+            ListKind::Ipset,
+            "ipset-sub.txt"
+        ));
+    }
+
+    #[test]
+    fn only_new_or_own_files() {
+        let subs = [sub("a", ListKind::Hostlist, "list-a.txt")];
+        // новый файл
+        assert!(
+            Subscriptions::check_owner(&subs, &sub("b", ListKind::Hostlist, "list-b.txt"), false)
+                .is_ok()
+        );
+        // файл пользователя или из комплекта zapret
+        assert!(
+            Subscriptions::check_owner(
+                &subs,
+                &sub("b", ListKind::Hostlist, "list-general.txt"),
+                true
+            )
+            .is_err()
+        );
+        // свой файл при повторном сохранении
+        assert!(
+            Subscriptions::check_owner(&subs, &sub("a", ListKind::Hostlist, "list-a.txt"), true)
+                .is_ok()
+        );
+        // чужой файл другой подписки
+        assert!(
+            Subscriptions::check_owner(&subs, &sub("b", ListKind::Hostlist, "LIST-A.txt"), true)
+                .is_err()
+        );
+        // то же имя в другой папке - другой файл
+        assert!(
+            Subscriptions::check_owner(&subs, &sub("b", ListKind::Ipset, "list-a.txt"), false)
+                .is_ok()
+        );
+    }
+}
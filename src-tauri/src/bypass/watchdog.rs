@@ -0,0 +1,60 @@
+use crate::bypass::engine::Engine;
+use crate::bypass::health::Health;
+use crate::bypass::netwatch::NetEvent;
+use crate::bypass::zapret::Zapret;
+use crate::settings;
+use crate::utils::*;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum NetworkChangeAction {
+    #[default]
+    None,
+    Restart,
+    HealthCheck,
+}
+
+pub struct Watchdog;
+
+impl Watchdog {
+    pub fn get_action() -> NetworkChangeAction {
+        settings::load_settings().on_network_change
+    }
+
+    pub fn set_action(app: &AppHandle, action: NetworkChangeAction) -> Result<(), String> {
+        let mut current = settings::load_settings();
+        current.on_network_change = action;
+        settings::save_settings(current)?;
+        info(app, &format!("watchdog: при смене сети {:?}", action));
+        Ok(())
+    }
+
+    pub fn on_net_event(app: &AppHandle, event: &NetEvent) {
+        if event.initial || (!event.resumed && !event.net_changed) {
+            return;
+        }
+        let action = Self::get_action();
+        if action == NetworkChangeAction::None || Zapret::get_strategy() == "Отсутствует"
+        {
+            return;
+        }
+        info(
+            app,
+            if event.resumed {
+                "watchdog: выход из сна"
+            } else {
+                "watchdog: сеть сменилась"
+            },
+        );
+        let restart = match action {
+            NetworkChangeAction::None => false,
+            NetworkChangeAction::Restart => true,
+            NetworkChangeAction::HealthCheck => !Health::quick_check(app).healthy,
+        };
+        if restart && let Err(e) = Engine::restart(app, "watchdog", event.at) {
+            info(app, &format!("watchdog restart err: {}", e));
+        }
+    }
+}
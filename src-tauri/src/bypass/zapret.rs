@@ -1,8 +1,14 @@
-use crate::sh;
+use crate::bypass::autohostlist::AutoHostlist;
+use crate::bypass::dns::Dns;
+use crate::bypass::errors::{ServiceError, output_text};
+use crate::bypass::forwarder::Forwarder;
+use crate::bypass::ipset::{self, Cidr, CompactReport, Ipset};
+use crate::settings;
 use crate::utils::*;
-use hickory_resolver::AsyncResolver;
-use hickory_resolver::config::{ResolverConfig, ResolverOpts};
+use crate::{sh, sh_output};
+use is_elevated::is_elevated;
 use md5::{Digest, Md5};
+use serde::Serialize;
 use std::fs;
 use std::io::Write;
 use std::net::IpAddr;
@@ -10,13 +16,18 @@ use std::path::Path;
 use std::path::PathBuf;
 use tauri::AppHandle;
 use tauri::Manager;
+#[cfg(windows)]
 use winreg::RegKey;
+#[cfg(windows)]
 use winreg::enums::*;
 
 const WINWS_EXE: &str =
     "https://github.com/bol-van/zapret-win-bundle/raw/refs/heads/master/zapret-winws/winws.exe";
 const MAX_RU_BIN: &str = "https://github.com/Flowseal/zapret-discord-youtube/raw/refs/heads/main/bin/tls_clienthello_max_ru.bin";
+#[cfg(windows)]
 const HKLM_PATH: &str = r"System\CurrentControlSet\Services\zapret";
+#[cfg(windows)]
+const IPSET_VALUE: &str = "zust-ipset";
 const CONFIG_EXTENSION: &str = ".zapret";
 const FLOWSEAL_REPO: &str =
     "https://raw.githubusercontent.com/Flowseal/zapret-discord-youtube/refs/heads/main/";
@@ -42,6 +53,12 @@ const REPO_STRAT_NAMES: &[&str] = &[
     "general (SIMPLE FAKE).bat",
 ];
 
+#[derive(Serialize, Default, Debug)]
+pub struct AddIpReport {
+    pub added: Vec<Cidr>,
+    pub skipped: Vec<Cidr>,
+}
+
 pub struct Zapret;
 
 impl Zapret {
@@ -56,7 +73,7 @@ impl Zapret {
             let current_strat_name = Self::get_strategy();
             let strat_list = Self::get_list_strategies(app);
             let strat_index = strat_list.iter().position(|s| s == &current_strat_name);
-            Self::stop_service(app);
+            Self::stop_service(app).map_err(|e| e.to_string())?;
             std::thread::sleep(std::time::Duration::from_millis(500));
             if force {
                 info(app, "moving strategies from legacy folder ");
@@ -89,7 +106,11 @@ impl Zapret {
             if current_strat_name != "Отсутствует" {
                 if let Some(idx) = strat_index {
                     info(app, &format!("strategy {} restart", current_strat_name));
-                    Self::start_service(app, (idx + 1) as i32, None);
+                    if let Err(e) =
+                        Self::start_service(app, (idx + 1) as i32, Self::running_ipsets())
+                    {
+                        info(app, &format!("restart err: {}", e));
+                    }
                 }
             }
         }
@@ -98,7 +119,9 @@ impl Zapret {
 
     pub async fn update_tls_bin(app: AppHandle) -> Result<String, String> {
         let target_path = Zapret::zapret_path(&app, "bin").join("tls_clienthello_max_ru.bin");
-        let response = reqwest::get(MAX_RU_BIN)
+        let response = Dns::http_client()?
+            .get(MAX_RU_BIN)
+            .send()
             .await
             .map_err(|e| format!("ошибка запроса: {}", e))?;
 
@@ -126,7 +149,11 @@ impl Zapret {
         } else {
             return Ok(true);
         };
-        let response = reqwest::get(WINWS_EXE).await.map_err(|e| e.to_string())?;
+        let response = Dns::http_client()?
+            .get(WINWS_EXE)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
         let bytes = response.bytes().await.map_err(|e| e.to_string())?;
         let remote_hash = format!("{:x}", Md5::digest(&bytes));
         info(
@@ -142,60 +169,114 @@ impl Zapret {
         Ok(false)
     }
 
-    pub async fn add_ip(app: AppHandle, file_name: String, ip: String) -> Result<(), String> {
-        let lists_dir = Self::zapret_path(&app, "ipset-configs");
-        let mut file_path = lists_dir.join(&file_name);
+    // ipset-configs/<name>, для ipset-all.txt - lists/
+    pub fn ipset_file_path(app: &AppHandle, file_name: &str) -> Result<PathBuf, String> {
+        let file_path = Self::zapret_path(app, "ipset-configs").join(file_name);
+        if file_path.exists() {
+            Ok(file_path)
+        } else if file_name == "ipset-all.txt" {
+            Ok(Self::zapret_path(app, "lists").join("ipset-all.txt"))
+        } else {
+            Err(format!("Файл {} не найден", file_name))
+        }
+    }
+
+    pub fn compact_ipset(app: &AppHandle, file_name: &str) -> Result<CompactReport, String> {
+        let path = Self::ipset_file_path(app, file_name)?;
+        let report = ipset::compact_file(&path)?;
+        info(
+            app,
+            &format!(
+                "ipset {} сжат: {} -> {} строк",
+                file_name, report.lines_before, report.lines_after
+            ),
+        );
+        Ok(report)
+    }
+
+    // ip, ip/префикс или домен. голый ip расширяется до префикса из настроек,
+    // домен резолвится во все A/AAAA. уже покрытые файлом адреса пропускаются
+    pub async fn add_ip(
+        app: AppHandle,
+        file_name: String,
+        ip: String,
+    ) -> Result<AddIpReport, String> {
+        let file_path = Self::ipset_file_path(&app, &file_name)?;
+        let settings = settings::load_settings();
+        let widen = |addr: IpAddr| {
+            let prefix = if addr.is_ipv4() {
+                settings.ipset_prefix_v4
+            } else {
+                settings.ipset_prefix_v6
+            };
+            Cidr::new(addr, prefix)
+        };
 
-        if !file_path.exists() {
-            if &file_name != "ipset-all.txt" {
-                return Err(format!("Файл {} не найден", file_name));
+        let input = ip.trim();
+        let candidates: Vec<Cidr> = if input.contains('/') {
+            vec![input.parse()?]
+        } else if let Ok(addr) = input.parse::<IpAddr>() {
+            vec![widen(addr)?]
+        } else if Self::is_hostname(input) {
+            Dns::lookup_all(&Dns::shared()?, input)
+                .await?
+                .into_iter()
+                .map(widen)
+                .collect::<Result<_, _>>()?
+        } else {
+            return Err(format!("Некорректный IP или домен: {}", input));
+        };
+
+        let content = fs::read_to_string(&file_path).unwrap_or_default();
+        let (mut existing, _) = Ipset::parse(&content);
+        let mut report = AddIpReport::default();
+        for cidr in candidates {
+            if existing.covers(&cidr) {
+                report.skipped.push(cidr);
             } else {
-                file_path = Self::zapret_path(&app, "lists").join("ipset-all.txt");
+                existing.extend([cidr]);
+                report.added.push(cidr);
             }
         }
-        let parts: Vec<&str> = ip.split('.').collect();
-        if parts.len() != 4 {
-            return Err("Некорректный формат IP. Ожидалось x.x.x.x".to_string());
+        if report.added.is_empty() {
+            return Ok(report);
         }
-        let formatted_ip = format!("{}.{}.{}.0/24", parts[0], parts[1], parts[2]);
 
         let mut file = std::fs::OpenOptions::new()
             .append(true)
             .open(&file_path)
             .map_err(|e| e.to_string())?;
-        writeln!(file, "{}", formatted_ip).map_err(|e| e.to_string())?;
-
-        Ok(())
+        if !content.is_empty() && !content.ends_with('\n') {
+            writeln!(file).map_err(|e| e.to_string())?;
+        }
+        for cidr in &report.added {
+            writeln!(file, "{}", cidr).map_err(|e| e.to_string())?;
+        }
+        info(
+            &app,
+            &format!(
+                "ipset {}: +{} пропущено {}",
+                file_name,
+                report.added.len(),
+                report.skipped.len()
+            ),
+        );
+        Ok(report)
     }
 
-    pub async fn resolve_host(host: String) -> Result<String, String> {
-        let resolver = AsyncResolver::tokio(ResolverConfig::default(), ResolverOpts::default());
-        let srv_name = format!("_minecraft._tcp.{}", host);
-        let target_host = if let Ok(srv_lookup) = resolver.srv_lookup(srv_name).await {
-            srv_lookup
-                .iter()
+    // у домена последняя метка не из цифр, иначе это кривой IP вроде 1.2.3
+    fn is_hostname(s: &str) -> bool {
+        s.contains('.')
+            && !s
+                .rsplit('.')
                 .next()
-                .map(|srv| srv.target().to_string().trim_end_matches('.').to_string())
-                .unwrap_or_else(|| host.clone())
-        } else {
-            host.clone()
-        };
-        let ip_lookup = resolver
-            .lookup_ip(target_host)
-            .await
-            .map_err(|e| format!("Ошибка резолва: {}", e))?;
-
-        let ip = ip_lookup
-            .iter()
-            .next()
-            .ok_or_else(|| "IP-адрес не найден".to_string())?;
-        match ip {
-            IpAddr::V4(v4) => {
-                let o = v4.octets();
-                Ok(format!("{}.{}.{}.0/24", o[0], o[1], o[2]))
-            }
-            IpAddr::V6(v6) => Ok(v6.to_string()),
-        }
+                .is_some_and(|tld| tld.chars().all(|c| c.is_ascii_digit()))
+            && s.split('.').all(|label| {
+                !label.is_empty()
+                    && !label.starts_with('-')
+                    && !label.ends_with('-')
+                    && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+            })
     }
 
     pub fn get_custom_ipset_files(app: &AppHandle) -> Vec<String> {
@@ -213,7 +294,7 @@ impl Zapret {
     }
 
     pub async fn check_strategy_updates(app: AppHandle) -> Result<Vec<String>, String> {
-        let client = reqwest::Client::new();
+        let client = Dns::http_client()?;
         let strats_dir = Self::zapret_path(&app, "strategies");
         let mut to_update = Vec::new();
 
@@ -247,7 +328,7 @@ impl Zapret {
     }
 
     pub async fn apply_strategy_update(app: AppHandle, file_name: String) -> Result<(), String> {
-        let client = reqwest::Client::new();
+        let client = Dns::http_client()?;
         let strats_dir = Self::zapret_path(&app, "strategies");
 
         let url = format!("{}{}", FLOWSEAL_REPO, file_name);
@@ -391,6 +472,89 @@ impl Zapret {
         list_files(Self::zapret_path(app, "strategies"), CONFIG_EXTENSION)
     }
 
+    // индекс для start_service, считается с 1
+    pub fn strategy_index(app: &AppHandle, name: &str) -> Option<i32> {
+        Self::get_list_strategies(app)
+            .iter()
+            .position(|s| s == name)
+            .map(|i| (i + 1) as i32)
+    }
+
+    // ipset-ы запущенного сервиса. старые версии писали REG_SZ с одним именем
+    #[cfg(windows)]
+    pub fn get_ipsets() -> Vec<String> {
+        let Ok(key) = RegKey::predef(HKEY_LOCAL_MACHINE).open_subkey(HKLM_PATH) else {
+            return Vec::new();
+        };
+        key.get_value::<Vec<String>, _>(IPSET_VALUE)
+            .or_else(|_| key.get_value::<String, _>(IPSET_VALUE).map(|s| vec![s]))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+
+    // сервиса zapret вне Windows нет
+    #[cfg(not(windows))]
+    pub fn get_ipsets() -> Vec<String> {
+        Vec::new()
+    }
+
+    // ipset для перезапуска: из реестра, а если там пусто (старая версия, ручная чистка) -
+    // None, и start_service возьмёт сохранённый выбор
+    fn running_ipsets() -> Option<Vec<String>> {
+        Some(Self::get_ipsets()).filter(|s| !s.is_empty())
+    }
+
+    // с какими ipset работает движок сейчас
+    pub fn active_ipsets() -> Vec<String> {
+        Self::running_ipsets().unwrap_or_else(Self::ipset_selection)
+    }
+
+    // сохранённый выбор из настроек, им запускаемся если ipset не передали явно
+    pub fn ipset_selection() -> Vec<String> {
+        settings::load_settings().ipset_selection
+    }
+
+    pub fn set_ipset_selection(
+        app: &AppHandle,
+        ipsets: Vec<String>,
+    ) -> Result<Vec<String>, String> {
+        let mut selection: Vec<String> = Vec::new();
+        for name in ipsets {
+            if !selection.contains(&name) {
+                selection.push(name);
+            }
+        }
+        let exclusive = selection.iter().any(|s| s == "any" || s == "none");
+        if exclusive && selection.len() > 1 {
+            return Err("any и none нельзя совмещать с другими списками".to_string());
+        }
+        if !exclusive {
+            for name in &selection {
+                check_list_name(name)?;
+                Self::ipset_file_path(app, name)?;
+            }
+        }
+        let mut current = settings::load_settings();
+        current.ipset_selection = selection.clone();
+        settings::save_settings(current)?;
+        info(app, &format!("ipset: {}", selection.join(", ")));
+        Ok(selection)
+    }
+
+    // перезапуск с теми же стратегией и ipset, если что-то запущено
+    pub fn restart_current(app: &AppHandle) -> Result<bool, ServiceError> {
+        let name = Self::get_strategy();
+        let Some(index) = Self::strategy_index(app, &name) else {
+            return Ok(false);
+        };
+        info(app, &format!("перезапуск {}", name));
+        Self::start_service(app, index, Self::running_ipsets())?;
+        Ok(true)
+    }
+
+    #[cfg(windows)]
     pub fn get_strategy() -> String {
         RegKey::predef(HKEY_LOCAL_MACHINE)
             .open_subkey(HKLM_PATH)
@@ -398,6 +562,11 @@ impl Zapret {
             .unwrap_or_else(|_| "Отсутствует".to_string())
     }
 
+    #[cfg(not(windows))]
+    pub fn get_strategy() -> String {
+        "Отсутствует".to_string()
+    }
+
     pub fn get_files_lists(app: &AppHandle) -> Vec<String> {
         fs::read_dir(Self::zapret_path(app, "lists"))
             .map(|entries| {
@@ -410,7 +579,28 @@ impl Zapret {
             .unwrap_or_default()
     }
 
-    pub fn build_full_args(app: &AppHandle, raw: &str, custom_ipset: Option<String>) -> String {
+    // any/none - отдельные режимы, иначе каждый файл идёт своим --ipset
+    fn ipset_paths(app: &AppHandle, ipsets: &[String]) -> Vec<PathBuf> {
+        let lists_dir = Self::zapret_path(app, "lists");
+        match ipsets {
+            [] => vec![lists_dir.join("ipset-all.txt")],
+            s if s.iter().any(|f| f == "none") => vec![lists_dir.join("ipset-none-hide.txt")],
+            s if s.iter().any(|f| f == "any") => vec![lists_dir.join("ipset-any-hide.txt")],
+            files => files
+                .iter()
+                .map(|file| {
+                    let custom = Self::zapret_path(app, "ipset-configs").join(file);
+                    if custom.exists() {
+                        custom
+                    } else {
+                        lists_dir.join(file)
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    pub fn build_full_args(app: &AppHandle, name: &str, raw: &str, ipsets: &[String]) -> String {
         let lists_dir = Self::zapret_path(app, "lists");
         let game_filter_enabled = Self::zapret_path(app, "utils/game_filter.enabled").exists();
         info(
@@ -435,22 +625,15 @@ impl Zapret {
             &format!("{}\\", Self::zapret_path(app, "bin").display()),
         );
 
-        let ipset_path = match custom_ipset.as_deref() {
-            Some("none") => lists_dir.join("ipset-none-hide.txt"),
-            Some("any") => lists_dir.join("ipset-any-hide.txt"),
-            Some(file) => {
-                let custom = Self::zapret_path(app, "ipset-configs").join(file);
-                if custom.exists() {
-                    custom
-                } else {
-                    lists_dir.join(file)
-                }
-            }
-            _ => lists_dir.join("ipset-all.txt"),
-        };
-        info(app, &format!("%IPSET%: {}", ipset_path.display()));
+        let ipset_args: Vec<String> = Self::ipset_paths(app, ipsets)
+            .iter()
+            .map(|path| {
+                info(app, &format!("%IPSET%: {}", path.display()));
+                format!("--ipset=\"{}\"", path.display())
+            })
+            .collect();
 
-        args = args.replace("%IPSET%", &format!("--ipset=\"{}\"", ipset_path.display()));
+        args = args.replace("%IPSET%", &ipset_args.join(" "));
 
         if args.contains("%LISTS%") {
             info(app, &format!("%LISTS%: {}", lists_dir.display()));
@@ -467,24 +650,36 @@ impl Zapret {
                 .collect();
             args.push_str(&hosts);
         }
-        args.trim().to_string()
+        AutoHostlist::args(app, name, args.trim())
     }
 
-    pub fn start_service(app: &AppHandle, index: i32, ipset_config: Option<String>) {
-        Self::stop_service(app);
+    pub fn start_service(
+        app: &AppHandle,
+        index: i32,
+        ipsets: Option<Vec<String>>,
+    ) -> Result<(), ServiceError> {
+        if !is_elevated() {
+            return Err(ServiceError::NotElevated);
+        }
+        Self::remove_services(app)?;
         let list = Self::get_list_strategies(app);
-        let name = match list.get((index - 1).max(0) as usize) {
-            Some(n) => n,
-            None => {
-                info(app, "Ошибка: Стратегия не найдена.");
-                return;
-            }
-        };
-
-        let strategy_raw =
-            fs::read_to_string(Self::zapret_path(app, "strategies").join(name)).unwrap_or_default();
-        let final_args = Self::build_full_args(app, strategy_raw.trim(), ipset_config);
+        let name = list
+            .get((index - 1).max(0) as usize)
+            .ok_or_else(|| ServiceError::strategy(format!("стратегия #{} не найдена", index)))?;
+
+        let strategy_raw = fs::read_to_string(Self::zapret_path(app, "strategies").join(name))
+            .map_err(|e| ServiceError::strategy(format!("{}: {}", name, e)))?;
+        if strategy_raw.trim().is_empty() {
+            return Err(ServiceError::strategy(format!("{} пустая", name)));
+        }
+        let ipsets = ipsets.unwrap_or_else(Self::ipset_selection);
+        let final_args = Self::build_full_args(app, name, strategy_raw.trim(), &ipsets);
         let bin = Self::zapret_path(app, "bin/winws.exe");
+        if !bin.exists() {
+            return Err(ServiceError::EngineMissing {
+                path: bin.display().to_string(),
+            });
+        }
 
         let _ = sh!(
             "netsh",
@@ -501,25 +696,117 @@ impl Zapret {
             final_args
         );
 
-        if let Ok(s) = sh!("powershell", "-NoProfile", "-Command", &cmd) {
-            let s: std::process::ExitStatus = s;
-            if s.success() {
-                let _ = sh!("sc", "start", "zapret");
-                let _ = RegKey::predef(HKEY_LOCAL_MACHINE)
-                    .create_subkey(HKLM_PATH)
-                    .map(|(k, _)| k.set_value("zapret-discord-youtube", name));
-                info(app, &format!("запущено: {}", name));
+        let out = sh_output!("powershell", "-NoProfile", "-Command", &cmd).map_err(|e| {
+            ServiceError::ServiceCreateFailed {
+                exit_code: None,
+                stderr: e.to_string(),
             }
+        })?;
+        if !out.status.success() {
+            return Err(ServiceError::ServiceCreateFailed {
+                exit_code: out.status.code(),
+                stderr: output_text(&out),
+            });
+        }
+
+        let out =
+            sh_output!("sc", "start", "zapret").map_err(|e| ServiceError::DriverLoadFailed {
+                exit_code: None,
+                stderr: e.to_string(),
+            })?;
+        if !out.status.success() {
+            let err = ServiceError::DriverLoadFailed {
+                exit_code: out.status.code(),
+                stderr: output_text(&out),
+            };
+            info(app, &err.to_string());
+            let _ = sh!("sc", "delete", "zapret");
+            return Err(err);
         }
+
+        #[cfg(windows)]
+        let _ = RegKey::predef(HKEY_LOCAL_MACHINE)
+            .create_subkey(HKLM_PATH)
+            .map(|(k, _)| {
+                let _ = k.set_value(IPSET_VALUE, &ipsets);
+                k.set_value("zapret-discord-youtube", name)
+            });
+        info(app, &format!("запущено: {}", name));
+        if let Err(e) = Forwarder::sync(app) {
+            info(app, &format!("dns forwarder err: {}", e));
+        }
+        Ok(())
     }
 
-    pub fn stop_service(app: &AppHandle) {
+    pub fn stop_service(app: &AppHandle) -> Result<(), ServiceError> {
+        Self::remove_services(app)?;
+        Forwarder::stop(app);
+        Ok(())
+    }
+
+    // при перезапуске форвардер не трогаем, чтобы не терять кеш
+    fn remove_services(app: &AppHandle) -> Result<(), ServiceError> {
+        if !is_elevated() {
+            return Err(ServiceError::NotElevated);
+        }
         let _ = sh!("taskkill", "/F", "/IM", "winws.exe", "/T");
         for d in ["WinDivert", "WinDivert14", "zapret"] {
             info(app, &format!("Удаляю сервис {}...", d));
             let _ = sh!("net", "stop", d);
-            let _ = sh!("sc", "delete", d);
+            let out = match sh_output!("sc", "delete", d) {
+                Ok(out) => out,
+                Err(e) => {
+                    return Err(ServiceError::ServiceRemoveFailed {
+                        name: d.to_string(),
+                        exit_code: None,
+                        stderr: e.to_string(),
+                    });
+                }
+            };
+            // 1060 - сервиса нет, 1072 - уже помечен на удаление
+            match out.status.code() {
+                Some(0) | Some(1060) | Some(1072) => {}
+                code => {
+                    return Err(ServiceError::ServiceRemoveFailed {
+                        name: d.to_string(),
+                        exit_code: code,
+                        stderr: output_text(&out),
+                    });
+                }
+            }
         }
         info(app, "Сервисы ZAPRET-а очищены и удалены.");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hostnames() {
+        for s in [
+            "example.com",
+            "sub.my-site.ru",
+            "1.example.com",
+            "пример.рф",
+            "xn--e1afmkfd.xn--p1ai",
+        ] {
+            assert!(Zapret::is_hostname(s), "{}", s);
+        }
+        for s in [
+            "1.2.3",
+            "1.2.3.4",
+            "256.1.1.1",
+            "example",
+            "a..b",
+            "-a.com",
+            "a-.com",
+            "a_b.com",
+            "a.b.",
+        ] {
+            assert!(!Zapret::is_hostname(s), "{}", s);
+        }
     }
 }
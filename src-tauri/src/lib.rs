@@ -53,6 +53,18 @@ pub fn run() {
             open_ipset_dir,
             get_hosts_data,
             save_hosts_selection,
+            preview_hosts_selection,
+            get_hosts_selection,
+            get_hosts_conflicts,
+            get_hosts_backups,
+            preview_hosts_restore,
+            restore_hosts_backup,
+            get_hosts_sources,
+            save_hosts_source,
+            remove_hosts_source,
+            check_hosts_health,
+            get_hosts_custom_entries,
+            set_hosts_custom_entries,
             check_winws_update,
             open_strats_dir,
             run_cleanup,
@@ -61,11 +73,54 @@ pub fn run() {
             apply_strategy_update,
             check_strategy_updates,
             resolve_host,
+            get_dns_settings,
+            set_dns_settings,
+            check_dns_poisoning,
+            check_hostlist_poisoning,
+            get_network_change_action,
+            set_network_change_action,
+            get_forwarder_settings,
+            set_forwarder_settings,
+            get_forwarder_status,
             add_ip,
             get_proxy_list,
             check_proxy_ping,
             main_window_init,
-            update_tls_bin
+            update_tls_bin,
+            scan_conflicts,
+            fix_conflicts,
+            get_activation_rules,
+            save_activation_rules,
+            get_network_info,
+            get_network_profiles,
+            save_network_profiles,
+            apply_network_profile,
+            run_health_check,
+            compact_ipset,
+            get_ipset_selection,
+            set_ipset_selection,
+            hostlist_add,
+            hostlist_remove,
+            hostlist_search,
+            get_strategy_profiles,
+            get_auto_hostlist_profiles,
+            set_auto_hostlist_profiles,
+            get_learned_domains,
+            promote_learned_domains,
+            discard_learned_domains,
+            get_exclusions,
+            add_exclusions,
+            remove_exclusions,
+            set_corporate_domains,
+            lookup_lists,
+            get_subscriptions,
+            save_subscription,
+            remove_subscription,
+            refresh_subscriptions,
+            import_asn_dataset,
+            get_asn_dataset_info,
+            generate_asn_ipset,
+            resolve_hostlist
         ])
         .setup(|app| {
             if let Ok(path) = app.path().executable_dir() {
@@ -98,6 +153,16 @@ pub fn run() {
                     }
                 })
                 .build(app)?;
+            crate::bypass::rules::Rules::spawn_monitor(app.handle().clone());
+            crate::bypass::netwatch::NetWatch::spawn(app.handle().clone());
+            crate::bypass::subscriptions::Subscriptions::spawn_scheduler(app.handle().clone());
+            crate::bypass::hosts::Hosts::spawn_scheduler(app.handle().clone());
+            // сервис переживает перезапуск zust, форвардер живёт в процессе
+            if crate::bypass::zapret::Zapret::get_strategy() != "Отсутствует"
+                && let Err(e) = crate::bypass::forwarder::Forwarder::sync(app.handle())
+            {
+                crate::utils::info(app.handle(), &format!("dns forwarder err: {}", e));
+            }
             Ok(())
         })
         .on_window_event(|window, event| {
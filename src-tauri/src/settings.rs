@@ -1,4 +1,11 @@
+use crate::bypass::autohostlist;
+use crate::bypass::dns::DnsSettings;
+use crate::bypass::forwarder::ForwarderSettings;
+use crate::bypass::network::NetworkProfile;
+use crate::bypass::rules::ActivationRule;
+use crate::bypass::watchdog::NetworkChangeAction;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
@@ -11,7 +18,42 @@ pub struct Settings {
     pub animation_enabled: bool,
     pub devtools: bool,
     pub game_filter: bool,
+    #[serde(default)]
+    pub activation_rules: Vec<ActivationRule>,
+    #[serde(default)]
+    pub network_profiles: Vec<NetworkProfile>,
+    #[serde(default)]
+    pub on_network_change: NetworkChangeAction,
+    #[serde(default = "default_prefix_v4")]
+    pub ipset_prefix_v4: u8,
+    #[serde(default = "default_prefix_v6")]
+    pub ipset_prefix_v6: u8,
+    #[serde(default)]
+    pub ipset_selection: Vec<String>,
+    #[serde(default = "default_true")]
+    pub protect_local_networks: bool,
+    #[serde(default)]
+    pub corporate_domains: Vec<String>,
+    #[serde(default, deserialize_with = "autohostlist::by_strategy")]
+    pub auto_hostlist_profiles: HashMap<String, Vec<usize>>,
+    #[serde(default)]
+    pub dns: DnsSettings,
+    #[serde(default)]
+    pub dns_forwarder: ForwarderSettings,
 }
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_prefix_v4() -> u8 {
+    24
+}
+
+fn default_prefix_v6() -> u8 {
+    64
+}
+
 pub fn get_config_path() -> PathBuf {
     let mut path = std::env::current_exe().unwrap_or_default();
     path.pop();
@@ -40,5 +82,16 @@ pub fn load_settings() -> Settings {
         animation_enabled: true,
         devtools: false,
         game_filter: false,
+        activation_rules: Vec::new(),
+        network_profiles: Vec::new(),
+        on_network_change: NetworkChangeAction::None,
+        ipset_prefix_v4: default_prefix_v4(),
+        ipset_prefix_v6: default_prefix_v6(),
+        ipset_selection: Vec::new(),
+        protect_local_networks: true,
+        corporate_domains: Vec::new(),
+        auto_hostlist_profiles: HashMap::new(),
+        dns: DnsSettings::default(),
+        dns_forwarder: ForwarderSettings::default(),
     }
 }
@@ -1,20 +1,35 @@
-use crate::bypass::hosts::Hosts;
+use crate::bypass::asn::{Asn, AsnIpsetReport, DatasetInfo};
+use crate::bypass::autohostlist::{AutoHostlist, FilterProfile, LearnedDomain};
+use crate::bypass::batch::{BatchReport, BatchResolve};
+use crate::bypass::conflicts::{Conflict, ConflictFix, Conflicts};
+use crate::bypass::dns::{Dns, DnsSettings, ResolveReport, ResolverPreset, ServiceHint};
+use crate::bypass::engine::Engine;
+use crate::bypass::errors::ServiceError;
+use crate::bypass::exclusions::{ExclusionKind, Exclusions, ExclusionsView};
+use crate::bypass::forwarder::{Forwarder, ForwarderSettings, ForwarderStatus};
+use crate::bypass::health::{Health, HealthReport};
+use crate::bypass::hostlist::{Hostlist, HostlistReport};
+use crate::bypass::hosts::{Hosts, HostsPreview, HostsSelection, HostsSource, HostsSourceData};
+use crate::bypass::hostsbackup::HostsBackup;
+use crate::bypass::hostsfile::HostsConflict;
+use crate::bypass::hostshealth::{HostsHealth, HostsHealthReport};
+use crate::bypass::ipset::{self, CompactReport};
+use crate::bypass::lookup::{Lookup, LookupReport};
+use crate::bypass::network::{Network, NetworkInfo, NetworkProfile, SystemProbe};
+use crate::bypass::poison::{BulkPoisonReport, PoisonCheck, PoisonReport};
 use crate::bypass::proxies::{Proxies, Proxy};
-use crate::bypass::zapret::Zapret;
+use crate::bypass::rules::{ActivationRule, Rules};
+use crate::bypass::subscriptions::{Subscription, Subscriptions};
+use crate::bypass::watchdog::{NetworkChangeAction, Watchdog};
+use crate::bypass::zapret::{AddIpReport, Zapret};
 use crate::settings::{self, Settings};
 use crate::utils;
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::net::IpAddr;
 use std::process::Command;
 use std::{fs, path::PathBuf};
 use tauri::{Manager, WebviewUrl, WebviewWindowBuilder};
 
-#[derive(serde::Serialize)]
-pub struct HostsData {
-    pub date: String,
-    pub categories: HashMap<String, Vec<String>>,
-}
-
 #[tauri::command]
 pub fn open_link(url: String) -> Result<(), String> {
     tauri_plugin_opener::open_url(&url, None::<&str>).map_err(|e| e.to_string())
@@ -23,7 +38,9 @@ pub fn open_link(url: String) -> Result<(), String> {
 #[derive(Deserialize)]
 pub struct StartServiceArgs {
     pub index: i32,
-    pub ipset_config: Option<String>,
+    // None - взять сохранённый выбор
+    #[serde(default, alias = "ipsetConfig", deserialize_with = "ipset::selection")]
+    pub ipset_config: Option<Vec<String>>,
 }
 
 #[tauri::command]
@@ -65,15 +82,22 @@ pub fn open_strats_dir(app: tauri::AppHandle) {
 }
 
 #[tauri::command]
-pub async fn start_service(app: tauri::AppHandle, args: StartServiceArgs) -> Result<(), String> {
-    Zapret::start_service(&app, args.index, args.ipset_config);
-    Ok(())
+pub async fn start_service(
+    app: tauri::AppHandle,
+    args: StartServiceArgs,
+) -> Result<(), ServiceError> {
+    Engine::exclusive(|| Zapret::start_service(&app, args.index, args.ipset_config)).inspect_err(
+        |e| {
+            utils::info(&app, &format!("start_service err: {}", e));
+        },
+    )
 }
 
 #[tauri::command]
-pub async fn stop_service(app: tauri::AppHandle) -> Result<(), String> {
-    Zapret::stop_service(&app);
-    Ok(())
+pub async fn stop_service(app: tauri::AppHandle) -> Result<(), ServiceError> {
+    Engine::exclusive(|| Zapret::stop_service(&app)).inspect_err(|e| {
+        utils::info(&app, &format!("stop_service err: {}", e));
+    })
 }
 
 #[tauri::command]
@@ -130,11 +154,8 @@ pub async fn convert_multiple_bats(
 }
 
 #[tauri::command]
-pub async fn get_hosts_data(app: tauri::AppHandle) -> Result<HostsData, String> {
-    let content = Hosts::fetch(&app).await?;
-    let date = Hosts::get_update_date(&content);
-    let categories = Hosts::get_categories(&content);
-    Ok(HostsData { date, categories })
+pub async fn get_hosts_data(app: tauri::AppHandle) -> Result<Vec<HostsSourceData>, String> {
+    Hosts::fetch_all(&app).await
 }
 
 #[tauri::command]
@@ -145,10 +166,78 @@ pub async fn check_winws_update(app: tauri::AppHandle) -> Result<bool, String> {
 #[tauri::command]
 pub async fn save_hosts_selection(
     app: tauri::AppHandle,
-    selected_lines: Vec<String>,
-) -> Result<(), String> {
-    let data = selected_lines.join("\n");
-    Hosts::write(&app, &data)
+    selection: HostsSelection,
+) -> Result<Vec<HostsConflict>, String> {
+    Hosts::write(&app, selection).await
+}
+
+#[tauri::command]
+pub fn preview_hosts_selection(
+    app: tauri::AppHandle,
+    selection: HostsSelection,
+) -> Result<HostsPreview, String> {
+    Hosts::preview(&app, &selection)
+}
+
+#[tauri::command]
+pub async fn get_hosts_selection(app: tauri::AppHandle) -> HostsSelection {
+    Hosts::selection(&app).await
+}
+
+#[tauri::command]
+pub fn get_hosts_conflicts(app: tauri::AppHandle) -> Result<Vec<HostsConflict>, String> {
+    Hosts::conflicts(&app)
+}
+
+#[tauri::command]
+pub async fn get_hosts_sources(app: tauri::AppHandle) -> Vec<HostsSource> {
+    Hosts::sources(&app).await
+}
+
+#[tauri::command]
+pub async fn save_hosts_source(app: tauri::AppHandle, source: HostsSource) -> Result<(), String> {
+    Hosts::save_source(&app, source).await
+}
+
+#[tauri::command]
+pub async fn remove_hosts_source(app: tauri::AppHandle, id: String) -> Result<(), String> {
+    Hosts::remove_source(&app, &id).await
+}
+
+#[tauri::command]
+pub async fn check_hosts_health(
+    app: tauri::AppHandle,
+    source_id: String,
+) -> Result<HostsHealthReport, String> {
+    HostsHealth::check(&app, &source_id).await
+}
+
+#[tauri::command]
+pub fn get_hosts_custom_entries(app: tauri::AppHandle) -> Result<Vec<String>, String> {
+    Hosts::custom_entries(&app)
+}
+
+#[tauri::command]
+pub fn set_hosts_custom_entries(
+    app: tauri::AppHandle,
+    lines: Vec<String>,
+) -> Result<Vec<HostsConflict>, String> {
+    Hosts::set_custom_entries(&app, &lines)
+}
+
+#[tauri::command]
+pub fn get_hosts_backups(app: tauri::AppHandle) -> Vec<HostsBackup> {
+    Hosts::backups(&app)
+}
+
+#[tauri::command]
+pub fn preview_hosts_restore(app: tauri::AppHandle, name: String) -> Result<HostsPreview, String> {
+    Hosts::preview_restore(&app, &name)
+}
+
+#[tauri::command]
+pub fn restore_hosts_backup(app: tauri::AppHandle, name: String) -> Result<(), String> {
+    Hosts::restore(&app, &name)
 }
 
 #[tauri::command]
@@ -170,13 +259,85 @@ pub async fn sync_zapret_files(app: tauri::AppHandle) -> Result<(), String> {
 }
 
 #[tauri::command]
-pub async fn add_ip(app: tauri::AppHandle, file_name: String, ip: String) -> Result<(), String> {
+pub async fn add_ip(
+    app: tauri::AppHandle,
+    file_name: String,
+    ip: String,
+) -> Result<AddIpReport, String> {
     Zapret::add_ip(app, file_name, ip).await
 }
 
 #[tauri::command]
-pub async fn resolve_host(host: String) -> Result<String, String> {
-    Zapret::resolve_host(host).await
+pub async fn resolve_host(
+    host: String,
+    service: Option<ServiceHint>,
+    port: Option<u16>,
+) -> Result<ResolveReport, String> {
+    let resolver = Dns::shared()?;
+    Dns::resolve_service(
+        &resolver,
+        &host,
+        &service.unwrap_or(ServiceHint::None),
+        port,
+    )
+    .await
+}
+
+#[tauri::command]
+pub fn get_dns_settings() -> DnsSettings {
+    settings::load_settings().dns
+}
+
+#[tauri::command]
+pub async fn set_dns_settings(dns: DnsSettings) -> Result<Vec<IpAddr>, String> {
+    Dns::apply_settings(dns).await
+}
+
+#[tauri::command]
+pub async fn check_dns_poisoning(
+    app: tauri::AppHandle,
+    domain: String,
+) -> Result<PoisonReport, String> {
+    PoisonCheck::check(&app, &domain).await
+}
+
+#[tauri::command]
+pub async fn check_hostlist_poisoning(
+    app: tauri::AppHandle,
+    list_name: String,
+) -> Result<BulkPoisonReport, String> {
+    PoisonCheck::check_hostlist(&app, &list_name).await
+}
+
+#[tauri::command]
+pub fn get_network_change_action() -> NetworkChangeAction {
+    Watchdog::get_action()
+}
+
+#[tauri::command]
+pub fn set_network_change_action(
+    app: tauri::AppHandle,
+    action: NetworkChangeAction,
+) -> Result<(), String> {
+    Watchdog::set_action(&app, action)
+}
+
+#[tauri::command]
+pub fn get_forwarder_settings() -> ForwarderSettings {
+    settings::load_settings().dns_forwarder
+}
+
+#[tauri::command]
+pub async fn set_forwarder_settings(
+    app: tauri::AppHandle,
+    forwarder: ForwarderSettings,
+) -> Result<ForwarderStatus, String> {
+    Forwarder::apply_settings(&app, forwarder)
+}
+
+#[tauri::command]
+pub fn get_forwarder_status() -> ForwarderStatus {
+    Forwarder::status()
 }
 
 #[tauri::command]
@@ -205,3 +366,249 @@ pub fn main_window_init(app: tauri::AppHandle) {
 pub async fn update_tls_bin(app: tauri::AppHandle) -> Result<String, String> {
     Zapret::update_tls_bin(app).await
 }
+
+#[tauri::command]
+pub async fn scan_conflicts(app: tauri::AppHandle) -> Vec<Conflict> {
+    Conflicts::scan(&app)
+}
+
+#[tauri::command]
+pub async fn fix_conflicts(app: tauri::AppHandle, fixes: Vec<ConflictFix>) -> Result<(), String> {
+    Conflicts::fix(&app, fixes)
+}
+
+#[tauri::command]
+pub fn get_activation_rules() -> Vec<ActivationRule> {
+    Rules::get()
+}
+
+#[tauri::command]
+pub fn save_activation_rules(
+    app: tauri::AppHandle,
+    rules: Vec<ActivationRule>,
+) -> Result<(), String> {
+    Rules::save(&app, rules)
+}
+
+#[tauri::command]
+pub async fn get_network_info() -> Result<NetworkInfo, String> {
+    tauri::async_runtime::spawn_blocking(|| Network::detect(&SystemProbe))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_network_profiles() -> Vec<NetworkProfile> {
+    Network::get_profiles()
+}
+
+#[tauri::command]
+pub fn save_network_profiles(
+    app: tauri::AppHandle,
+    profiles: Vec<NetworkProfile>,
+) -> Result<(), String> {
+    Network::save_profiles(&app, profiles)
+}
+
+#[tauri::command]
+pub async fn apply_network_profile(app: tauri::AppHandle, id: String) -> Result<(), String> {
+    let profiles = Network::get_profiles();
+    let profile = profiles
+        .iter()
+        .find(|p| p.id == id)
+        .ok_or_else(|| format!("профиль {} не найден", id))?;
+    Network::apply(&app, profile)
+}
+
+#[tauri::command]
+pub async fn run_health_check(app: tauri::AppHandle) -> Result<HealthReport, String> {
+    tauri::async_runtime::spawn_blocking(move || Health::quick_check(&app))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn compact_ipset(
+    app: tauri::AppHandle,
+    file_name: String,
+) -> Result<CompactReport, String> {
+    Zapret::compact_ipset(&app, &file_name)
+}
+
+#[tauri::command]
+pub fn get_ipset_selection() -> Vec<String> {
+    Zapret::ipset_selection()
+}
+
+#[tauri::command]
+pub fn set_ipset_selection(
+    app: tauri::AppHandle,
+    ipsets: Vec<String>,
+) -> Result<Vec<String>, String> {
+    Zapret::set_ipset_selection(&app, ipsets)
+}
+
+#[tauri::command]
+pub fn hostlist_add(
+    app: tauri::AppHandle,
+    file_name: String,
+    domains: Vec<String>,
+) -> Result<HostlistReport, String> {
+    Hostlist::add(&app, &file_name, domains)
+}
+
+#[tauri::command]
+pub fn hostlist_remove(
+    app: tauri::AppHandle,
+    file_name: String,
+    domains: Vec<String>,
+) -> Result<Vec<String>, String> {
+    Hostlist::remove(&app, &file_name, domains)
+}
+
+#[tauri::command]
+pub fn hostlist_search(
+    app: tauri::AppHandle,
+    file_name: String,
+    query: String,
+) -> Result<Vec<String>, String> {
+    Hostlist::search(&app, &file_name, &query)
+}
+
+#[tauri::command]
+pub fn get_strategy_profiles(
+    app: tauri::AppHandle,
+    name: String,
+) -> Result<Vec<FilterProfile>, String> {
+    AutoHostlist::strategy_profiles(&app, &name)
+}
+
+#[tauri::command]
+pub fn get_auto_hostlist_profiles(strategy: String) -> Vec<usize> {
+    AutoHostlist::get_profiles(&strategy)
+}
+
+#[tauri::command]
+pub async fn set_auto_hostlist_profiles(
+    app: tauri::AppHandle,
+    strategy: String,
+    profiles: Vec<usize>,
+) -> Result<(), String> {
+    AutoHostlist::set_profiles(&app, &strategy, profiles)
+}
+
+#[tauri::command]
+pub fn get_learned_domains(app: tauri::AppHandle) -> Vec<LearnedDomain> {
+    AutoHostlist::learned(&app)
+}
+
+#[tauri::command]
+pub fn promote_learned_domains(
+    app: tauri::AppHandle,
+    domains: Vec<String>,
+    file_name: String,
+) -> Result<HostlistReport, String> {
+    AutoHostlist::promote(&app, domains, &file_name)
+}
+
+#[tauri::command]
+pub fn discard_learned_domains(
+    app: tauri::AppHandle,
+    domains: Vec<String>,
+) -> Result<Vec<String>, String> {
+    AutoHostlist::discard(&app, domains)
+}
+
+#[tauri::command]
+pub async fn get_exclusions(app: tauri::AppHandle) -> Result<ExclusionsView, String> {
+    tauri::async_runtime::spawn_blocking(move || Exclusions::get(&app))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn add_exclusions(
+    app: tauri::AppHandle,
+    kind: ExclusionKind,
+    entries: Vec<String>,
+) -> Result<Vec<String>, String> {
+    Exclusions::add(&app, kind, entries)
+}
+
+#[tauri::command]
+pub async fn remove_exclusions(
+    app: tauri::AppHandle,
+    kind: ExclusionKind,
+    entries: Vec<String>,
+) -> Result<Vec<String>, String> {
+    Exclusions::remove(&app, kind, entries)
+}
+
+#[tauri::command]
+pub async fn set_corporate_domains(
+    app: tauri::AppHandle,
+    domains: Vec<String>,
+) -> Result<(), String> {
+    Exclusions::set_corporate_domains(&app, domains)
+}
+
+#[tauri::command]
+pub fn lookup_lists(app: tauri::AppHandle, query: String) -> Result<LookupReport, String> {
+    Lookup::find(&app, &query)
+}
+
+#[tauri::command]
+pub async fn get_subscriptions(app: tauri::AppHandle) -> Vec<Subscription> {
+    Subscriptions::get(&app).await
+}
+
+#[tauri::command]
+pub async fn save_subscription(app: tauri::AppHandle, sub: Subscription) -> Result<(), String> {
+    Subscriptions::save(&app, sub).await
+}
+
+#[tauri::command]
+pub async fn remove_subscription(app: tauri::AppHandle, id: String) -> Result<(), String> {
+    Subscriptions::remove(&app, &id).await
+}
+
+#[tauri::command]
+pub async fn refresh_subscriptions(
+    app: tauri::AppHandle,
+    id: Option<String>,
+) -> Result<(), String> {
+    Subscriptions::refresh(&app, id.as_deref(), true).await
+}
+
+#[tauri::command]
+pub async fn import_asn_dataset(
+    app: tauri::AppHandle,
+    path: String,
+) -> Result<DatasetInfo, String> {
+    Asn::import_dataset(&app, &path)
+}
+
+#[tauri::command]
+pub async fn get_asn_dataset_info(app: tauri::AppHandle) -> Result<DatasetInfo, String> {
+    Asn::dataset_info(&app)
+}
+
+#[tauri::command]
+pub async fn generate_asn_ipset(
+    app: tauri::AppHandle,
+    asns: Vec<String>,
+    file_name: String,
+) -> Result<AsnIpsetReport, String> {
+    Asn::generate_ipset(&app, asns, &file_name)
+}
+
+#[tauri::command]
+pub async fn resolve_hostlist(
+    app: tauri::AppHandle,
+    list_name: String,
+    ipset_name: String,
+    resolvers: Vec<ResolverPreset>,
+    diff_only: bool,
+) -> Result<BatchReport, String> {
+    BatchResolve::run(&app, &list_name, &ipset_name, resolvers, diff_only).await
+}
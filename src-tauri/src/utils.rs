@@ -11,11 +11,29 @@ use tauri::Manager;
 #[macro_export]
 macro_rules! sh {
     ($cmd:expr, $($arg:expr),*) => {{
-        use std::os::windows::process::CommandExt;
-        std::process::Command::new($cmd)
-            .args([$($arg),*])
-            .creation_flags(0x08000000) // CREATE_NO_WINDOW
-            .status()
+        let mut command = std::process::Command::new($cmd);
+        command.args([$($arg),*]);
+        #[cfg(windows)]
+        {
+            use std::os::windows::process::CommandExt;
+            command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+        }
+        command.status()
+    }};
+}
+
+// как sh!, но с захватом stdout/stderr
+#[macro_export]
+macro_rules! sh_output {
+    ($cmd:expr, $($arg:expr),*) => {{
+        let mut command = std::process::Command::new($cmd);
+        command.args([$($arg),*]);
+        #[cfg(windows)]
+        {
+            use std::os::windows::process::CommandExt;
+            command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+        }
+        command.output()
     }};
 }
 fn write_to_log(app: &AppHandle, text: &str) {
@@ -67,3 +85,21 @@ pub fn list_files(path: PathBuf, ext: &str) -> Vec<String> {
         })
         .unwrap_or_default()
 }
+
+// пишем во временный файл и подменяем, чтобы не оставить полфайла
+pub fn write_atomic(path: &Path, content: &str) -> Result<(), String> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, content).map_err(|e| format!("ошибка записи {}: {}", tmp.display(), e))?;
+    fs::rename(&tmp, path).map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        format!("ошибка замены {}: {}", path.display(), e)
+    })
+}
+
+// имя .txt файла внутри папки zapret, без выхода наружу
+pub fn check_list_name(file_name: &str) -> Result<(), String> {
+    if file_name.contains(['/', '\\']) || file_name.contains("..") || !file_name.ends_with(".txt") {
+        return Err(format!("некорректное имя файла: {}", file_name));
+    }
+    Ok(())
+}
@@ -132,7 +132,7 @@ function App() {
       <IpsetModal
         isOpen={state.isIpsetModalOpen}
         onClose={() => { prefs.setIsIpsetModalOpen(false); prefs.setIpsetView('main'); }}
-        selectedIpset={zapret.selectedIpset}
+        selectedIpsets={zapret.selectedIpsets}
         ipsetView={state.ipsetView}
         setIpsetView={prefs.setIpsetView}
         customIpsetFiles={state.customIpsetFiles}
@@ -146,7 +146,7 @@ function App() {
       <ResolverModal
         isOpen={state.isResolverOpen}
         onClose={() => prefs.setIsResolverOpen(false)}
-        selectedIpset={zapret.selectedIpset}
+        selectedIpsets={zapret.selectedIpsets}
         onAdd={actions.addIp}
       />
       <ProxyModal isOpen={state.isProxyModalOpen} onClose={() => prefs.setIsProxyModalOpen(false)} />
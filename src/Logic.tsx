@@ -114,9 +114,23 @@ export function Logic() {
     }, []);
     const actions = {
         changeIpsetMode: async (mode: any, fileName: string | null = null) => {
-            const val = mode === 'Any' ? 'any' : mode === 'None' ? 'none' : (fileName || 'any');
-            zapret.setSelectedIpset(val);
-            localStorage.setItem("selected_ipset", val);
+            let val: string[];
+            if (mode === 'Any' || mode === 'None' || !fileName) {
+                val = [mode === 'None' ? 'none' : 'any'];
+            } else {
+                // файлы выбираются галочками, any/none при этом сбрасываются
+                const files = zapret.selectedIpsets.filter(f => f !== 'any' && f !== 'none');
+                val = files.includes(fileName) ? files.filter(f => f !== fileName) : [...files, fileName];
+                if (!val.length) val = ['none'];
+            }
+            try {
+                val = await invoke<string[]>('set_ipset_selection', { ipsets: val });
+            } catch (e) {
+                notify("Ошибка при выборе ipset", "error");
+                log("ipset err " + e);
+                return;
+            }
+            zapret.setSelectedIpsets(val);
             if (zapret.status === 'running') await zapret.startProcess(undefined, val);
         },
         handlePickFiles: async () => {
@@ -131,20 +145,26 @@ export function Logic() {
                 } catch (e) { notify("Ошибка при конвертации", "error"); }
             }
         },
-        addIp: async (ip: string) => {
-            const targetFile = (zapret.selectedIpset === 'any' || zapret.selectedIpset === 'none' || !zapret.selectedIpset)
-                ? 'ipset-all.txt'
-                : zapret.selectedIpset;
+        addIp: async (ips: string[]) => {
+            const targetFile = zapret.selectedIpsets.find(f => f !== 'any' && f !== 'none') || 'ipset-all.txt';
 
             try {
-                await invoke('add_ip', { fileName: targetFile, ip });
-                const message = `resolver - ip ${ip} -> ${targetFile}`;
-                log(message);
+                const report = { added: [] as string[], skipped: [] as string[] };
+                for (const ip of ips) {
+                    const r = await invoke<{ added: string[], skipped: string[] }>('add_ip', { fileName: targetFile, ip });
+                    report.added.push(...r.added);
+                    report.skipped.push(...r.skipped);
+                }
+                log(`resolver - ip ${ips.join(', ')} -> ${targetFile}: +${report.added.length}, skipped ${report.skipped.length}`);
+                if (report.added.length === 0) {
+                    notify(`Уже есть в ${targetFile}`, "info");
+                    return;
+                }
                 if (zapret.status === 'running') {
                     await zapret.stopProcess();
                     await zapret.startProcess();
                 }
-                notify(`Добавлено в ${targetFile}`, "success");
+                notify(`Добавлено в ${targetFile}: ${report.added.length}`, "success");
             } catch (e) {
                 notify("Ошибка при записи IP", "error");
                 log("ipset err " + e);
@@ -3,14 +3,30 @@ import { invoke } from "@tauri-apps/api/core";
 import { notify } from './Notifications';
 
 export type Status = 'stopped' | 'running' | 'loading';
-const DEFAULT_IPSET = "none";
+const DEFAULT_IPSETS = ["none"];
+
+export type ServiceErrorCode =
+    'NOT_ELEVATED' | 'ENGINE_MISSING' | 'SERVICE_CREATE_FAILED' |
+    'SERVICE_REMOVE_FAILED' | 'DRIVER_LOAD_FAILED' | 'STRATEGY_INVALID';
+
+const SERVICE_ERRORS: Record<ServiceErrorCode, string> = {
+    NOT_ELEVATED: "Нет прав администратора",
+    ENGINE_MISSING: "Не найден winws.exe",
+    SERVICE_CREATE_FAILED: "Не удалось создать сервис",
+    SERVICE_REMOVE_FAILED: "Не удалось удалить сервис",
+    DRIVER_LOAD_FAILED: "Не удалось загрузить драйвер WinDivert",
+    STRATEGY_INVALID: "Стратегия повреждена или не найдена",
+};
+
+export const serviceErrorText = (e: any): string =>
+    SERVICE_ERRORS[e?.code as ServiceErrorCode] || "Случилась непредвиденная ошибка.";
 
 export function ZapretUtils() {
     const [status, setStatus] = useState<Status>('loading');
     const [stratName, setStratName] = useState('Проверяем...');
     const [configs, setConfigs] = useState<string[]>([]);
     const [selectedConfig, setSelectedConfig] = useState("");
-    const [selectedIpset, setSelectedIpset] = useState<string | null>(null);
+    const [selectedIpsets, setSelectedIpsets] = useState<string[]>([]);
 
     const checkZapret = async () => {
         const name = await invoke<string>('get_strategy');
@@ -23,16 +39,16 @@ export function ZapretUtils() {
         try {
             await invoke('stop_service');
             await checkZapret();
-        } catch {
-            notify("Ошибка при остановке", "error");
+        } catch (e) {
+            notify(serviceErrorText(e), "error");
             setStatus('stopped');
         }
     };
 
-    const startProcess = async (forcedConfig?: string, forcedIpset?: string) => {
+    const startProcess = async (forcedConfig?: string, forcedIpsets?: string[]) => {
         setStatus('loading');
         const configName = forcedConfig || selectedConfig || localStorage.getItem("selected_strategy") || "";
-        const ipsetConfig = forcedIpset || selectedIpset || localStorage.getItem("selected_ipset") || DEFAULT_IPSET;
+        const ipsetConfig = forcedIpsets || (selectedIpsets.length ? selectedIpsets : DEFAULT_IPSETS);
         const idx = configs.indexOf(configName) + 1;
         if (idx <= 0) {
             console.error("Config not found:", configName);
@@ -44,6 +60,7 @@ export function ZapretUtils() {
             setTimeout(checkZapret, 500);
         } catch (e) {
             console.error(e);
+            notify(serviceErrorText(e), "error");
             setStatus('stopped');
         }
     };
@@ -53,8 +70,13 @@ export function ZapretUtils() {
         setConfigs(list);
 
         const currentRunning = await invoke<string>('get_strategy');
-        const savedIpset = localStorage.getItem("selected_ipset") || DEFAULT_IPSET;
-        setSelectedIpset(savedIpset);
+        let savedIpsets = await invoke<string[]>('get_ipset_selection');
+        const legacyIpset = localStorage.getItem("selected_ipset");
+        if (!savedIpsets.length && legacyIpset) {
+            savedIpsets = await invoke<string[]>('set_ipset_selection', { ipsets: [legacyIpset] }).catch(() => []);
+            localStorage.removeItem("selected_ipset");
+        }
+        setSelectedIpsets(savedIpsets.length ? savedIpsets : DEFAULT_IPSETS);
 
         if (currentRunning !== "Отсутствует") {
             setStratName(currentRunning);
@@ -71,8 +93,8 @@ export function ZapretUtils() {
     };
 
     return {
-        status, stratName, configs, selectedConfig, selectedIpset,
-        setConfigs, setSelectedConfig, setSelectedIpset,
+        status, stratName, configs, selectedConfig, selectedIpsets,
+        setConfigs, setSelectedConfig, setSelectedIpsets,
         startProcess, stopProcess, checkZapret, init
     };
 }
\ No newline at end of file
@@ -1,4 +1,4 @@
-import { useEffect, useState, useMemo } from "react";
+import { useEffect, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { notify } from "../Notifications"
 import { log } from "../Logic";
@@ -8,32 +8,188 @@ interface Props {
     onClose: () => void;
 }
 
+interface HostsConflict {
+    name: string;
+    entries: { ip: string, line: number, block: string | null }[];
+    effective: string;
+}
+
+interface DiffLine {
+    op: 'context' | 'add' | 'remove';
+    line: number;
+    text: string;
+}
+
+interface HostsPreview {
+    diff: { lines: DiffLine[], added: number, removed: number };
+    conflicts: HostsConflict[];
+}
+
+interface HostsSource {
+    id: string;
+    name: string;
+    url: string;
+    enabled: boolean;
+    marker: { begin: string, end: string } | null;
+    lastFetched: string | null;
+    lastError: string | null;
+}
+
+interface HostsCategory {
+    id: string;
+    name: string;
+    lines: string[];
+}
+
+interface HostsSelection {
+    categories: Record<string, string[]>;
+    excluded: Record<string, string[]>;
+    autoUpdateHours: number;
+}
+
+interface HostsSourceData {
+    id: string;
+    name: string;
+    date: string;
+    categories: HostsCategory[];
+    cached: boolean;
+    fetchedAt: string | null;
+    error: string | null;
+}
+
+interface EntryHealth {
+    category: string;
+    line: string;
+    ip: string;
+    name: string;
+    status: 'dead' | 'mismatch' | 'badCertificate';
+    detail: string | null;
+}
+
+interface HostsHealthReport {
+    source: string;
+    checked: number;
+    ok: number;
+    skipped: number;
+    problems: EntryHealth[];
+}
+
+const HEALTH_LABEL = { dead: 'не отвечает', mismatch: 'чужой сертификат', badCertificate: 'плохой сертификат' };
+
+interface HostsBackup {
+    name: string;
+    createdAt: string | null;
+    size: number;
+}
+
+const DIFF_SIGN = { context: ' ', add: '+', remove: '-' };
+const LIMIT_DIFF = 300;
+
+// система берёт первую запись, о перекрытых предупреждаем
+const reportConflicts = (conflicts: HostsConflict[]) => {
+    conflicts.forEach(c => log(`hosts конфликт ${c.name}: ${c.entries.map(e => `${e.ip} (стр. ${e.line})`).join(', ')}, работает ${c.effective}`));
+    if (conflicts.length > 0) {
+        notify(`В hosts ${conflicts.length} доменов с разными IP, подробности в логе.`, "info");
+    }
+};
+
 export const HostsModal = ({ isOpen, onClose }: Props) => {
     const ERR_TIMEOUT = "ERR: (Проверьте интернет!)";
     const ERR_READING = "ERR: (Не удалось прочитать данные?)"
-    const BASIC = "Базовая";
-    const [data, setData] = useState<Record<string, string[]>>({});
+    const [data, setData] = useState<HostsSourceData[]>([]);
     const [loading, setLoading] = useState(true);
     const [error, setError] = useState<string | null>(null);
+    // ключи вида "<id источника>/<категория>"
     const [expanded, setExpanded] = useState<string[]>([]);
     const [selected, setSelected] = useState<string[]>([]);
     const [saving, setSaving] = useState(false);
-    const [lastUpdate, setLastUpdate] = useState<string>("");
+    // что покажем перед записью: новая выборка или откат к бэкапу
+    const [preview, setPreview] = useState<{ data: HostsPreview, selection: HostsSelection | null, backup: string | null } | null>(null);
+    const [backups, setBackups] = useState<HostsBackup[]>([]);
+    const [sources, setSources] = useState<HostsSource[]>([]);
+    const [newSource, setNewSource] = useState({ name: "", url: "" });
+    const [custom, setCustom] = useState("");
+    const [health, setHealth] = useState<Record<string, HostsHealthReport>>({});
+    const [checking, setChecking] = useState<string | null>(null);
+    // строки, которые не пойдут в hosts: "<id источника>/<строка>"
+    const [excluded, setExcluded] = useState<string[]>([]);
+    const [autoUpdateHours, setAutoUpdateHours] = useState(24);
 
     const [shouldRender, setShouldRender] = useState(isOpen);
     const [isAnimatingOut, setIsAnimatingOut] = useState(false);
 
-    const TIMEOUT = 10 * 1000;
+    // источники качаются параллельно, на каждый 8 секунд и потом кеш
+    const TIMEOUT = 20 * 1000;
     const LIMIT_DOMAINS = 50;
 
-    const handleClear = async () => {
+    const loadBackups = () => {
+        invoke<HostsBackup[]>("get_hosts_backups").then(setBackups).catch(console.error);
+    };
+
+    const loadSources = () => {
+        invoke<HostsSource[]>("get_hosts_sources").then(setSources).catch(console.error);
+    };
+
+    const loadCustom = () => {
+        invoke<string[]>("get_hosts_custom_entries").then(lines => setCustom(lines.join("\n"))).catch(console.error);
+    };
+
+    // выбор хранит бэкенд, ключи тут - "<id источника>/<id категории или строка>"
+    const loadSelection = () => {
+        invoke<HostsSelection>("get_hosts_selection").then(sel => {
+            setSelected(Object.entries(sel.categories).flatMap(([src, ids]) => ids.map(id => `${src}/${id}`)));
+            setExcluded(Object.entries(sel.excluded).flatMap(([src, lines]) => lines.map(l => `${src}/${l}`)));
+            setAutoUpdateHours(sel.autoUpdateHours);
+        }).catch(console.error);
+    };
+
+    const groupKeys = (keys: string[]) => {
+        const map: Record<string, string[]> = {};
+        keys.forEach(key => {
+            const at = key.indexOf('/');
+            (map[key.slice(0, at)] ??= []).push(key.slice(at + 1));
+        });
+        return map;
+    };
+
+    const showPreview = async (selection: HostsSelection | null, backup: string | null) => {
+        try {
+            const data = backup
+                ? await invoke<HostsPreview>("preview_hosts_restore", { name: backup })
+                : await invoke<HostsPreview>("preview_hosts_selection", { selection });
+            if (data.diff.lines.length === 0) {
+                notify("hosts уже в таком состоянии.", "info");
+                return;
+            }
+            setPreview({ data, selection, backup });
+        } catch (e) {
+            notify("Не удалось подготовить изменения hosts.", "error");
+            log("hosts err " + e);
+        }
+    };
+
+    const handleClear = () => showPreview({ categories: {}, excluded: groupKeys(excluded), autoUpdateHours }, null);
+
+    const applyPreview = async () => {
+        if (!preview) return;
         setSaving(true);
         try {
-            await invoke("save_hosts_selection", { selectedLines: [] });
-            notify("Записи успешно удалены!", "success");
+            if (preview.backup) {
+                await invoke("restore_hosts_backup", { name: preview.backup });
+                notify("hosts восстановлен!", "success");
+            } else {
+                const conflicts = await invoke<HostsConflict[]>("save_hosts_selection", { selection: preview.selection });
+                const applied = Object.values(preview.selection?.categories ?? {}).some(ids => ids.length > 0);
+                notify(applied ? "Метод hosts успешно применен!" : "Записи успешно удалены!", "success");
+                if (applied) {
+                    notify("Для некоторых приложений потребуется перезагрузка.", "success");
+                }
+                reportConflicts(conflicts);
+            }
+            setPreview(null);
             onClose();
         } catch (e) {
-            notify("Ошибка при очистке hosts.", "error");
+            notify("Произошла ошибка во время записи hosts.", "error");
             log("hosts err " + e);
         } finally {
             setSaving(false);
@@ -44,13 +200,17 @@ export const HostsModal = ({ isOpen, onClose }: Props) => {
         setLoading(true);
         setError(null);
         try {
-            const fetchPromise = invoke<{ date: string, categories: Record<string, string[]> }>("get_hosts_data");
+            const fetchPromise = invoke<HostsSourceData[]>("get_hosts_data");
             const timeoutPromise = new Promise((_, reject) =>
                 setTimeout(() => reject(new Error("timeout")), TIMEOUT)
             );
-            const result = await (Promise.race([fetchPromise, timeoutPromise]) as Promise<{ date: string, categories: Record<string, string[]> }>);
-            setData(result.categories);
-            setLastUpdate(result.date);
+            const result = await (Promise.race([fetchPromise, timeoutPromise]) as Promise<HostsSourceData[]>);
+            result.filter(s => s.error).forEach(s => log(`hosts ${s.id}: ${s.error}${s.cached ? ' (взят кеш)' : ''}`));
+            // ни сети, ни кеша - показываем ошибку, а не пустой список
+            if (result.every(s => s.categories.length === 0)) {
+                throw new Error("timeout");
+            }
+            setData(result);
         } catch (e: any) {
             console.error(e);
             setError(e.message === "timeout" ? ERR_TIMEOUT : ERR_READING);
@@ -74,20 +234,19 @@ export const HostsModal = ({ isOpen, onClose }: Props) => {
     }, [isOpen]);
 
     useEffect(() => {
-        if (isOpen && Object.keys(data).length === 0) {
+        if (isOpen && data.length === 0) {
             loadHosts();
         }
+        if (isOpen) {
+            setPreview(null);
+            loadBackups();
+            loadSources();
+            loadCustom();
+            loadSelection();
+        }
     }, [isOpen]);
 
-    const sortedKeys = useMemo(() => {
-        return Object.keys(data).sort((a, b) => {
-            if (a === BASIC) return -1;
-            if (b === BASIC) return 1;
-            if (a === "Блокировка") return 1;
-            if (b === "Блокировка") return -1;
-            return a.localeCompare(b);
-        });
-    }, [data]);
+    const groupsCount = data.reduce((n, src) => n + src.categories.length, 0);
 
     if (!shouldRender) return null;
 
@@ -95,25 +254,95 @@ export const HostsModal = ({ isOpen, onClose }: Props) => {
         setExpanded(prev => prev.includes(name) ? prev.filter(n => n !== name) : [...prev, name]);
     };
 
-    const handleSave = async () => {
+    const handleSave = () => {
+        // категории пропавших из списка источников не шлём, бэкенд их не знает
+        const known = selected.filter(key => data.some(src => key.startsWith(`${src.id}/`)));
+        showPreview({ categories: groupKeys(known), excluded: groupKeys(excluded), autoUpdateHours }, null);
+    };
+
+    const problemsOf = (srcId: string) => {
+        const map = new Map<string, EntryHealth[]>();
+        health[srcId]?.problems.forEach(p => map.set(p.line, [...(map.get(p.line) ?? []), p]));
+        return map;
+    };
+
+    const checkHealth = async (srcId: string) => {
+        setChecking(srcId);
+        try {
+            const report = await invoke<HostsHealthReport>("check_hosts_health", { sourceId: srcId });
+            setHealth(prev => ({ ...prev, [srcId]: report }));
+            report.problems.forEach(p => log(`hosts ${p.name} → ${p.ip}: ${HEALTH_LABEL[p.status]}${p.detail ? ` (${p.detail})` : ''}`));
+            notify(`Проверено ${report.checked - report.skipped}: рабочих ${report.ok}, проблемных ${report.problems.length}.`, report.problems.length ? "info" : "success");
+        } catch (e) {
+            notify(`Не удалось проверить записи: ${e}`, "error");
+        } finally {
+            setChecking(null);
+        }
+    };
+
+    const excludeBroken = (srcId: string) => {
+        const keys = [...problemsOf(srcId).keys()].map(l => `${srcId}/${l}`);
+        setExcluded(prev => [...new Set([...prev, ...keys])]);
+    };
+
+    const toggleExcluded = (key: string) => {
+        setExcluded(prev => prev.includes(key) ? prev.filter(k => k !== key) : [...prev, key]);
+    };
+
+    const saveCustom = async () => {
         setSaving(true);
         try {
-            const formattedLines = selected.flatMap(cat => [
-                `# ${cat}`,
-                ...data[cat],
-                ""
-            ]);
-            await invoke("save_hosts_selection", { selectedLines: formattedLines });
-            notify("Метод hosts успешно применен!", "success");
-            notify("Для некоторых приложений потребуется перезагрузка.", "success");
-            onClose();
+            const lines = custom.split("\n").map(l => l.trimEnd());
+            while (lines.length > 0 && lines[lines.length - 1] === "") lines.pop();
+            const conflicts = await invoke<HostsConflict[]>("set_hosts_custom_entries", { lines });
+            notify("Свои записи сохранены!", "success");
+            reportConflicts(conflicts);
+            loadBackups();
         } catch (e) {
-            notify("Произошла ошибка во время записи hosts.", "error");
+            notify(`Не удалось сохранить свои записи: ${e}`, "error");
         } finally {
             setSaving(false);
         }
     };
 
+    const addSource = async () => {
+        const url = newSource.url.trim();
+        // id из адреса: host + путь, только допустимые символы
+        const id = url.replace(/^https?:\/\//, "").toLowerCase().replace(/[^a-z0-9.-]+/g, "-").replace(/^[.-]+|[.-]+$/g, "");
+        try {
+            await invoke("save_hosts_source", {
+                source: { id, name: newSource.name.trim() || id, url, enabled: true, marker: null, lastFetched: null, lastError: null }
+            });
+            setNewSource({ name: "", url: "" });
+            loadSources();
+            loadHosts();
+        } catch (e) {
+            notify(`Не удалось добавить источник: ${e}`, "error");
+        }
+    };
+
+    const toggleSource = async (source: HostsSource) => {
+        try {
+            await invoke("save_hosts_source", { source: { ...source, enabled: !source.enabled } });
+            loadSources();
+            loadHosts();
+        } catch (e) {
+            notify(`Не удалось изменить источник: ${e}`, "error");
+        }
+    };
+
+    const removeSource = async (id: string) => {
+        try {
+            await invoke("remove_hosts_source", { id });
+            setSelected(prev => prev.filter(k => !k.startsWith(`${id}/`)));
+            loadSources();
+            loadBackups();
+            loadHosts();
+        } catch (e) {
+            notify(`Не удалось удалить источник: ${e}`, "error");
+        }
+    };
+
     return (
         <div
             className={`modal-overlay ${isAnimatingOut ? 'closing' : ''}`}
@@ -128,13 +357,18 @@ export const HostsModal = ({ isOpen, onClose }: Props) => {
                         <div className="modal-title-row">
                             <h3>Настройка</h3>
                             {!loading && !error && (
-                                <span className="hosts-badge">{Object.keys(data).length} групп</span>
+                                <span className="hosts-badge">{groupsCount} групп</span>
                             )}
                         </div>
-                        <div className="hosts-update-info">
-                            <span className="calendar-icon">📅</span>
-                            <span className="calendar-text">Обновлено: {lastUpdate}</span>
-                        </div>
+                        {data.map(src => (
+                            <div key={src.id} className="hosts-update-info">
+                                <span className="calendar-icon">📅</span>
+                                <span className="calendar-text">
+                                    {src.name}: {src.date}
+                                    {src.cached && ` (офлайн, копия от ${src.fetchedAt ? new Date(src.fetchedAt).toLocaleString() : '?'})`}
+                                </span>
+                            </div>
+                        ))}
                     </div>
                     <div className="header-decoration">
                         <div className="glow-dot"></div>
@@ -142,14 +376,37 @@ export const HostsModal = ({ isOpen, onClose }: Props) => {
                 </div>
 
                 <div className="modal-body">
-                    {loading && (
+                    {preview && (
+                        <div className="hosts-scroll-area">
+                            <p className="clear-hint hosts-diff-summary">
+                                {preview.backup ? `Откат к ${preview.backup}: ` : ''}
+                                +{preview.data.diff.added} / -{preview.data.diff.removed} строк.
+                                {preview.data.conflicts.length > 0 && ` Конфликтов: ${preview.data.conflicts.length}.`}
+                                {' '}Текущий hosts сохранится в резервную копию.
+                            </p>
+                            <div className="hosts-diff">
+                                {preview.data.diff.lines.slice(0, LIMIT_DIFF).map((l, i) => (
+                                    <div key={i} className={`hosts-diff-line ${l.op}`}>
+                                        <span className="hosts-diff-num">{l.line}</span>
+                                        <span>{DIFF_SIGN[l.op]} {l.text}</span>
+                                    </div>
+                                ))}
+                                {preview.data.diff.lines.length > LIMIT_DIFF && (
+                                    <div className="host-more-info">
+                                        Показано {LIMIT_DIFF} из {preview.data.diff.lines.length} строк.
+                                    </div>
+                                )}
+                            </div>
+                        </div>
+                    )}
+                    {!preview && loading && (
                         <div className="loading-state">
                             <div className="spinner">↺</div>
                             <p>Подождите, мы обновляем список.. (¬ ¬ )</p>
                         </div>
                     )}
 
-                    {error && (
+                    {!preview && error && (
                         <div className="error-state">
                             <div className="error-visual">(╥﹏╥)</div>
                             <div className="error-message-box">
@@ -161,63 +418,88 @@ export const HostsModal = ({ isOpen, onClose }: Props) => {
                             </button>
                         </div>
                     )}
-                    {!loading && !error && (
+                    {!preview && !loading && !error && (
                         <div className="hosts-scroll-area">
-                            <div className="category-list">
-                                {sortedKeys.map((name) => {
-                                    const lines = data[name];
-                                    return (
-                                        <div key={name} className="category-group">
-                                            <div
-                                                className={`category-header ${expanded.includes(name) ? 'active' : ''}`}
-                                                onClick={() => toggleExpand(name)}
-                                            >
-                                                <div className="checkbox-wrapper" onClick={e => e.stopPropagation()}>
-                                                    <label className="custom-checkbox">
-                                                        <input
-                                                            type="checkbox"
-                                                            checked={selected.includes(name)}
-                                                            onChange={e => setSelected(prev => e.target.checked ? [...prev, name] : prev.filter(n => n !== name))}
-                                                        />
-                                                        <span className="checkmark"></span>
-                                                    </label>
-                                                </div>
+                            {data.map(src => (
+                                <div key={src.id} className="category-list">
+                                    <div className="hosts-source-row">
+                                        <span className="hosts-source-name">{src.name}</span>
+                                        {(health[src.id]?.problems.length ?? 0) > 0 && (
+                                            <button className="clear-hosts-btn" onClick={() => excludeBroken(src.id)}>
+                                                Исключить нерабочие ({problemsOf(src.id).size})
+                                            </button>
+                                        )}
+                                        <button className="clear-hosts-btn" onClick={() => checkHealth(src.id)} disabled={checking !== null}>
+                                            {checking === src.id ? "Проверка..." : "Проверить записи"}
+                                        </button>
+                                    </div>
+                                    {src.categories.map((cat) => {
+                                        const problems = problemsOf(src.id);
+                                        // проблемные наверх, чтобы не прятались за лимитом
+                                        const lines = [...cat.lines].sort((a, b) => Number(problems.has(b)) - Number(problems.has(a)));
+                                        const broken = lines.filter(l => problems.has(l)).length;
+                                        const name = `${src.id}/${cat.id}`;
+                                        return (
+                                            <div key={name} className="category-group">
+                                                <div
+                                                    className={`category-header ${expanded.includes(name) ? 'active' : ''}`}
+                                                    onClick={() => toggleExpand(name)}
+                                                >
+                                                    <div className="checkbox-wrapper" onClick={e => e.stopPropagation()}>
+                                                        <label className="custom-checkbox">
+                                                            <input
+                                                                type="checkbox"
+                                                                checked={selected.includes(name)}
+                                                                onChange={e => setSelected(prev => e.target.checked ? [...prev, name] : prev.filter(n => n !== name))}
+                                                            />
+                                                            <span className="checkmark"></span>
+                                                        </label>
+                                                    </div>
 
-                                                <span className="category-name">{name}</span>
+                                                    <span className="category-name">{cat.name}{broken > 0 && ` ⚠ ${broken}`}</span>
 
-                                                <div className={`arrow-icon ${expanded.includes(name) ? 'rotated' : ''}`}>
-                                                    <svg width="12" height="8" viewBox="0 0 12 8" fill="none">
-                                                        <path d="M1 1.5L6 6.5L11 1.5" stroke="currentColor" strokeWidth="2" strokeLinecap="round" strokeLinejoin="round" />
-                                                    </svg>
+                                                    <div className={`arrow-icon ${expanded.includes(name) ? 'rotated' : ''}`}>
+                                                        <svg width="12" height="8" viewBox="0 0 12 8" fill="none">
+                                                            <path d="M1 1.5L6 6.5L11 1.5" stroke="currentColor" strokeWidth="2" strokeLinecap="round" strokeLinejoin="round" />
+                                                        </svg>
+                                                    </div>
                                                 </div>
-                                            </div>
 
-                                            <div className={`category-content-wrapper ${expanded.includes(name) ? 'is-open' : ''}`}>
-                                                <div className="category-content-inner">
-                                                    <div className="category-lines">
-                                                        {lines.slice(0, LIMIT_DOMAINS).map((line, i) => {
-                                                            const [ip, ...domainParts] = line.trim().split(/\s+/);
-                                                            return (
-                                                                <div key={i} className="host-row" style={{ animationDelay: `${i * 15}ms` }}>
-                                                                    <div className="host-indicator-dot" />
-                                                                    <span className="host-ip">{ip}</span>
-                                                                    <span className="host-domain">{domainParts.join(' ')}</span>
+                                                <div className={`category-content-wrapper ${expanded.includes(name) ? 'is-open' : ''}`}>
+                                                    <div className="category-content-inner">
+                                                        <div className="category-lines">
+                                                            {lines.slice(0, LIMIT_DOMAINS).map((line, i) => {
+                                                                const [ip, ...domainParts] = line.trim().split(/\s+/);
+                                                                const issues = problems.get(line);
+                                                                const key = `${src.id}/${line}`;
+                                                                return (
+                                                                    <div
+                                                                        key={i}
+                                                                        className={`host-row ${issues ? 'broken' : ''} ${excluded.includes(key) ? 'excluded' : ''}`}
+                                                                        style={{ animationDelay: `${i * 15}ms` }}
+                                                                        title={issues?.map(p => `${p.name}: ${HEALTH_LABEL[p.status]}`).join('\n')}
+                                                                        onClick={() => toggleExcluded(key)}
+                                                                    >
+                                                                        <div className="host-indicator-dot" />
+                                                                        <span className="host-ip">{ip}</span>
+                                                                        <span className="host-domain">{domainParts.join(' ')}</span>
+                                                                    </div>
+                                                                );
+                                                            })}
+                                                            {lines.length > LIMIT_DOMAINS && (
+                                                                <div className="host-more-info">
+                                                                    Показано {LIMIT_DOMAINS} из {lines.length} записей.
+                                                                    <span className="host-limited-label">Лимит</span>
                                                                 </div>
-                                                            );
-                                                        })}
-                                                        {lines.length > LIMIT_DOMAINS && (
-                                                            <div className="host-more-info">
-                                                                Показано {LIMIT_DOMAINS} из {lines.length} записей.
-                                                                <span className="host-limited-label">Лимит</span>
-                                                            </div>
-                                                        )}
+                                                            )}
+                                                        </div>
                                                     </div>
                                                 </div>
                                             </div>
-                                        </div>
-                                    );
-                                })}
-                            </div>
+                                        );
+                                    })}
+                                </div>
+                            ))}
                             {!loading && !error && (
                                 <div className="clear-hosts-container">
                                     <button
@@ -231,24 +513,92 @@ export const HostsModal = ({ isOpen, onClose }: Props) => {
                                         Очистить записи
                                     </button>
                                     <p className="clear-hint">
-                                        Не будут очищены остальные записи, кроме dns.malw.link
+                                        Очищаются только блоки источников, свои записи и остальной hosts не трогаются
                                     </p>
+                                    <div className="hosts-source-row">
+                                        <span className="hosts-source-name">Обновлять выбранное из источников</span>
+                                        <select value={autoUpdateHours} onChange={e => setAutoUpdateHours(Number(e.target.value))}>
+                                            <option value={0}>Никогда</option>
+                                            <option value={6}>Каждые 6 ч</option>
+                                            <option value={24}>Раз в день</option>
+                                            <option value={168}>Раз в неделю</option>
+                                        </select>
+                                    </div>
                                 </div>
                             )}
+                            <div className="clear-hosts-container">
+                                <p className="clear-hint">Свои записи (отдельный блок, источники его не перезаписывают)</p>
+                                <textarea
+                                    className="hosts-custom"
+                                    value={custom}
+                                    onChange={e => setCustom(e.target.value)}
+                                    placeholder="1.2.3.4 example.com"
+                                    spellCheck={false}
+                                    rows={4}
+                                />
+                                <button className="clear-hosts-btn" onClick={saveCustom} disabled={saving}>
+                                    Сохранить свои записи
+                                </button>
+                            </div>
+                        </div>
+                    )}
+                    {!preview && !loading && (
+                        <div className="clear-hosts-container">
+                            <p className="clear-hint">Источники hosts</p>
+                            {sources.map(src => (
+                                <div key={src.id} className="hosts-source-row">
+                                    <label className="custom-checkbox">
+                                        <input type="checkbox" checked={src.enabled} onChange={() => toggleSource(src)} />
+                                        <span className="checkmark"></span>
+                                    </label>
+                                    <span className="hosts-source-name" title={src.lastError ?? src.url}>
+                                        {src.name}{src.lastError ? ' ⚠' : ''}
+                                    </span>
+                                    <button className="clear-hosts-btn" onClick={() => removeSource(src.id)} disabled={saving}>✕</button>
+                                </div>
+                            ))}
+                            <div className="hosts-source-row">
+                                <input
+                                    placeholder="Название"
+                                    value={newSource.name}
+                                    onChange={e => setNewSource(prev => ({ ...prev, name: e.target.value }))}
+                                />
+                                <input
+                                    placeholder="https://.../hosts"
+                                    value={newSource.url}
+                                    onChange={e => setNewSource(prev => ({ ...prev, url: e.target.value }))}
+                                />
+                                <button className="clear-hosts-btn" onClick={addSource} disabled={!newSource.url.trim()}>+</button>
+                            </div>
+                        </div>
+                    )}
+                    {!preview && !loading && backups.length > 0 && (
+                        <div className="clear-hosts-container">
+                            <p className="clear-hint">Резервные копии hosts</p>
+                            {backups.slice(0, 5).map(b => (
+                                <button
+                                    key={b.name}
+                                    className="clear-hosts-btn"
+                                    onClick={() => showPreview(null, b.name)}
+                                    disabled={saving}
+                                >
+                                    ↺ {b.createdAt ? new Date(b.createdAt).toLocaleString() : b.name} ({Math.ceil(b.size / 1024)} КБ)
+                                </button>
+                            ))}
                         </div>
                     )}
                 </div>
 
                 <div className="modal-footer">
-                    <button className="close-modal-btn" onClick={onClose} disabled={saving}>
-                        Отмена
+                    <button className="close-modal-btn" onClick={preview ? () => setPreview(null) : onClose} disabled={saving}>
+                        {preview ? "Назад" : "Отмена"}
                     </button>
                     <button
                         className="save-modal-btn"
-                        onClick={handleSave}
-                        disabled={loading || !!error || saving}
+                        onClick={preview ? applyPreview : handleSave}
+                        disabled={preview ? saving : (loading || !!error || saving)}
                     >
-                        {saving ? "Сохранение..." : "Применить"}
+                        {saving ? "Сохранение..." : preview ? "Записать" : "Применить"}
                     </button>
                 </div>
             </div>
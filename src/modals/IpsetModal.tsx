@@ -4,7 +4,7 @@ import { useModalAnimation } from "./useModalAnimation";
 interface Props {
     isOpen: boolean;
     onClose: () => void;
-    selectedIpset: string | null;
+    selectedIpsets: string[];
     ipsetView: 'main' | 'custom';
     setIpsetView: (v: 'main' | 'custom') => void;
     customIpsetFiles: string[];
@@ -18,7 +18,7 @@ const DESCRIPTIONS: Record<string, string> = {
     'Any': 'Использовать стандартный список ipset-all.txt для обхода',
     'None': 'Не использовать списки фильтрации. ZAPRET будет игнорировать IP-адреса.',
     'ipset-all.txt': 'Будет использован пользовательский набор из zapret/lists/ipset-all.txt',
-    'custom': 'Фильтрация по вашему индивидуальному списку адресов.'
+    'custom': 'Фильтрация по вашим спискам адресов. Можно выбрать несколько.'
 };
 
 export const IpsetModal = (p: Props) => {
@@ -36,7 +36,7 @@ export const IpsetModal = (p: Props) => {
                     {p.ipsetView === 'main' ? (
                         <div className="ipset-modal-grid">
                             <button
-                                className={`ipset-item ${p.selectedIpset === 'any' ? 'active' : ''}`}
+                                className={`ipset-item ${p.selectedIpsets.includes('any') ? 'active' : ''}`}
                                 onClick={() => { p.onModeChange('Any'); p.onClose(); }}
                                 onMouseEnter={() => p.setHoveredDesc(DESCRIPTIONS['Any'])}
                                 onMouseLeave={() => p.setHoveredDesc(null)}
@@ -45,7 +45,7 @@ export const IpsetModal = (p: Props) => {
                             </button>
 
                             <button
-                                className={`ipset-item ${p.selectedIpset === 'none' ? 'active' : ''}`}
+                                className={`ipset-item ${p.selectedIpsets.includes('none') ? 'active' : ''}`}
                                 onClick={() => { p.onModeChange('None'); p.onClose(); }}
                                 onMouseEnter={() => p.setHoveredDesc(DESCRIPTIONS['None'])}
                                 onMouseLeave={() => p.setHoveredDesc(null)}
@@ -71,8 +71,8 @@ export const IpsetModal = (p: Props) => {
 
                             <div className="ipset-file-list">
                                 <button
-                                    className={`ipset-item wide ${p.selectedIpset === 'ipset-all.txt' ? 'active' : ''}`}
-                                    onClick={() => { p.onModeChange('Custom', 'ipset-all.txt'); p.onClose(); }}
+                                    className={`ipset-item wide ${p.selectedIpsets.includes('ipset-all.txt') ? 'active' : ''}`}
+                                    onClick={() => p.onModeChange('Custom', 'ipset-all.txt')}
                                     onMouseEnter={() => p.setHoveredDesc(DESCRIPTIONS['ipset-all.txt'])}
                                     onMouseLeave={() => p.setHoveredDesc(null)}
                                 >
@@ -81,8 +81,8 @@ export const IpsetModal = (p: Props) => {
                                 {p.customIpsetFiles.map(file => (
                                     <button
                                         key={file}
-                                        className={`ipset-item wide ${p.selectedIpset === file ? 'active' : ''}`}
-                                        onClick={() => { p.onModeChange('Custom', file); p.onClose(); }}
+                                        className={`ipset-item wide ${p.selectedIpsets.includes(file) ? 'active' : ''}`}
+                                        onClick={() => p.onModeChange('Custom', file)}
                                         onMouseLeave={() => p.setHoveredDesc(null)}
                                         onMouseEnter={() => p.setHoveredDesc(DESCRIPTIONS['custom'])}
                                     >
@@ -5,29 +5,46 @@ import { useModalAnimation } from "./useModalAnimation";
 interface Props {
     isOpen: boolean;
     onClose: () => void;
-    selectedIpset: string | null;
-    onAdd: (ip: string) => void;
+    selectedIpsets: string[];
+    onAdd: (ips: string[]) => void;
 }
 
+type ServiceKind = 'none' | 'minecraft' | 'teamspeak' | 'xmpp' | 'custom';
+
+interface ResolveReport {
+    host: string;
+    targets: { host: string, port: number | null, addresses: { ip: string, ttl: number }[] }[];
+}
+
+const SERVICES: { kind: ServiceKind, label: string }[] = [
+    { kind: 'minecraft', label: 'Minecraft' },
+    { kind: 'teamspeak', label: 'TeamSpeak' },
+    { kind: 'xmpp', label: 'XMPP' },
+    { kind: 'custom', label: 'Свой SRV' },
+    { kind: 'none', label: 'Без SRV' },
+];
+
 export const ResolverModal = (p: Props) => {
     const { shouldRender, isAnimatingOut } = useModalAnimation(p.isOpen);
     const [host, setHost] = useState('');
     const [loading, setLoading] = useState(false);
+    const [service, setService] = useState<ServiceKind>('minecraft');
+    const [customSrv, setCustomSrv] = useState('_sip._udp');
 
     if (!shouldRender) return null;
 
-    const targetFile = (p.selectedIpset === 'any' || p.selectedIpset === 'none' || !p.selectedIpset)
-        ? 'ipset-all.txt'
-        : p.selectedIpset;
+    const targetFile = p.selectedIpsets.find(f => f !== 'any' && f !== 'none') || 'ipset-all.txt';
 
     const handleResolve = async () => {
         if (!host) return;
         setLoading(true);
         try {
             const [domain, portStr] = host.split(':');
-            const port = portStr ? parseInt(portStr) : 25565;
-            const ip = await invoke<string>('resolve_host', { host: domain, port });
-            p.onAdd(ip);
+            const port = portStr ? parseInt(portStr) : null;
+            const hint = service === 'custom' ? { kind: service, name: customSrv } : { kind: service };
+            const report = await invoke<ResolveReport>('resolve_host', { host: domain, service: hint, port });
+            const ips = [...new Set(report.targets.flatMap(t => t.addresses.map(a => a.ip)))];
+            p.onAdd(ips);
             setHost('');
             p.onClose();
         } catch (e) {
@@ -70,6 +87,25 @@ export const ResolverModal = (p: Props) => {
                                 autoFocus
                             />
                         </div>
+                        <div className="input-group-v2">
+                            <select
+                                className="resolver-input"
+                                value={service}
+                                onChange={e => setService(e.target.value as ServiceKind)}
+                                disabled={loading}
+                            >
+                                {SERVICES.map(s => <option key={s.kind} value={s.kind}>{s.label}</option>)}
+                            </select>
+                            {service === 'custom' && (
+                                <input
+                                    className="resolver-input"
+                                    placeholder="_sip._udp"
+                                    value={customSrv}
+                                    onChange={e => setCustomSrv(e.target.value)}
+                                    disabled={loading}
+                                />
+                            )}
+                        </div>
                         <p className="resolver-desc">
                             Введите буквенный адрес сервес для получения айпи и внедрение его в IPSET-ы. Рекомендуется использовать при проблемах с подключением к серверам.
                         </p>
@@ -26,6 +26,263 @@ const SettingItem = ({ label, description, emoji, enabled, onToggle }: SettingIt
     </div>
 );
 
+type DnsMode = 'system' | 'udp' | 'doh' | 'dot';
+type DnsProvider = 'cloudflare' | 'google' | 'quad9' | 'custom';
+
+interface DnsSettings {
+    mode: DnsMode;
+    provider: DnsProvider;
+    servers: string[];
+    tlsName: string | null;
+}
+
+const DNS_MODES: { mode: DnsMode, label: string }[] = [
+    { mode: 'system', label: 'Системный' },
+    { mode: 'udp', label: 'UDP' },
+    { mode: 'doh', label: 'DNS-over-HTTPS' },
+    { mode: 'dot', label: 'DNS-over-TLS' },
+];
+
+type NetworkChangeAction = 'none' | 'restart' | 'healthCheck';
+
+const NETWORK_CHANGE_ACTIONS: { action: NetworkChangeAction, label: string }[] = [
+    { action: 'none', label: 'Ничего не делать' },
+    { action: 'restart', label: 'Перезапускать сборку' },
+    { action: 'healthCheck', label: 'Проверять и перезапускать при сбое' },
+];
+
+const NetworkChangeSection = () => {
+    const [action, setAction] = useState<NetworkChangeAction | null>(null);
+
+    useEffect(() => {
+        invoke<NetworkChangeAction>('get_network_change_action').then(setAction).catch(console.error);
+    }, []);
+
+    if (!action) return null;
+
+    const change = async (next: NetworkChangeAction) => {
+        const prev = action;
+        setAction(next);
+        try {
+            await invoke('set_network_change_action', { action: next });
+        } catch (e) {
+            setAction(prev);
+            log("network change action err " + e);
+            notify("Случилась непредвиденная ошибка.", "error");
+        }
+    };
+
+    return (
+        <div className={`settings-card ${action !== 'none' ? 'active' : ''}`}>
+            <div className="settings-info">
+                <span className="settings-emoji">📶</span>
+                <div className="settings-text">
+                    <span className="settings-label">Смена сети и выход из сна</span>
+                    <span className="settings-desc">Что делать с запущенной сборкой, когда сеть переключилась.</span>
+                    <select className="resolver-input" value={action} onChange={e => change(e.target.value as NetworkChangeAction)}>
+                        {NETWORK_CHANGE_ACTIONS.map(a => <option key={a.action} value={a.action}>{a.label}</option>)}
+                    </select>
+                </div>
+            </div>
+        </div>
+    );
+};
+
+const DnsSection = () => {
+    const [dns, setDns] = useState<DnsSettings | null>(null);
+    const [saving, setSaving] = useState(false);
+
+    useEffect(() => {
+        invoke<DnsSettings>('get_dns_settings').then(setDns).catch(console.error);
+    }, []);
+
+    if (!dns) return null;
+
+    const save = async () => {
+        setSaving(true);
+        try {
+            const ips = await invoke<string[]>('set_dns_settings', { dns });
+            log(`dns ${dns.mode}/${dns.provider}: github.com -> ${ips.join(', ')}`);
+            notify("DNS сохранён!", "success");
+        } catch (e) {
+            log("dns err " + e);
+            notify(`DNS не работает: ${e}`, "error");
+        } finally {
+            setSaving(false);
+        }
+    };
+
+    return (
+        <div className="settings-card">
+            <div className="settings-info">
+                <span className="settings-emoji">🧭</span>
+                <div className="settings-text">
+                    <span className="settings-label">DNS для Zust</span>
+                    <span className="settings-desc">Через него идут резолвер, проверка прокси и загрузки обновлений.</span>
+                    <select className="resolver-input" value={dns.mode} onChange={e => setDns({ ...dns, mode: e.target.value as DnsMode })}>
+                        {DNS_MODES.map(m => <option key={m.mode} value={m.mode}>{m.label}</option>)}
+                    </select>
+                    {dns.mode !== 'system' && (
+                        <select className="resolver-input" value={dns.provider} onChange={e => setDns({ ...dns, provider: e.target.value as DnsProvider })}>
+                            <option value="cloudflare">Cloudflare</option>
+                            <option value="google">Google</option>
+                            <option value="quad9">Quad9</option>
+                            <option value="custom">Свой сервер</option>
+                        </select>
+                    )}
+                    {dns.mode !== 'system' && dns.provider === 'custom' && (
+                        <>
+                            <input
+                                className="resolver-input"
+                                placeholder="1.1.1.1, 1.0.0.1"
+                                value={dns.servers.join(', ')}
+                                onChange={e => setDns({ ...dns, servers: e.target.value.split(',').map(s => s.trim()).filter(Boolean) })}
+                            />
+                            {dns.mode !== 'udp' && (
+                                <input
+                                    className="resolver-input"
+                                    placeholder="cloudflare-dns.com"
+                                    value={dns.tlsName || ''}
+                                    onChange={e => setDns({ ...dns, tlsName: e.target.value || null })}
+                                />
+                            )}
+                        </>
+                    )}
+                </div>
+            </div>
+            <button className="save-modal-btn" onClick={save} disabled={saving}>
+                {saving ? <span className="spinner">↻</span> : "Проверить и сохранить"}
+            </button>
+        </div>
+    );
+};
+
+interface DnsOverride {
+    domain: string;
+    ips: string[];
+}
+
+interface ForwarderSettings {
+    enabled: boolean;
+    listen: string;
+    upstream: DnsSettings;
+    overrides: DnsOverride[];
+}
+
+interface ForwarderStatus {
+    running: boolean;
+    listen: string | null;
+    cached: number;
+    hits: number;
+    misses: number;
+}
+
+// "example.com 1.2.3.4 ::1", домен без IP - блокировка
+const parseOverrides = (text: string): DnsOverride[] =>
+    text.split('\n')
+        .map(l => l.trim().split(/\s+/).filter(Boolean))
+        .filter(parts => parts.length > 0)
+        .map(([domain, ...ips]) => ({ domain, ips }));
+
+const formatOverrides = (overrides: DnsOverride[]) =>
+    overrides.map(o => [o.domain, ...o.ips].join(' ')).join('\n');
+
+const ForwarderSection = () => {
+    const [forwarder, setForwarder] = useState<ForwarderSettings | null>(null);
+    const [overrides, setOverrides] = useState('');
+    const [status, setStatus] = useState<ForwarderStatus | null>(null);
+    const [saving, setSaving] = useState(false);
+
+    useEffect(() => {
+        invoke<ForwarderSettings>('get_forwarder_settings').then(f => {
+            setForwarder(f);
+            setOverrides(formatOverrides(f.overrides));
+        }).catch(console.error);
+        invoke<ForwarderStatus>('get_forwarder_status').then(setStatus).catch(console.error);
+    }, []);
+
+    if (!forwarder) return null;
+    const upstream = forwarder.upstream;
+
+    const save = async () => {
+        setSaving(true);
+        try {
+            const next = { ...forwarder, overrides: parseOverrides(overrides) };
+            const res = await invoke<ForwarderStatus>('set_forwarder_settings', { forwarder: next });
+            setStatus(res);
+            log(`dns forwarder ${next.enabled ? 'вкл' : 'выкл'} ${next.listen}`);
+            notify("Форвардер сохранён!", "success");
+        } catch (e) {
+            log("dns forwarder err " + e);
+            notify(`Форвардер: ${e}`, "error");
+        } finally {
+            setSaving(false);
+        }
+    };
+
+    return (
+        <div className={`settings-card ${forwarder.enabled ? 'active' : ''}`}>
+            <div className="settings-info">
+                <span className="settings-emoji">🛰️</span>
+                <div className="settings-text">
+                    <span className="settings-label">Локальный DNS</span>
+                    <span className="settings-desc">
+                        Запускается вместе с обходом. Укажите его адрес как DNS в настройках сети.
+                        {status?.running && ` Работает на ${status.listen}, в кеше ${status.cached}, попаданий ${status.hits}/${status.hits + status.misses}.`}
+                    </span>
+                    <label className="settings-desc">
+                        <input
+                            type="checkbox"
+                            checked={forwarder.enabled}
+                            onChange={e => setForwarder({ ...forwarder, enabled: e.target.checked })}
+                        /> Включён
+                    </label>
+                    <input
+                        className="resolver-input"
+                        placeholder="127.0.0.1:53"
+                        value={forwarder.listen}
+                        onChange={e => setForwarder({ ...forwarder, listen: e.target.value })}
+                    />
+                    <select className="resolver-input" value={upstream.mode} onChange={e => setForwarder({ ...forwarder, upstream: { ...upstream, mode: e.target.value as DnsMode } })}>
+                        {DNS_MODES.filter(m => m.mode === 'doh' || m.mode === 'dot').map(m => <option key={m.mode} value={m.mode}>{m.label}</option>)}
+                    </select>
+                    <select className="resolver-input" value={upstream.provider} onChange={e => setForwarder({ ...forwarder, upstream: { ...upstream, provider: e.target.value as DnsProvider } })}>
+                        <option value="cloudflare">Cloudflare</option>
+                        <option value="google">Google</option>
+                        <option value="quad9">Quad9</option>
+                        <option value="custom">Свой сервер</option>
+                    </select>
+                    {upstream.provider === 'custom' && (
+                        <>
+                            <input
+                                className="resolver-input"
+                                placeholder="1.1.1.1, 1.0.0.1"
+                                value={upstream.servers.join(', ')}
+                                onChange={e => setForwarder({ ...forwarder, upstream: { ...upstream, servers: e.target.value.split(',').map(s => s.trim()).filter(Boolean) } })}
+                            />
+                            <input
+                                className="resolver-input"
+                                placeholder="cloudflare-dns.com"
+                                value={upstream.tlsName || ''}
+                                onChange={e => setForwarder({ ...forwarder, upstream: { ...upstream, tlsName: e.target.value || null } })}
+                            />
+                        </>
+                    )}
+                    <textarea
+                        className="resolver-input"
+                        placeholder={"example.com 1.2.3.4\nads.example.com"}
+                        value={overrides}
+                        onChange={e => setOverrides(e.target.value)}
+                    />
+                </div>
+            </div>
+            <button className="save-modal-btn" onClick={save} disabled={saving}>
+                {saving ? <span className="spinner">↻</span> : "Сохранить"}
+            </button>
+        </div>
+    );
+};
+
 export const SettingsPage = () => {
     const [settings, setSettings] = useState({
         notifications: true,
@@ -52,7 +309,9 @@ export const SettingsPage = () => {
             clearTimeout(cooldown.current)
         }
         try {
-            await invoke('save_settings', { settings: newSettings });
+            // DNS и прочее сохраняется отдельно, поэтому берём свежие настройки, а не копию со старта страницы
+            const fresh = await invoke<typeof settings>('load_settings');
+            await invoke('save_settings', { settings: { ...fresh, [key]: newValue } });
             switch (key) {
                 case 'animationDisabled':
                     document.body.classList.toggle('no-animations', newValue);
@@ -72,12 +331,7 @@ export const SettingsPage = () => {
                         const list = await invoke<string[]>('get_list_strategies');
                         const idx = list.indexOf(currentStrat) + 1;
                         if (idx > 0) {
-                            await invoke('start_service', {
-                                args: {
-                                    index: idx,
-                                    ipsetConfig: localStorage.getItem('selected_ipset') || "ipset-all.txt"
-                                }
-                            });
+                            await invoke('start_service', { args: { index: idx } });
                         }
                     }
                     notify("Сборка перезапущена!", "success");
@@ -133,6 +387,10 @@ export const SettingsPage = () => {
                     enabled={settings.gameFilter}
                     onToggle={() => toggle('gameFilter')}
                 />
+                <NetworkChangeSection />
+                <h2 className="section-title" style={{ marginTop: '20px' }}>DNS</h2>
+                <DnsSection />
+                <ForwarderSection />
             </div>
         </div>
     );